@@ -1,9 +1,32 @@
-use core::marker::PhantomData;
+use core::{
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
+};
 
+use async_trait::async_trait;
+use bb8::{ManageConnection, Pool, PooledConnection, RunError};
 use fbthrift_transport::{
-    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+#[cfg(feature = "tls")]
+use nebula_client::v3::tls::TlsConfig;
+use nebula_client::{
+    v3::{
+        graph::GraphInterceptor, Connector, GraphClient, GraphSession, ThriftProtocol, TraceConfig,
+    },
+    Version, VersionV3,
 };
-use nebula_client::Version;
+use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
 
 //
 #[derive(Debug, Clone)]
@@ -12,7 +35,16 @@ pub struct GraphClientConfiguration {
     pub port: u16,
     pub username: String,
     pub password: String,
+    /// Selected at connect time, and again at checkout when a borrower ran `USE` on another one.
+    /// Without bb8's `test_on_check_out`, such sessions are dropped when returned instead.
     pub space: Option<String>,
+    pub trace_config: TraceConfig,
+    pub protocol: ThriftProtocol,
+    /// Checked by `is_valid`; without it, only sessions flagged by a failed statement are dropped.
+    pub validation: Option<GraphValidation>,
+    /// Used by `new_tls_graph_connection_manager`; other managers refuse to connect when set.
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsConfig>,
 }
 
 impl GraphClientConfiguration {
@@ -29,8 +61,34 @@ impl GraphClientConfiguration {
             username,
             password,
             space,
+            trace_config: TraceConfig::default(),
+            protocol: ThriftProtocol::default(),
+            validation: None,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
+
+    pub fn with_trace_config(mut self, trace_config: TraceConfig) -> Self {
+        self.trace_config = trace_config;
+        self
+    }
+
+    pub fn with_protocol(mut self, protocol: ThriftProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn with_validation(mut self, validation: GraphValidation) -> Self {
+        self.validation = Some(validation);
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
 }
 
 impl GraphClientConfiguration {
@@ -40,18 +98,248 @@ impl GraphClientConfiguration {
 }
 
 //
-#[derive(Clone)]
-pub struct GraphConnectionManager<S, SLEEP, H, V>
+/// The statement `is_valid` runs before bb8 hands out a session.
+///
+/// Sessions that fail it, time out, or get `E_SESSION_INVALID` (e.g. expired on
+/// the server, or graphd restarted) are dropped and replaced by the pool.
+#[derive(Debug, Clone)]
+pub struct GraphValidation {
+    pub query: String,
+    pub timeout: Duration,
+    /// Only sessions idle for at least this long are checked, `None` checks every checkout.
+    pub idle_threshold: Option<Duration>,
+}
+
+impl Default for GraphValidation {
+    fn default() -> Self {
+        Self {
+            query: "YIELD 1".to_owned(),
+            timeout: Duration::from_secs(5),
+            idle_threshold: None,
+        }
+    }
+}
+
+impl GraphValidation {
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = query.into();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_idle_threshold(mut self, idle_threshold: Duration) -> Self {
+        self.idle_threshold = Some(idle_threshold);
+        self
+    }
+
+    pub(crate) async fn check<C, H>(
+        &self,
+        session: &mut GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>,
+    ) -> Result<(), IoError>
+    where
+        C: Connector,
+        H: ResponseHandler + Send + Sync + 'static + Unpin,
+    {
+        if let Some(idle_threshold) = self.idle_threshold {
+            if session.last_used().elapsed() < idle_threshold {
+                return Ok(());
+            }
+        }
+
+        let stmt = self.query.as_bytes().to_vec();
+        let res =
+            async_sleep::timeout::<C::Sleep, _>(self.timeout, Box::pin(session.execute(&stmt)))
+                .await
+                .map_err(|_| IoError::new(IoErrorKind::TimedOut, "validation query timed out"))?
+                .map_err(IoError::other)?;
+
+        // `E_SESSION_INVALID` and `E_SESSION_TIMEOUT` also flag the session for `has_broken`.
+        if res.error_code != ErrorCode::SUCCEEDED {
+            return Err(IoError::other(format!(
+                "validation query failed: {}",
+                res.error_code
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Pings the idle sessions of `pool` one at a time, so the others stay available,
+    /// and passes the ones that fail to `failed` before they go back.
+    pub(crate) async fn ping_idle<M, C, H>(&self, pool: &Pool<M>, failed: impl Fn(i64))
+    where
+        M: ManageConnection,
+        M::Connection: BorrowMut<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>>,
+        C: Connector,
+        H: ResponseHandler + Send + Sync + 'static + Unpin,
+    {
+        let started = Instant::now();
+        let mut pinged = BTreeSet::new();
+        for _ in 0..pool.state().idle_connections {
+            if pool.state().idle_connections == 0 {
+                break;
+            }
+            let mut conn = match pool.get().await {
+                Ok(conn) => conn,
+                Err(_) => break,
+            };
+
+            let session: &mut GraphSession<_> = (*conn).borrow_mut();
+            // With `QueueStrategy::Lifo`, the session just pinged is handed out again.
+            if !pinged.insert(session.session_id()) {
+                break;
+            }
+            // Already checked by `is_valid`, or used by a borrower since.
+            if session.last_used() >= started {
+                continue;
+            }
+
+            if self.check::<C, H>(session).await.is_err() && !session.is_close_required() {
+                failed(session.session_id());
+            }
+        }
+    }
+}
+
+//
+/// Opens sessions through `C`, so the pool works on any runtime with a [`Connector`].
+///
+/// bb8 spawns its background tasks with tokio. On async-std or smol, build and
+/// use the pool inside `async_compat::Compat`.
+///
+/// Keep a clone of the manager to [`shutdown`](Self::shutdown) the pool; clones share the sessions.
+pub struct GraphConnectionManager<C, H, V>
 where
     H: ResponseHandler,
     V: Version,
 {
     pub client_configuration: GraphClientConfiguration,
     pub transport_configuration: AsyncTransportConfiguration<H>,
-    phantom: PhantomData<(S, SLEEP, V)>,
+    pub connector: C,
+    /// Attached to every session the pool opens, in this order.
+    pub interceptors: Vec<Arc<dyn GraphInterceptor>>,
+    sessions: Arc<Mutex<Sessions>>,
+    phantom: PhantomData<V>,
 }
 
-impl<S, SLEEP, H, V> GraphConnectionManager<S, SLEEP, H, V>
+impl<C, H, V> Clone for GraphConnectionManager<C, H, V>
+where
+    C: Clone,
+    H: ResponseHandler,
+    V: Version,
+{
+    fn clone(&self) -> Self {
+        Self {
+            client_configuration: self.client_configuration.clone(),
+            transport_configuration: self.transport_configuration.clone(),
+            connector: self.connector.clone(),
+            interceptors: self.interceptors.clone(),
+            sessions: self.sessions.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Dropped sessions waiting for a signout; past this, graphd expires them after `session_idle_timeout_secs`.
+pub(crate) const MAX_DISCARDED: usize = 1024;
+
+/// The sessions a manager opened and has not signed out.
+#[derive(Default)]
+struct Sessions {
+    live: BTreeSet<i64>,
+    /// Dropped by the pool, signed out over the next connection.
+    discarded: Vec<i64>,
+    /// Failed a keepalive ping without being flagged by the client, dropped by `has_broken`.
+    failed: BTreeSet<i64>,
+    /// Checked out by `get_for_space`, with the space they had before.
+    switched: BTreeMap<i64, Option<String>>,
+    /// Returned in another space, switched back by the next `is_valid`.
+    reset: BTreeMap<i64, String>,
+    /// bb8 runs `is_valid` at checkout (`test_on_check_out`), so it can switch sessions back.
+    validated: bool,
+    closed: bool,
+}
+
+//
+/// A pooled session. However bb8 drops it, as broken, invalid, expired or idle,
+/// the session is queued for a signout.
+pub struct PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    session: GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>,
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl<C, H> Deref for PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Target = GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl<C, H> DerefMut for PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.session
+    }
+}
+
+impl<C, H> Borrow<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>> for PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn borrow(&self) -> &GraphSession<AsyncTransport<C::Stream, C::Sleep, H>> {
+        &self.session
+    }
+}
+
+impl<C, H> BorrowMut<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>>
+    for PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn borrow_mut(&mut self) -> &mut GraphSession<AsyncTransport<C::Stream, C::Sleep, H>> {
+        &mut self.session
+    }
+}
+
+impl<C, H> Drop for PooledGraphSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn drop(&mut self) {
+        let session_id = self.session.session_id();
+        let mut sessions = self.sessions.lock().expect("not poisoned");
+        sessions.switched.remove(&session_id);
+        sessions.reset.remove(&session_id);
+        // Sessions graphd dropped need no signout.
+        if sessions.live.remove(&session_id)
+            && !self.session.is_session_invalid()
+            && (sessions.closed || sessions.discarded.len() < MAX_DISCARDED)
+        {
+            sessions.discarded.push(session_id);
+        }
+    }
+}
+
+impl<C, H, V> GraphConnectionManager<C, H, V>
 where
     H: ResponseHandler + Send + Sync + 'static + Unpin,
     V: Version,
@@ -59,11 +347,374 @@ where
     pub fn new(
         client_configuration: GraphClientConfiguration,
         transport_configuration: AsyncTransportConfiguration<H>,
+        connector: C,
     ) -> Self {
         Self {
             client_configuration,
             transport_configuration,
+            connector,
+            interceptors: vec![],
+            sessions: Default::default(),
             phantom: PhantomData,
         }
     }
+
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn GraphInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Opened by the pool and not signed out yet.
+    pub fn session_ids(&self) -> Vec<i64> {
+        self.sessions().live.iter().copied().collect()
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, Sessions> {
+        self.sessions.lock().expect("not poisoned")
+    }
+}
+
+//
+impl<C, H> GraphConnectionManager<C, H, VersionV3>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    async fn client(&self) -> Result<GraphClient<AsyncTransport<C::Stream, C::Sleep, H>>, IoError> {
+        #[cfg(feature = "tls")]
+        if self.client_configuration.tls.is_some() && !self.connector.is_tls() {
+            return Err(IoError::new(
+                std::io::ErrorKind::InvalidInput,
+                "tls is configured but the connector is plain TCP",
+            ));
+        }
+
+        let addr = self.client_configuration.tcp_connect_addr();
+        let transport = self
+            .connector
+            .transport(&addr, self.transport_configuration.clone())
+            .await?;
+
+        Ok(
+            GraphClient::new_with_protocol(transport, self.client_configuration.protocol)
+                .with_addr(addr)
+                .with_trace_config(self.client_configuration.trace_config.clone())
+                .with_interceptors(self.interceptors.iter().cloned()),
+        )
+    }
+
+    async fn get_async_connection(&self) -> Result<PooledGraphSession<C, H>, IoError> {
+        if self.sessions().closed {
+            return Err(IoError::other("pool is shut down"));
+        }
+
+        let client = self.client().await?;
+
+        // Best effort, graphd drops them after `session_idle_timeout_secs` anyway.
+        let discarded = core::mem::take(&mut self.sessions().discarded);
+        let _ = signout_all::<C, H>(&client, discarded).await;
+
+        let mut session = client
+            .authenticate(
+                &self.client_configuration.username.as_bytes().to_vec(),
+                &self.client_configuration.password.as_bytes().to_vec(),
+            )
+            .await
+            .map_err(IoError::other)?;
+
+        if let Some(ref space) = self.client_configuration.space {
+            if let Err(err) = use_space::<C, H>(&mut session, space).await {
+                let _ = session.signout().await;
+                return Err(err);
+            }
+        }
+
+        let closed = {
+            let mut sessions = self.sessions();
+            if !sessions.closed {
+                sessions.live.insert(session.session_id());
+            }
+            sessions.closed
+        };
+        if closed {
+            let _ = session.signout().await;
+            return Err(IoError::other("pool is shut down"));
+        }
+
+        Ok(PooledGraphSession {
+            session,
+            sessions: self.sessions.clone(),
+        })
+    }
+
+    /// Refuses new connections and checkouts, waits up to `drain_timeout` for
+    /// checkouts to come back, then signs out every session the pool opened.
+    ///
+    /// Sessions still checked out after the timeout are counted as in flight and
+    /// stay signed in; the pool drops them when they come back.
+    pub async fn shutdown(
+        &self,
+        pool: Pool<Self>,
+        drain_timeout: Duration,
+    ) -> Result<(), GraphShutdownError> {
+        self.sessions().closed = true;
+
+        let deadline = Instant::now() + drain_timeout;
+        loop {
+            let state = pool.state();
+            if state.connections == state.idle_connections || Instant::now() >= deadline {
+                break;
+            }
+            async_sleep::sleep::<C::Sleep>(Duration::from_millis(10)).await;
+        }
+
+        // Takes the idle sessions out of the pool, `is_valid` or `has_broken` drops them.
+        while pool.state().idle_connections > 0 {
+            let _ = async_sleep::timeout::<C::Sleep, _>(
+                Duration::from_millis(10),
+                Box::pin(pool.get()),
+            )
+            .await;
+        }
+        drop(pool);
+
+        let in_flight = u32::try_from(self.sessions().live.len()).unwrap_or(u32::MAX);
+        let failed = self.signout_discarded().await;
+
+        if in_flight == 0 && failed.is_empty() {
+            Ok(())
+        } else {
+            Err(GraphShutdownError { in_flight, failed })
+        }
+    }
+
+    /// Signs out the discarded sessions, returning the ones that failed.
+    async fn signout_discarded(&self) -> Vec<(i64, IoError)> {
+        let session_ids = core::mem::take(&mut self.sessions().discarded);
+        if session_ids.is_empty() {
+            return vec![];
+        }
+
+        match self.client().await {
+            Ok(client) => signout_all::<C, H>(&client, session_ids).await,
+            Err(err) => session_ids
+                .into_iter()
+                .map(|session_id| (session_id, IoError::new(err.kind(), err.to_string())))
+                .collect(),
+        }
+    }
+
+    /// A session of `pool` in `space`, so one pool can serve several.
+    ///
+    /// Once returned, the session is switched back to the space it had before at its
+    /// next checkout. Sessions that had no space, or all of them without bb8's
+    /// `test_on_check_out`, are dropped instead and the pool opens new ones.
+    pub async fn get_for_space<'a>(
+        &self,
+        pool: &'a Pool<Self>,
+        space: &str,
+    ) -> Result<PooledConnection<'a, Self>, RunError<IoError>> {
+        let mut conn = pool.get().await?;
+        if conn.space_name() == Some(space.as_bytes()) {
+            return Ok(conn);
+        }
+
+        self.sessions().switched.insert(
+            conn.session_id(),
+            conn.space_name()
+                .map(|x| String::from_utf8_lossy(x).into_owned()),
+        );
+        use_space::<C, H>(&mut conn, space)
+            .await
+            .map_err(RunError::User)?;
+        Ok(conn)
+    }
+
+    /// Every `interval`, pings the idle sessions unused for as long, so graphd does
+    /// not expire them. Sessions that fail the ping are dropped and the pool opens
+    /// new ones. Uses the configured validation query and timeout, or the defaults.
+    ///
+    /// Runs until [`shutdown`](Self::shutdown); spawn it on any runtime, e.g.
+    /// `tokio::spawn(manager.clone().keepalive(pool.clone(), interval))`.
+    pub async fn keepalive(self, pool: Pool<Self>, interval: Duration) {
+        let validation = GraphValidation {
+            idle_threshold: Some(interval),
+            ..self
+                .client_configuration
+                .validation
+                .clone()
+                .unwrap_or_default()
+        };
+
+        loop {
+            async_sleep::sleep::<C::Sleep>(interval).await;
+            if self.sessions().closed {
+                return;
+            }
+
+            // Best effort, graphd drops them after `session_idle_timeout_secs` anyway.
+            let _ = self.signout_discarded().await;
+
+            validation
+                .ping_idle::<_, C, H>(&pool, |session_id| {
+                    self.sessions().failed.insert(session_id);
+                })
+                .await;
+        }
+    }
+}
+
+/// Signs out each session, returning the ones that failed.
+pub(crate) async fn signout_all<C, H>(
+    client: &GraphClient<AsyncTransport<C::Stream, C::Sleep, H>>,
+    session_ids: impl IntoIterator<Item = i64>,
+) -> Vec<(i64, IoError)>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    let mut failed = vec![];
+    for session_id in session_ids {
+        if let Err(err) = client.signout(session_id).await {
+            failed.push((session_id, IoError::other(err)));
+        }
+    }
+    failed
+}
+
+//
+#[derive(Debug)]
+pub struct GraphShutdownError {
+    /// Checkouts that had not come back when the drain timed out, left signed in.
+    pub in_flight: u32,
+    /// Sessions that could not be signed out.
+    pub failed: Vec<(i64, IoError)>,
+}
+
+impl fmt::Display for GraphShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} checkouts still in flight, {} signouts failed",
+            self.in_flight,
+            self.failed.len()
+        )?;
+        if let Some((session_id, err)) = self.failed.first() {
+            write!(f, ", first for session {session_id}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GraphShutdownError {}
+
+#[async_trait]
+impl<C, H> bb8::ManageConnection for GraphConnectionManager<C, H, VersionV3>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Connection = PooledGraphSession<C, H>;
+    type Error = IoError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let session = self.get_async_connection().await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(self.client_configuration.tcp_connect_addr());
+
+        Ok(session)
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        let reset = {
+            let mut sessions = self.sessions();
+            if sessions.closed {
+                return Err(IoError::other("pool is shut down"));
+            }
+            sessions.validated = true;
+            sessions.reset.remove(&conn.session_id())
+        };
+
+        if let Some(ref validation) = self.client_configuration.validation {
+            validation.check::<C, H>(conn).await?;
+        }
+
+        // Undoes a `USE` or `get_for_space` of the previous borrower.
+        if let Some(space) = reset.or_else(|| self.client_configuration.space.clone()) {
+            use_space::<C, H>(conn, &space).await?;
+        }
+
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        let broken = {
+            let mut sessions = self.sessions();
+            let session_id = conn.session_id();
+
+            // The space the session must be back in, `Some(None)` for none.
+            let expected = match sessions.switched.remove(&session_id) {
+                Some(space) => Some(space),
+                None => self.client_configuration.space.clone().map(Some),
+            };
+            let mut misplaced = false;
+            if let Some(space) = expected {
+                if conn.space_name() != space.as_deref().map(str::as_bytes) {
+                    match space {
+                        Some(space) if sessions.validated => {
+                            sessions.reset.insert(session_id, space);
+                        }
+                        // graphd has no `USE` back to no space.
+                        _ => misplaced = true,
+                    }
+                }
+            }
+
+            sessions.failed.remove(&session_id)
+                || misplaced
+                || sessions.closed
+                || conn.is_close_required()
+        };
+
+        #[cfg(feature = "metrics")]
+        if broken {
+            crate::metrics::broken();
+        }
+
+        broken
+    }
+}
+
+/// Runs ``USE `{space}` ``, unless the last response was already in it.
+pub(crate) async fn use_space<C, H>(
+    session: &mut GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>,
+    space: &str,
+) -> Result<(), IoError>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    if session.space_name() == Some(space.as_bytes()) {
+        return Ok(());
+    }
+    if space.is_empty() || space.contains('`') {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            format!("invalid space name {space:?}"),
+        ));
+    }
+
+    let res = session
+        .execute(&format!("USE `{space}`").as_bytes().to_vec())
+        .await
+        .map_err(IoError::other)?;
+    if res.error_code != ErrorCode::SUCCEEDED {
+        return Err(IoError::other(format!(
+            "USE `{space}` failed: {}",
+            res.error_code
+        )));
+    }
+
+    Ok(())
 }
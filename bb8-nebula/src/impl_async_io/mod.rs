@@ -0,0 +1,5 @@
+pub mod v3;
+
+//
+pub use fbthrift_transport::impl_async_io::{AsyncIoSleep, AsyncIoTcpStream};
+pub use nebula_client::v3::connector::AsyncIoConnector;
@@ -0,0 +1,116 @@
+#[cfg(feature = "tls")]
+use std::io::Error as IoError;
+
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+};
+#[cfg(feature = "tls")]
+use nebula_client::v3::tls::TlsConnector;
+use nebula_client::{v3::connector::AsyncIoConnector, VersionV3};
+
+use crate::{
+    graph::{GraphClientConfiguration, GraphConnectionManager},
+    session_pool::GraphSessionManager,
+    shared_session::SharedSessionManager,
+};
+
+//
+pub fn new_graph_connection_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> GraphConnectionManager<AsyncIoConnector, H, VersionV3>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    GraphConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        AsyncIoConnector,
+    )
+}
+
+pub fn new_graph_session_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> GraphSessionManager<AsyncIoConnector, H>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    GraphSessionManager::new(
+        client_configuration,
+        transport_configuration,
+        AsyncIoConnector,
+    )
+}
+
+pub fn new_shared_session_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> SharedSessionManager<AsyncIoConnector, H>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    SharedSessionManager::new(
+        client_configuration,
+        transport_configuration,
+        AsyncIoConnector,
+    )
+}
+
+/// Connects with `client_configuration.tls`, or the default `TlsConfig` when it is `None`.
+#[cfg(feature = "tls")]
+pub fn new_tls_graph_connection_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> Result<GraphConnectionManager<TlsConnector<AsyncIoConnector>, H, VersionV3>, IoError>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    let tls = client_configuration.tls.clone().unwrap_or_default();
+    let connector = TlsConnector::new(AsyncIoConnector, &tls)?;
+
+    Ok(GraphConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        connector,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use async_compat::Compat;
+    use nebula_client::v3::{
+        mock::{MockGraph, Reply},
+        GraphQuery as _, GraphTransportResponseHandler,
+    };
+
+    #[test]
+    fn with_mock() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on("SHOW HOSTS", Reply::Response(Default::default()));
+
+        // bb8 spawns with tokio; Compat provides it outside a tokio runtime.
+        async_io::block_on(Compat::new(async {
+            let server = mock.serve().await?;
+
+            let manager = new_graph_connection_manager(
+                GraphClientConfiguration::new(
+                    server.addr().ip().to_string(),
+                    server.addr().port(),
+                    "root".to_owned(),
+                    "nebula".to_owned(),
+                    None,
+                ),
+                AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+            );
+            let pool = bb8::Pool::builder().max_size(1).build(manager).await?;
+
+            pool.get().await?.query(&b"SHOW HOSTS".to_vec()).await?;
+            mock.assert_received("SHOW HOSTS");
+
+            Ok(())
+        }))
+    }
+}
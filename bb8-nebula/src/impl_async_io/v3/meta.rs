@@ -0,0 +1,21 @@
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+};
+use nebula_client::{v3::connector::AsyncIoConnector, VersionV3};
+
+use crate::meta::{MetaClientConfiguration, MetaConnectionManager};
+
+//
+pub fn new_meta_connection_manager<H>(
+    client_configuration: MetaClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> MetaConnectionManager<AsyncIoConnector, H, VersionV3>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    MetaConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        AsyncIoConnector,
+    )
+}
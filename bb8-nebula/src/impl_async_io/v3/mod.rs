@@ -0,0 +1,6 @@
+#[cfg(feature = "graph")]
+pub mod graph;
+#[cfg(feature = "meta")]
+pub mod meta;
+#[cfg(feature = "storage")]
+pub mod storage;
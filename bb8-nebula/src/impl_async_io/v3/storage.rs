@@ -0,0 +1,26 @@
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+};
+use nebula_client::{v3::connector::AsyncIoConnector, VersionV3};
+
+use crate::storage::{StorageConnectionManager, StoragePools};
+
+//
+pub fn new_storage_connection_manager<H>(
+    addr: String,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> StorageConnectionManager<AsyncIoConnector, H, VersionV3>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    StorageConnectionManager::new(addr, transport_configuration, AsyncIoConnector)
+}
+
+pub fn new_storage_pools<H>(
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> StoragePools<AsyncIoConnector, H>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    StoragePools::new(transport_configuration, AsyncIoConnector)
+}
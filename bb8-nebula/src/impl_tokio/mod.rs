@@ -1,5 +1,5 @@
-//
 pub mod v3;
 
 //
 pub use fbthrift_transport::impl_tokio::{TokioSleep, TokioTcpStream};
+pub use nebula_client::v3::connector::TokioConnector;
@@ -1,81 +1,886 @@
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+#[cfg(feature = "tls")]
+use std::io::Error as IoError;
 
-use async_trait::async_trait;
 use fbthrift_transport::{
-    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
-    AsyncTransportConfiguration,
-};
-use nebula_client::{
-    v3::{GraphClient, GraphSession},
-    VersionV3,
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
 };
+#[cfg(feature = "tls")]
+use nebula_client::v3::tls::TlsConnector;
+use nebula_client::{v3::connector::TokioConnector, VersionV3};
 
-use super::super::{TokioSleep, TokioTcpStream};
-use crate::graph::{GraphClientConfiguration, GraphConnectionManager};
+use crate::{
+    graph::{GraphClientConfiguration, GraphConnectionManager},
+    session_pool::GraphSessionManager,
+    shared_session::SharedSessionManager,
+};
 
 //
 pub fn new_graph_connection_manager<H>(
     client_configuration: GraphClientConfiguration,
     transport_configuration: AsyncTransportConfiguration<H>,
-) -> GraphConnectionManager<TokioTcpStream, TokioSleep, H, VersionV3>
+) -> GraphConnectionManager<TokioConnector, H, VersionV3>
 where
     H: ResponseHandler + Send + Sync + 'static + Unpin,
 {
-    GraphConnectionManager::new(client_configuration, transport_configuration)
+    GraphConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        TokioConnector,
+    )
 }
 
-//
-impl<H> GraphConnectionManager<TokioTcpStream, TokioSleep, H, VersionV3>
+pub fn new_graph_session_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> GraphSessionManager<TokioConnector, H>
 where
     H: ResponseHandler + Send + Sync + 'static + Unpin,
 {
-    async fn get_async_connection(
-        &self,
-    ) -> Result<GraphSession<AsyncTransport<TokioTcpStream, TokioSleep, H>>, IoError> {
-        let transport = AsyncTransport::with_tokio_tcp_connect(
-            self.client_configuration.tcp_connect_addr(),
-            self.transport_configuration.clone(),
-        )
-        .await?;
+    GraphSessionManager::new(
+        client_configuration,
+        transport_configuration,
+        TokioConnector,
+    )
+}
+
+pub fn new_shared_session_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> SharedSessionManager<TokioConnector, H>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    SharedSessionManager::new(
+        client_configuration,
+        transport_configuration,
+        TokioConnector,
+    )
+}
+
+/// Connects with `client_configuration.tls`, or the default `TlsConfig` when it is `None`.
+#[cfg(feature = "tls")]
+pub fn new_tls_graph_connection_manager<H>(
+    client_configuration: GraphClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> Result<GraphConnectionManager<TlsConnector<TokioConnector>, H, VersionV3>, IoError>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    let tls = client_configuration.tls.clone().unwrap_or_default();
+    let connector = TlsConnector::new(TokioConnector, &tls)?;
+
+    Ok(GraphConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        connector,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_client::v3::{
+        mock::{MockGraph, Reply},
+        GraphQuery as _, GraphTransportResponseHandler,
+    };
+
+    #[tokio::test]
+    async fn with_mock() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on("SHOW HOSTS", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                Some("nba".to_owned()),
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder().max_size(2).build(manager).await?;
+
+        let mut session = pool.get().await?;
+        session.query(&b"SHOW HOSTS".to_vec()).await?;
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+
+        mock.assert_received_in_session(session.session_id(), "USE `nba`");
+        mock.assert_received("SHOW HOSTS");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_validation() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::graph::GraphValidation;
+
+        let mock = MockGraph::new();
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            )
+            .with_validation(GraphValidation::default()),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder().max_size(1).build(manager).await?;
+
+        let session_id = pool.get().await?.session_id();
+        mock.assert_received_in_session(session_id, "YIELD 1");
+        assert_eq!(pool.get().await?.session_id(), session_id);
+
+        // Expired on the server, the session is replaced instead of handed out.
+        mock.expire_session(session_id);
+        let session = pool.get().await?;
+        assert_ne!(session.session_id(), session_id);
+        assert_eq!(mock.sessions(), vec![session.session_id()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_validation_idle_threshold() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use crate::graph::GraphValidation;
+
+        let mock = MockGraph::new();
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            )
+            .with_validation(
+                GraphValidation::default().with_idle_threshold(Duration::from_millis(200)),
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder().max_size(1).build(manager).await?;
+
+        let session_id = pool.get().await?.session_id();
+        mock.assert_not_received("YIELD 1");
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(pool.get().await?.session_id(), session_id);
+        mock.assert_received_in_session(session_id, "YIELD 1");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_space_reset() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                Some("nba".to_owned()),
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .build(manager.clone())
+            .await?;
+
+        let mut session = pool.get().await?;
+        let session_id = session.session_id();
+        session.query(&b"USE other".to_vec()).await?;
+        assert_eq!(session.space_name(), Some(&b"other"[..]));
+        drop(session);
+        let session = pool.get().await?;
+        assert_eq!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+        drop(session);
+
+        // Already in the space, no `USE` is sent.
+        mock.clear_received();
+        manager.get_for_space(&pool, "nba").await?;
+        assert!(mock.statements().is_empty());
+
+        // Switched back at the next checkout, one session serves every space.
+        let session = manager.get_for_space(&pool, "a").await?;
+        assert_eq!(session.space_name(), Some(&b"a"[..]));
+        drop(session);
+        let session = manager.get_for_space(&pool, "b").await?;
+        assert_eq!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"b"[..]));
+        drop(session);
+        let session = pool.get().await?;
+        assert_eq!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+        assert_eq!(
+            mock.statements(),
+            vec!["USE `a`", "USE `nba`", "USE `b`", "USE `nba`"]
+        );
+        drop(session);
+        assert!(mock.signed_out().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_space_reset_without_test_on_check_out() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                Some("nba".to_owned()),
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(false)
+            .build(manager.clone())
+            .await?;
+
+        // Nothing would switch it back, so the session is dropped.
+        let mut session = pool.get().await?;
+        let session_id = session.session_id();
+        session.query(&b"USE other".to_vec()).await?;
+        drop(session);
+        let session = pool.get().await?;
+        assert_ne!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+        assert_eq!(mock.signed_out(), vec![session_id]);
+        drop(session);
+
+        let session = pool.get().await?;
+        let session_id = session.session_id();
+        drop(session);
+        assert_eq!(pool.get().await?.session_id(), session_id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_space_without_configured() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
 
-        let client = GraphClient::new(transport);
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(false)
+            .build(manager.clone())
+            .await?;
 
-        let mut session = client
-            .authenticate(
-                &self.client_configuration.username.as_bytes().to_vec(),
-                &self.client_configuration.password.as_bytes().to_vec(),
+        let session = manager.get_for_space(&pool, "nba").await?;
+        let session_id = session.session_id();
+        drop(session);
+
+        // The session in `nba` is not handed out again, the next connect signs it out.
+        let session = pool.get().await?;
+        assert_ne!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), None);
+        assert_eq!(mock.signed_out(), vec![session_id]);
+        drop(session);
+
+        assert!(matches!(
+            manager.get_for_space(&pool, "a`b").await,
+            Err(bb8::RunError::User(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_shutdown() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
+
+        use crate::graph::GraphValidation;
+
+        let mock = MockGraph::new();
+        mock.on_times(
+            "YIELD 1",
+            Reply::Error(ErrorCode::E_EXECUTION_ERROR, "lost".to_owned()),
+            1,
+        );
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
             )
+            .with_validation(GraphValidation::default()),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(2)
+            .build(manager.clone())
+            .await?;
+
+        // The failed check discards the first session, the next connect signs it out.
+        let session = pool.get_owned().await?;
+        let signed_out = mock.signed_out();
+        assert_eq!(signed_out.len(), 1);
+        assert_ne!(signed_out[0], session.session_id());
+        assert_eq!(manager.session_ids(), vec![session.session_id()]);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(session);
+        });
+        let session_ids = manager.session_ids();
+        manager.shutdown(pool, Duration::from_secs(5)).await?;
+        assert!(manager.session_ids().is_empty());
+
+        // Signout has no reply, graphd may not have handled it yet.
+        for _ in 0..100 {
+            if mock.sessions().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(mock.sessions().is_empty());
+        let signed_out = mock.signed_out();
+        assert_eq!(signed_out.len(), 2);
+        assert_eq!(signed_out[1..], session_ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_reaped_sessions() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+
+        // Expired when returned, the next connect signs it out.
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .max_lifetime(Duration::from_millis(50))
+            .build(manager.clone())
+            .await?;
+        let session = pool.get().await?;
+        let session_id = session.session_id();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(session);
+        assert!(manager.session_ids().is_empty());
+        assert_ne!(pool.get().await?.session_id(), session_id);
+        assert_eq!(mock.signed_out(), vec![session_id]);
+        drop(pool);
+
+        // Reaped while idle, shutdown signs it out and counts nothing in flight.
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .idle_timeout(Duration::from_millis(50))
+            .reaper_rate(Duration::from_millis(20))
+            .build(manager.clone())
+            .await?;
+        let session_id = pool.get().await?.session_id();
+        eventually(|| !manager.session_ids().contains(&session_id)).await;
+        manager.shutdown(pool, Duration::from_secs(1)).await?;
+        eventually(|| mock.signed_out().contains(&session_id)).await;
+        assert!(mock.sessions().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_shutdown_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(2)
+            .connection_timeout(Duration::from_millis(100))
+            .build(manager.clone())
+            .await?;
+        let other = pool.clone();
+
+        let session = pool.get_owned().await?;
+        let idle_session_id = pool.get().await?.session_id();
+        let err = manager
+            .shutdown(pool, Duration::from_millis(20))
             .await
-            .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+            .err()
+            .unwrap();
+        assert_eq!(err.in_flight, 1);
+        assert!(err.failed.is_empty());
+
+        // The idle session is signed out, the one in flight is left alone.
+        eventually(|| mock.signed_out() == vec![idle_session_id]).await;
+        assert_eq!(mock.sessions(), vec![session.session_id()]);
+        assert_eq!(manager.session_ids(), vec![session.session_id()]);
+        assert!(matches!(other.get().await, Err(bb8::RunError::TimedOut)));
+
+        drop(session);
+        assert!(manager.session_ids().is_empty());
+        assert_eq!(other.state().connections, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_keepalive() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
+
+        let mock = MockGraph::new();
+        mock.on_times(
+            "YIELD 1",
+            Reply::Error(ErrorCode::E_EXECUTION_ERROR, "lost".to_owned()),
+            1,
+        );
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .build(manager.clone())
+            .await?;
+
+        let session_id = pool.get().await?.session_id();
+        tokio::spawn(
+            manager
+                .clone()
+                .keepalive(pool.clone(), Duration::from_millis(50)),
+        );
+
+        // A failed ping drops the session, and the next tick signs it out.
+        eventually(|| pool.state().connections == 0).await;
+        assert!(manager.session_ids().is_empty());
+        let new_session_id = pool.get().await?.session_id();
+        assert_ne!(new_session_id, session_id);
+        eventually(|| mock.signed_out() == vec![session_id]).await;
+
+        // Pinged while idle, then replaced once graphd expired it.
+        eventually(|| {
+            mock.received()
+                .iter()
+                .any(|x| x.session_id == new_session_id && x.statement == "YIELD 1")
+        })
+        .await;
+        mock.expire_session(new_session_id);
+        eventually(|| pool.state().connections == 0).await;
+        let mut session = pool.get().await?;
+        assert_ne!(session.session_id(), new_session_id);
+        session.query(&b"YIELD 1".to_vec()).await?;
+        drop(session);
+        assert_eq!(mock.signed_out(), vec![session_id]);
+
+        manager.shutdown(pool, Duration::from_secs(1)).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_keepalive_validation() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use crate::graph::GraphValidation;
+
+        let mock = MockGraph::new();
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_connection_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            )
+            .with_validation(GraphValidation::default()),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(2)
+            .build(manager.clone())
+            .await?;
+
+        let session_ids = {
+            let first = pool.get().await?;
+            let second = pool.get().await?;
+            vec![first.session_id(), second.session_id()]
+        };
+        mock.clear_received();
+        tokio::spawn(
+            manager
+                .clone()
+                .keepalive(pool.clone(), Duration::from_millis(200)),
+        );
+
+        // Checked by `is_valid` at checkout, not pinged again.
+        let pings = |session_id: i64| {
+            mock.received()
+                .iter()
+                .filter(|x| x.session_id == session_id && x.statement == "YIELD 1")
+                .count()
+        };
+        eventually(|| session_ids.iter().all(|x| pings(*x) > 0)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        for session_id in session_ids {
+            assert_eq!(pings(session_id), 1);
+        }
+
+        manager.shutdown(pool, Duration::from_secs(1)).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_session_pool_keepalive() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use crate::session_pool::GraphSessionPool;
+
+        let mock = MockGraph::new();
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_session_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = GraphSessionPool::new(manager, 1).await?;
+        let session_id = pool.session_ids()[0];
+        tokio::spawn(pool.keepalive(Duration::from_millis(50)));
+
+        eventually(|| mock.received().iter().any(|x| x.statement == "YIELD 1")).await;
+        mock.expire_session(session_id);
+        eventually(|| matches!(pool.session_ids()[..], [x] if x != session_id)).await;
+        assert_ne!(pool.get().await?.session_id(), session_id);
+
+        pool.close().await?;
+
+        Ok(())
+    }
 
-        if let Some(ref space) = self.client_configuration.space {
-            session
-                .execute(&format!("USE {space}").as_bytes().to_vec())
+    #[tokio::test]
+    async fn with_shared_session() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+
+        use crate::shared_session::SharedGraphSession;
+
+        fn assert_send_sync<T: Send + Sync + Clone + 'static>(_: &T) {}
+
+        let mock = MockGraph::new();
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_shared_session_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                Some("nba".to_owned()),
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let shared = SharedGraphSession::new(manager, 2).await?;
+        assert_send_sync(&shared);
+        let session_id = shared.session_id().unwrap();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let mut shared = shared.clone();
+                tokio::spawn(async move { shared.query(&b"YIELD 1".to_vec()).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await??;
+        }
+        assert_eq!(mock.sessions(), vec![session_id]);
+        let yields: Vec<_> = mock
+            .received()
+            .into_iter()
+            .filter(|x| x.statement == "YIELD 1")
+            .collect();
+        assert_eq!(yields.len(), 8);
+        assert!(yields.iter().all(|x| x.session_id == session_id));
+        assert!(shared.pool().state().connections <= 2);
+
+        // Both connections busy, the next execute waits for one.
+        let first = shared.pool().get().await?;
+        let second = shared.pool().get().await?;
+        let stmt = b"YIELD 1".to_vec();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), shared.execute(&stmt))
                 .await
-                .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+                .is_err()
+        );
+        drop(first);
+        shared.execute(&stmt).await?;
+        drop(second);
+
+        // Expired on the server, the next connection authenticates a new session.
+        mock.expire_session(session_id);
+        assert!(shared.clone().query(&stmt).await.is_err());
+        shared.clone().query(&stmt).await?;
+        let new_session_id = shared.session_id().unwrap();
+        assert_ne!(new_session_id, session_id);
+        assert_eq!(mock.sessions(), vec![new_session_id]);
+
+        // `USE` would switch the space of every connection.
+        mock.clear_received();
+        for stmt in ["USE other", "  use `other`", "YIELD 1; USE other"] {
+            let stmt = stmt.as_bytes().to_vec();
+            assert!(shared.execute(&stmt).await.is_err());
+            assert!(shared.execute_json(&stmt).await.is_err());
+            assert!(shared.clone().query(&stmt).await.is_err());
         }
+        assert!(mock.received().is_empty());
+        shared.execute(&b"YIELD 1 AS user".to_vec()).await?;
 
-        Ok(session)
+        shared.close().await?;
+        eventually(|| mock.signed_out() == vec![new_session_id]).await;
+
+        Ok(())
     }
-}
 
-#[async_trait]
-impl<H> bb8::ManageConnection for GraphConnectionManager<TokioTcpStream, TokioSleep, H, VersionV3>
-where
-    H: ResponseHandler + Send + Sync + 'static + Unpin,
-{
-    type Connection = GraphSession<AsyncTransport<TokioTcpStream, TokioSleep, H>>;
-    type Error = IoError;
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn with_shared_session_json() -> Result<(), Box<dyn std::error::Error>> {
+        use serde::Deserialize;
+
+        use crate::shared_session::SharedGraphSession;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Row {
+            n: i64,
+        }
+
+        let mock = MockGraph::new();
+        mock.on(
+            "YIELD 1 AS n",
+            Reply::Json(
+                br#"{"errors": [{"code": 0}], "results": [{"columns": ["n"], "data": [{"row": [1]}]}]}"#
+                    .to_vec(),
+            ),
+        );
+        let server = mock.serve().await?;
+
+        let manager = new_shared_session_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                None,
+            ),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let shared = SharedGraphSession::new(manager, 2).await?;
+
+        let output = shared
+            .query_json_as::<Row>(&b"YIELD 1 AS n".to_vec())
+            .await?;
+        assert_eq!(output.data_set, vec![Row { n: 1 }]);
+        assert!(shared
+            .query_json_as::<Row>(&b"USE other".to_vec())
+            .await
+            .is_err());
 
-    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.get_async_connection().await
+        shared.close().await?;
+
+        Ok(())
+    }
+
+    async fn eventually(f: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if f() {
+                return;
+            }
+            tokio::time::sleep(core::time::Duration::from_millis(10)).await;
+        }
+        panic!("condition not met within 1s");
     }
 
-    async fn is_valid(&self, _conn: &mut Self::Connection) -> Result<(), Self::Error> {
+    #[tokio::test]
+    async fn with_session_pool() -> Result<(), Box<dyn std::error::Error>> {
+        use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
+
+        use crate::{graph::GraphValidation, session_pool::GraphSessionPool};
+
+        let mock = MockGraph::new();
+        // The first check fails as a broken connection would, the session then moves to a new one.
+        mock.on_times(
+            "YIELD 1",
+            Reply::Error(ErrorCode::E_EXECUTION_ERROR, "lost".to_owned()),
+            1,
+        );
+        mock.on("YIELD 1", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+
+        let manager = new_graph_session_manager(
+            GraphClientConfiguration::new(
+                server.addr().ip().to_string(),
+                server.addr().port(),
+                "root".to_owned(),
+                "nebula".to_owned(),
+                Some("nba".to_owned()),
+            )
+            .with_validation(GraphValidation::default()),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let pool = GraphSessionPool::new(manager, 1).await?;
+        let session_ids = pool.session_ids();
+        assert_eq!(session_ids.len(), 1);
+        let session_id = session_ids[0];
+
+        let mut session = pool.get().await?;
+        assert_eq!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+        session.query(&b"YIELD 1".to_vec()).await?;
+        drop(session);
+        assert_eq!(mock.sessions(), vec![session_id]);
+        let uses = mock
+            .received()
+            .iter()
+            .filter(|x| x.session_id == session_id && x.statement == "USE `nba`")
+            .count();
+        assert_eq!(uses, 2);
+
+        // Reattached to a new connection, back in the configured space.
+        let session = pool.get_for_space("other").await?;
+        assert_eq!(session.space_name(), Some(&b"other"[..]));
+        drop(session);
+        let session = pool.get().await?;
+        assert_eq!(session.session_id(), session_id);
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+        drop(session);
+
+        // Expired on the server, the session fails the check and is replaced.
+        mock.expire_session(session_id);
+        let session = pool.get().await?;
+        assert_ne!(session.session_id(), session_id);
+        assert_eq!(pool.session_ids(), vec![session.session_id()]);
+        drop(session);
+
+        let session_ids = pool.session_ids();
+        pool.close().await?;
+        // Signout has no reply, graphd may not have handled it yet.
+        for _ in 0..100 {
+            if !mock.signed_out().is_empty() {
+                break;
+            }
+            tokio::time::sleep(core::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(mock.signed_out(), session_ids);
+        assert!(mock.sessions().is_empty());
+
         Ok(())
     }
 
-    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
-        conn.is_close_required()
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn with_tls_configured() -> Result<(), Box<dyn std::error::Error>> {
+        use bb8::ManageConnection as _;
+        use nebula_client::v3::{tls::TlsConfig, Connector as _};
+
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+        let client_configuration = GraphClientConfiguration::new(
+            server.addr().ip().to_string(),
+            server.addr().port(),
+            "root".to_owned(),
+            "nebula".to_owned(),
+            None,
+        )
+        .with_tls(TlsConfig::default().with_insecure(true));
+
+        // A plain manager refuses rather than sending credentials unencrypted.
+        let manager = new_graph_connection_manager(
+            client_configuration.clone(),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        );
+        let err = manager.connect().await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(mock.sessions().is_empty());
+
+        let manager = new_tls_graph_connection_manager(
+            client_configuration,
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        )?;
+        assert!(manager.connector.is_tls());
+
+        Ok(())
     }
 }
@@ -0,0 +1,56 @@
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+};
+use nebula_client::{v3::connector::TokioConnector, VersionV3};
+
+use crate::meta::{MetaClientConfiguration, MetaConnectionManager};
+
+//
+pub fn new_meta_connection_manager<H>(
+    client_configuration: MetaClientConfiguration,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> MetaConnectionManager<TokioConnector, H, VersionV3>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    MetaConnectionManager::new(
+        client_configuration,
+        transport_configuration,
+        TokioConnector,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_client::v3::{mock::MockCluster, MetaTransportResponseHandler};
+    use nebula_fbthrift_storage::v3::dependencies::common::types::PropertyType;
+
+    #[tokio::test]
+    async fn with_mock() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster.space("nba", 1, PropertyType::INT64);
+        let server = cluster.serve_meta().await?;
+
+        let manager = new_meta_connection_manager(
+            MetaClientConfiguration::new(server.addr().ip().to_string(), server.addr().port()),
+            AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+        );
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(true)
+            .build(manager)
+            .await?;
+
+        for _ in 0..2 {
+            let client = pool.get().await?;
+            let res = client.get_space(b"nba".to_vec()).await?;
+            assert_eq!(res.item.properties.space_name, b"nba".to_vec());
+            assert!(!client.is_close_required());
+        }
+        assert_eq!(pool.state().connections, 1);
+
+        Ok(())
+    }
+}
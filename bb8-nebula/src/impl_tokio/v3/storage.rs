@@ -0,0 +1,101 @@
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransportConfiguration,
+};
+use nebula_client::{v3::connector::TokioConnector, VersionV3};
+
+use crate::storage::{StorageConnectionManager, StoragePools};
+
+//
+pub fn new_storage_connection_manager<H>(
+    addr: String,
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> StorageConnectionManager<TokioConnector, H, VersionV3>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    StorageConnectionManager::new(addr, transport_configuration, TokioConnector)
+}
+
+pub fn new_storage_pools<H>(
+    transport_configuration: AsyncTransportConfiguration<H>,
+) -> StoragePools<TokioConnector, H>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    StoragePools::new(transport_configuration, TokioConnector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_client::v3::{
+        mock::MockCluster, MetaTransportResponseHandler, StorageTransportResponseHandler,
+    };
+    use nebula_fbthrift_storage::v3::dependencies::common::types::{PropertyType, Value};
+
+    use crate::{
+        impl_tokio::v3::meta::new_meta_connection_manager, meta::MetaClientConfiguration,
+        storage::ScanPools,
+    };
+
+    #[tokio::test]
+    async fn with_scan() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster.space("nba", 3, PropertyType::INT64).tag(
+            "nba",
+            "player",
+            &[("name", PropertyType::STRING)],
+        );
+        for (vid, name) in [(1, "Tim"), (2, "Tony"), (3, "Manu")] {
+            cluster.insert_vertex(
+                "nba",
+                "player",
+                Value::iVal(vid),
+                vec![Value::sVal(name.as_bytes().to_vec())],
+            );
+        }
+        let meta = cluster.serve_meta().await?;
+        let storage = [
+            cluster.serve_storage().await?,
+            cluster.serve_storage().await?,
+        ];
+
+        let meta_pool = bb8::Pool::builder()
+            .max_size(1)
+            .build(new_meta_connection_manager(
+                MetaClientConfiguration::new(meta.addr().ip().to_string(), meta.addr().port()),
+                AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+            ))
+            .await?;
+        let pools = ScanPools::new(
+            meta_pool,
+            new_storage_pools(AsyncTransportConfiguration::new(
+                StorageTransportResponseHandler,
+            ))
+            .with_builder(|| bb8::Pool::builder().max_size(1)),
+        );
+
+        for _ in 0..2 {
+            let vertices: Vec<String> = pools
+                .scan_vertex("nba".into(), "player".into())
+                .await?
+                .into_iter()
+                .flatten()
+                .collect();
+            assert_eq!(vertices.len(), 3);
+        }
+        assert_eq!(cluster.scanned().len(), 6);
+
+        // Both scans went through one connection per host.
+        let mut addrs: Vec<String> = storage.iter().map(|x| x.addr().to_string()).collect();
+        addrs.sort();
+        assert_eq!(pools.storage.addrs(), addrs);
+        for addr in addrs {
+            assert_eq!(pools.storage.pool(&addr).state().connections, 1);
+        }
+        assert_eq!(pools.meta.state().connections, 1);
+
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ pub use fbthrift_transport;
 pub use nebula_client;
 
 //
+#[cfg(feature = "impl_async_io")]
+pub mod impl_async_io;
 #[cfg(feature = "impl_tokio")]
 pub mod impl_tokio;
 
@@ -10,4 +12,30 @@ pub mod impl_tokio;
 #[cfg(feature = "graph")]
 pub mod graph;
 #[cfg(feature = "graph")]
-pub use graph::{GraphClientConfiguration, GraphConnectionManager};
+pub use graph::{
+    GraphClientConfiguration, GraphConnectionManager, GraphShutdownError, GraphValidation,
+};
+
+#[cfg(feature = "graph")]
+pub mod session_pool;
+#[cfg(feature = "graph")]
+pub use session_pool::{GraphSessionManager, GraphSessionPool};
+
+#[cfg(feature = "graph")]
+pub mod shared_session;
+#[cfg(feature = "graph")]
+pub use shared_session::{SharedGraphSession, SharedSessionManager};
+
+#[cfg(feature = "meta")]
+pub mod meta;
+#[cfg(feature = "meta")]
+pub use meta::{MetaClientConfiguration, MetaConnectionManager};
+
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "storage")]
+pub use storage::{ScanPools, StorageConnectionManager, StoragePool, StoragePools};
+
+//
+#[cfg(feature = "metrics")]
+pub mod metrics;
@@ -0,0 +1,116 @@
+use core::marker::PhantomData;
+use std::io::Error as IoError;
+
+use async_trait::async_trait;
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+use nebula_client::{
+    v3::{Connector, MetaClient, ThriftProtocol},
+    Version, VersionV3,
+};
+
+//
+#[derive(Debug, Clone)]
+pub struct MetaClientConfiguration {
+    pub host: String,
+    pub port: u16,
+    pub protocol: ThriftProtocol,
+}
+
+impl MetaClientConfiguration {
+    pub fn new(host: String, port: u16) -> Self {
+        Self {
+            host,
+            port,
+            protocol: ThriftProtocol::default(),
+        }
+    }
+
+    pub fn with_protocol(mut self, protocol: ThriftProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+}
+
+impl MetaClientConfiguration {
+    pub fn tcp_connect_addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+//
+/// Pooled metad clients. Checkouts are validated with `listSpaces`.
+#[derive(Clone)]
+pub struct MetaConnectionManager<C, H, V>
+where
+    H: ResponseHandler,
+    V: Version,
+{
+    pub client_configuration: MetaClientConfiguration,
+    pub transport_configuration: AsyncTransportConfiguration<H>,
+    pub connector: C,
+    phantom: PhantomData<V>,
+}
+
+impl<C, H, V> MetaConnectionManager<C, H, V>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+    V: Version,
+{
+    pub fn new(
+        client_configuration: MetaClientConfiguration,
+        transport_configuration: AsyncTransportConfiguration<H>,
+        connector: C,
+    ) -> Self {
+        Self {
+            client_configuration,
+            transport_configuration,
+            connector,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<C, H> bb8::ManageConnection for MetaConnectionManager<C, H, VersionV3>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Connection = MetaClient<AsyncTransport<C::Stream, C::Sleep, H>>;
+    type Error = IoError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let addr = self.client_configuration.tcp_connect_addr();
+        let transport = self
+            .connector
+            .transport(&addr, self.transport_configuration.clone())
+            .await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(addr.clone());
+
+        Ok(
+            MetaClient::new_with_protocol(transport, self.client_configuration.protocol)
+                .with_addr(addr),
+        )
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.list_spaces().await.map_err(IoError::other)?;
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        let broken = conn.is_close_required();
+
+        #[cfg(feature = "metrics")]
+        if broken {
+            crate::metrics::broken();
+        }
+
+        broken
+    }
+}
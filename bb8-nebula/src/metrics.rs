@@ -0,0 +1,87 @@
+//! Pool metrics, emitted through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! bb8 has no hooks of its own, so the gauges and the checkout wait are only
+//! recorded when connections are taken with [`get`] / [`get_owned`], or when
+//! [`record_state`] is called (e.g. from a periodic task). Those metrics carry
+//! the `pool` label. Request metrics come from `nebula_client::v3::metrics`.
+
+use std::time::Instant;
+
+use bb8::{ManageConnection, Pool, PooledConnection, RunError};
+
+/// Connections managed by the pool, gauge.
+pub const POOL_CONNECTIONS: &str = "nebula_pool_connections";
+/// Idle connections, gauge.
+pub const POOL_IDLE_CONNECTIONS: &str = "nebula_pool_idle_connections";
+/// Time spent waiting for a connection, histogram in seconds.
+pub const POOL_CHECKOUT_WAIT_SECONDS: &str = "nebula_pool_checkout_wait_seconds";
+/// Checkouts that failed or timed out, counter.
+pub const POOL_CHECKOUT_ERRORS_TOTAL: &str = "nebula_pool_checkout_errors_total";
+/// Connections opened by the manager, counter with the `addr` label.
+/// bb8 does not report reconnects, this approximates them: once the pool is warm,
+/// every increment replaces a broken or expired connection, but growing the pool counts too.
+pub const POOL_CONNECTS_TOTAL: &str = "nebula_pool_connects_total";
+/// Connections dropped because they were broken, counter.
+pub const POOL_BROKEN_TOTAL: &str = "nebula_pool_broken_total";
+
+pub fn describe() {
+    use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+
+    nebula_client::v3::metrics::describe();
+
+    describe_gauge!(POOL_CONNECTIONS, "Connections managed by the pool");
+    describe_gauge!(POOL_IDLE_CONNECTIONS, "Idle connections in the pool");
+    describe_histogram!(
+        POOL_CHECKOUT_WAIT_SECONDS,
+        Unit::Seconds,
+        "Time spent waiting for a pooled connection"
+    );
+    describe_counter!(POOL_CHECKOUT_ERRORS_TOTAL, "Failed pool checkouts");
+    describe_counter!(POOL_CONNECTS_TOTAL, "Connections opened by the pool");
+    describe_counter!(POOL_BROKEN_TOTAL, "Broken connections dropped by the pool");
+}
+
+pub fn record_state<M: ManageConnection>(pool: &Pool<M>, name: &'static str) {
+    let state = pool.state();
+    metrics::gauge!(POOL_CONNECTIONS, "pool" => name).set(state.connections as f64);
+    metrics::gauge!(POOL_IDLE_CONNECTIONS, "pool" => name).set(state.idle_connections as f64);
+}
+
+/// `Pool::get`, recording the wait and the pool state.
+pub async fn get<'a, M: ManageConnection>(
+    pool: &'a Pool<M>,
+    name: &'static str,
+) -> Result<PooledConnection<'a, M>, RunError<M::Error>> {
+    let now = Instant::now();
+    let conn = pool.get().await;
+    checked_out(pool, name, now, conn.is_ok());
+    conn
+}
+
+/// `Pool::get_owned`, recording the wait and the pool state.
+pub async fn get_owned<M: ManageConnection>(
+    pool: &Pool<M>,
+    name: &'static str,
+) -> Result<PooledConnection<'static, M>, RunError<M::Error>> {
+    let now = Instant::now();
+    let conn = pool.get_owned().await;
+    checked_out(pool, name, now, conn.is_ok());
+    conn
+}
+
+fn checked_out<M: ManageConnection>(pool: &Pool<M>, name: &'static str, now: Instant, ok: bool) {
+    metrics::histogram!(POOL_CHECKOUT_WAIT_SECONDS, "pool" => name)
+        .record(now.elapsed().as_secs_f64());
+    if !ok {
+        metrics::counter!(POOL_CHECKOUT_ERRORS_TOTAL, "pool" => name).increment(1);
+    }
+    record_state(pool, name);
+}
+
+pub(crate) fn connected(addr: String) {
+    metrics::counter!(POOL_CONNECTS_TOTAL, "addr" => addr).increment(1);
+}
+
+pub(crate) fn broken() {
+    metrics::counter!(POOL_BROKEN_TOTAL).increment(1);
+}
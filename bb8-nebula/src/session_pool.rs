@@ -0,0 +1,457 @@
+//! A fixed set of graphd sessions, kept across connections.
+//!
+//! graphd identifies sessions by id, not by connection. [`GraphSessionManager`]
+//! authenticates each session once: when a connection breaks, bb8 drops it and
+//! the session is attached to the next connection, with `USE <space>` run again.
+//! Only sessions graphd reports invalid are replaced by new ones.
+//! [`GraphSessionPool::close`] signs every session out.
+
+use core::{
+    borrow::{Borrow, BorrowMut},
+    future::Future,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Error as IoError,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use bb8::{ManageConnection, Pool, PooledConnection, RunError};
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+use nebula_client::v3::{graph::GraphInterceptor, Connector, GraphClient, GraphSession};
+
+use crate::graph::{
+    signout_all, use_space, GraphClientConfiguration, GraphShutdownError, GraphValidation,
+    MAX_DISCARDED,
+};
+
+//
+#[derive(Default)]
+struct State {
+    /// Authenticated and not signed out.
+    all: BTreeSet<i64>,
+    /// Of `all`, those without a connection.
+    detached: Vec<i64>,
+    /// Failed a keepalive ping; `has_broken` drops their connections.
+    failed: BTreeSet<i64>,
+    /// Checked out by `get_for_space`, with the space they had before.
+    switched: BTreeMap<i64, Option<Vec<u8>>>,
+    /// Left in another space with no configured space to go back to, signed out over the next connection.
+    discarded: Vec<i64>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Sessions(Mutex<State>);
+
+impl Sessions {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().expect("not poisoned")
+    }
+
+    fn take_detached(&self) -> Option<i64> {
+        self.state().detached.pop()
+    }
+
+    fn detach(&self, session_id: i64) {
+        let mut state = self.state();
+        if !state.closed && state.all.contains(&session_id) {
+            state.detached.push(session_id);
+        }
+    }
+
+    fn forget(&self, session_id: i64) {
+        self.state().all.remove(&session_id);
+    }
+}
+
+//
+/// A session on its current connection. Dropping it detaches the session, for the next connection.
+pub struct PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    session: GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>,
+    sessions: Arc<Sessions>,
+}
+
+impl<C, H> Deref for PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Target = GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl<C, H> DerefMut for PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.session
+    }
+}
+
+impl<C, H> Borrow<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>> for PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn borrow(&self) -> &GraphSession<AsyncTransport<C::Stream, C::Sleep, H>> {
+        &self.session
+    }
+}
+
+impl<C, H> BorrowMut<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>> for PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn borrow_mut(&mut self) -> &mut GraphSession<AsyncTransport<C::Stream, C::Sleep, H>> {
+        &mut self.session
+    }
+}
+
+impl<C, H> Drop for PooledSession<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    fn drop(&mut self) {
+        let session_id = self.session.session_id();
+        if self.session.is_session_invalid() {
+            self.sessions.forget(session_id);
+        } else {
+            self.sessions.detach(session_id);
+        }
+    }
+}
+
+//
+/// Connections for [`GraphSessionPool`]; each carries a session authenticated once and reattached since.
+#[derive(Clone)]
+pub struct GraphSessionManager<C, H>
+where
+    H: ResponseHandler,
+{
+    pub client_configuration: GraphClientConfiguration,
+    pub transport_configuration: AsyncTransportConfiguration<H>,
+    pub connector: C,
+    /// Attached to every session, in this order.
+    pub interceptors: Vec<Arc<dyn GraphInterceptor>>,
+    sessions: Arc<Sessions>,
+}
+
+impl<C, H> GraphSessionManager<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    pub fn new(
+        client_configuration: GraphClientConfiguration,
+        transport_configuration: AsyncTransportConfiguration<H>,
+        connector: C,
+    ) -> Self {
+        Self {
+            client_configuration,
+            transport_configuration,
+            connector,
+            interceptors: vec![],
+            sessions: Default::default(),
+        }
+    }
+
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn GraphInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Authenticated sessions that are not signed out, attached or not.
+    pub fn session_ids(&self) -> Vec<i64> {
+        self.sessions.state().all.iter().copied().collect()
+    }
+
+    async fn client(&self) -> Result<GraphClient<AsyncTransport<C::Stream, C::Sleep, H>>, IoError> {
+        #[cfg(feature = "tls")]
+        if self.client_configuration.tls.is_some() && !self.connector.is_tls() {
+            return Err(IoError::new(
+                std::io::ErrorKind::InvalidInput,
+                "tls is configured but the connector is plain TCP",
+            ));
+        }
+
+        let addr = self.client_configuration.tcp_connect_addr();
+        let transport = self
+            .connector
+            .transport(&addr, self.transport_configuration.clone())
+            .await?;
+
+        Ok(
+            GraphClient::new_with_protocol(transport, self.client_configuration.protocol)
+                .with_addr(addr)
+                .with_trace_config(self.client_configuration.trace_config.clone())
+                .with_interceptors(self.interceptors.iter().cloned()),
+        )
+    }
+
+    /// Like [`GraphConnectionManager::keepalive`](crate::GraphConnectionManager::keepalive).
+    /// Sessions that time out are reattached to a new connection, expired ones replaced.
+    pub async fn keepalive(self, pool: Pool<Self>, interval: Duration) {
+        let validation = GraphValidation {
+            idle_threshold: Some(interval),
+            ..self
+                .client_configuration
+                .validation
+                .clone()
+                .unwrap_or_default()
+        };
+
+        loop {
+            async_sleep::sleep::<C::Sleep>(interval).await;
+            if self.sessions.state().closed {
+                return;
+            }
+
+            validation
+                .ping_idle::<_, C, H>(&pool, |session_id| {
+                    self.sessions.state().failed.insert(session_id);
+                })
+                .await;
+        }
+    }
+}
+
+#[async_trait]
+impl<C, H> ManageConnection for GraphSessionManager<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Connection = PooledSession<C, H>;
+    type Error = IoError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        if self.sessions.state().closed {
+            return Err(IoError::other("session pool is closed"));
+        }
+
+        let client = self.client().await?;
+
+        // Best effort, graphd drops them after `session_idle_timeout_secs` anyway.
+        let discarded = core::mem::take(&mut self.sessions.state().discarded);
+        let _ = signout_all::<C, H>(&client, discarded).await;
+
+        let session = match self.sessions.take_detached() {
+            Some(session_id) => {
+                // Wrapped first, so a failed `USE` detaches or forgets the session on drop.
+                // An attached session has no space yet, so `USE` also tells whether graphd still knows it.
+                let mut session = PooledSession {
+                    session: client.attach(session_id),
+                    sessions: self.sessions.clone(),
+                };
+                if let Some(ref space) = self.client_configuration.space {
+                    use_space::<C, H>(&mut session, space).await?;
+                }
+                session
+            }
+            None => {
+                let mut session = client
+                    .authenticate(
+                        &self.client_configuration.username.as_bytes().to_vec(),
+                        &self.client_configuration.password.as_bytes().to_vec(),
+                    )
+                    .await
+                    .map_err(IoError::other)?;
+
+                if let Some(ref space) = self.client_configuration.space {
+                    if let Err(err) = use_space::<C, H>(&mut session, space).await {
+                        let _ = session.signout().await;
+                        return Err(err);
+                    }
+                }
+
+                let closed = {
+                    let mut state = self.sessions.state();
+                    if !state.closed {
+                        state.all.insert(session.session_id());
+                    }
+                    state.closed
+                };
+                if closed {
+                    let _ = session.signout().await;
+                    return Err(IoError::other("session pool is closed"));
+                }
+
+                PooledSession {
+                    session,
+                    sessions: self.sessions.clone(),
+                }
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(self.client_configuration.tcp_connect_addr());
+
+        Ok(session)
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        if let Some(ref validation) = self.client_configuration.validation {
+            validation.check::<C, H>(conn).await?;
+        }
+
+        if let Some(ref space) = self.client_configuration.space {
+            use_space::<C, H>(conn, space).await?;
+        }
+
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        let broken = {
+            let mut state = self.sessions.state();
+            let session_id = conn.session_id();
+            let switched = state
+                .switched
+                .remove(&session_id)
+                .is_some_and(|space| conn.space_name() != space.as_deref());
+            // Reattached sessions only go back to a configured space, others are replaced.
+            if switched
+                && self.client_configuration.space.is_none()
+                && state.all.remove(&session_id)
+                && state.discarded.len() < MAX_DISCARDED
+            {
+                state.discarded.push(session_id);
+            }
+            state.failed.remove(&session_id) || switched || conn.is_close_required()
+        };
+
+        #[cfg(feature = "metrics")]
+        if broken {
+            crate::metrics::broken();
+        }
+
+        broken
+    }
+}
+
+//
+/// `size` sessions, each on its own connection.
+pub struct GraphSessionPool<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    pool: Pool<GraphSessionManager<C, H>>,
+    manager: GraphSessionManager<C, H>,
+}
+
+impl<C, H> GraphSessionPool<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    /// Authenticates `size` sessions before returning.
+    pub async fn new(manager: GraphSessionManager<C, H>, size: u32) -> Result<Self, IoError> {
+        let pool = Pool::builder()
+            .max_size(size)
+            .min_idle(Some(size))
+            .build(manager.clone())
+            .await?;
+
+        Ok(Self { pool, manager })
+    }
+
+    pub async fn get(
+        &self,
+    ) -> Result<PooledConnection<'_, GraphSessionManager<C, H>>, RunError<IoError>> {
+        self.pool.get().await
+    }
+
+    /// A session in `space`. Unless it is back in its previous space when returned,
+    /// its connection is dropped: the session is reattached in the configured space,
+    /// or, without one, replaced and signed out over the next connection.
+    pub async fn get_for_space(
+        &self,
+        space: &str,
+    ) -> Result<PooledConnection<'_, GraphSessionManager<C, H>>, RunError<IoError>> {
+        let mut conn = self.pool.get().await?;
+        if conn.space_name() == Some(space.as_bytes()) {
+            return Ok(conn);
+        }
+
+        self.manager
+            .sessions
+            .state()
+            .switched
+            .entry(conn.session_id())
+            .or_insert_with(|| conn.space_name().map(|x| x.to_vec()));
+        use_space::<C, H>(&mut conn, space)
+            .await
+            .map_err(RunError::User)?;
+        Ok(conn)
+    }
+
+    pub fn pool(&self) -> &Pool<GraphSessionManager<C, H>> {
+        &self.pool
+    }
+
+    pub fn session_ids(&self) -> Vec<i64> {
+        self.manager.session_ids()
+    }
+
+    /// See [`GraphSessionManager::keepalive`]; spawn the returned future, it ends after `close`.
+    pub fn keepalive(&self, interval: Duration) -> impl Future<Output = ()> + Send + 'static {
+        self.manager.clone().keepalive(self.pool.clone(), interval)
+    }
+
+    /// Signs out every session over a new connection. Connections still being opened fail.
+    pub async fn close(self) -> Result<(), GraphShutdownError> {
+        let Self { pool, manager } = self;
+
+        let session_ids = {
+            let mut state = manager.sessions.state();
+            state.closed = true;
+            state.detached.clear();
+            let all = core::mem::take(&mut state.all);
+            core::mem::take(&mut state.discarded)
+                .into_iter()
+                .chain(all)
+                .collect::<Vec<_>>()
+        };
+        // Only checkouts through `pool().get_owned()` can outlive `self`.
+        let state = pool.state();
+        let in_flight = state.connections - state.idle_connections;
+        drop(pool);
+
+        let failed = if session_ids.is_empty() {
+            vec![]
+        } else {
+            match manager.client().await {
+                Ok(client) => signout_all::<C, H>(&client, session_ids).await,
+                Err(err) => session_ids
+                    .into_iter()
+                    .map(|session_id| (session_id, IoError::new(err.kind(), err.to_string())))
+                    .collect(),
+            }
+        };
+
+        if in_flight == 0 && failed.is_empty() {
+            Ok(())
+        } else {
+            Err(GraphShutdownError { in_flight, failed })
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! One graphd session, shared by concurrent tasks.
+//!
+//! graphd identifies sessions by id, not by connection. [`SharedGraphSession`]
+//! authenticates once and attaches the session to up to `max_in_flight`
+//! connections; each execute runs on an idle one, and waits while all are busy.
+//! When graphd reports the session invalid, the next connection authenticates a
+//! new one and connections still on the old session are dropped.
+//!
+//! The space is per session on graphd, so `USE` is refused: it would switch
+//! every connection. Set [`GraphClientConfiguration::space`] instead.
+
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use bb8::{ManageConnection, Pool, RunError};
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+use nebula_client::v3::{
+    graph::{GraphInterceptor, GraphQueryError, GraphQueryOutput},
+    Connector, GraphClient, GraphQuery, GraphSession,
+};
+use nebula_fbthrift_graph::v3::{
+    dependencies::common::types::ErrorCode,
+    errors::graph_service::{ExecuteError, ExecuteJsonError},
+    types::ExecutionResponse,
+};
+use serde::de::DeserializeOwned;
+
+use crate::graph::{use_space, GraphClientConfiguration};
+
+//
+#[derive(Default)]
+struct State {
+    session_id: Option<i64>,
+    closed: bool,
+}
+
+//
+/// Connections for [`SharedGraphSession`], all attached to the same session.
+#[derive(Clone)]
+pub struct SharedSessionManager<C, H>
+where
+    H: ResponseHandler,
+{
+    pub client_configuration: GraphClientConfiguration,
+    pub transport_configuration: AsyncTransportConfiguration<H>,
+    pub connector: C,
+    /// Attached to every connection, in this order.
+    pub interceptors: Vec<Arc<dyn GraphInterceptor>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl<C, H> SharedSessionManager<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    pub fn new(
+        client_configuration: GraphClientConfiguration,
+        transport_configuration: AsyncTransportConfiguration<H>,
+        connector: C,
+    ) -> Self {
+        Self {
+            client_configuration,
+            transport_configuration,
+            connector,
+            interceptors: vec![],
+            state: Default::default(),
+        }
+    }
+
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn GraphInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// The current session, `None` before the first connection or after it went invalid.
+    pub fn session_id(&self) -> Option<i64> {
+        self.state().session_id
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("not poisoned")
+    }
+
+    async fn client(&self) -> Result<GraphClient<AsyncTransport<C::Stream, C::Sleep, H>>, IoError> {
+        #[cfg(feature = "tls")]
+        if self.client_configuration.tls.is_some() && !self.connector.is_tls() {
+            return Err(IoError::new(
+                std::io::ErrorKind::InvalidInput,
+                "tls is configured but the connector is plain TCP",
+            ));
+        }
+
+        let addr = self.client_configuration.tcp_connect_addr();
+        let transport = self
+            .connector
+            .transport(&addr, self.transport_configuration.clone())
+            .await?;
+
+        Ok(
+            GraphClient::new_with_protocol(transport, self.client_configuration.protocol)
+                .with_addr(addr)
+                .with_trace_config(self.client_configuration.trace_config.clone())
+                .with_interceptors(self.interceptors.iter().cloned()),
+        )
+    }
+
+    async fn session(
+        &self,
+    ) -> Result<GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>, IoError> {
+        let session_id = self.state().session_id;
+        if let Some(session_id) = session_id {
+            return Ok(self.client().await?.attach(session_id));
+        }
+
+        let session = self
+            .client()
+            .await?
+            .authenticate(
+                &self.client_configuration.username.as_bytes().to_vec(),
+                &self.client_configuration.password.as_bytes().to_vec(),
+            )
+            .await
+            .map_err(IoError::other)?;
+
+        // Connections opened concurrently may all have authenticated, the first one wins.
+        let (closed, current) = {
+            let mut state = self.state();
+            if !state.closed && state.session_id.is_none() {
+                state.session_id = Some(session.session_id());
+            }
+            (state.closed, state.session_id)
+        };
+        if closed {
+            let _ = session.signout().await;
+            return Err(IoError::other("shared session is closed"));
+        }
+        match current {
+            Some(session_id) if session_id != session.session_id() => {
+                let _ = session.signout().await;
+                Ok(self.client().await?.attach(session_id))
+            }
+            _ => Ok(session),
+        }
+    }
+}
+
+#[async_trait]
+impl<C, H> ManageConnection for SharedSessionManager<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Connection = GraphSession<AsyncTransport<C::Stream, C::Sleep, H>>;
+    type Error = IoError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        if self.state().closed {
+            return Err(IoError::other("shared session is closed"));
+        }
+
+        let mut session = self.session().await?;
+
+        if let Some(ref space) = self.client_configuration.space {
+            use_space::<C, H>(&mut session, space).await?;
+        }
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(self.client_configuration.tcp_connect_addr());
+
+        Ok(session)
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        if self.state().session_id != Some(conn.session_id()) {
+            return Err(IoError::other("session was replaced"));
+        }
+
+        if let Some(ref validation) = self.client_configuration.validation {
+            validation.check::<C, H>(conn).await?;
+        }
+
+        if let Some(ref space) = self.client_configuration.space {
+            use_space::<C, H>(conn, space).await?;
+        }
+
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        if conn.is_session_invalid() {
+            let mut state = self.state();
+            if state.session_id == Some(conn.session_id()) {
+                state.session_id = None;
+            }
+        }
+
+        let broken = conn.is_close_required();
+
+        #[cfg(feature = "metrics")]
+        if broken {
+            crate::metrics::broken();
+        }
+
+        broken
+    }
+}
+
+//
+/// A `Send + Sync` session handle; clones share the session and the `max_in_flight` connections.
+#[derive(Clone)]
+pub struct SharedGraphSession<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    pool: Pool<SharedSessionManager<C, H>>,
+    manager: SharedSessionManager<C, H>,
+}
+
+impl<C, H> SharedGraphSession<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    /// Authenticates before returning. At most `max_in_flight` executes run at once.
+    pub async fn new(
+        manager: SharedSessionManager<C, H>,
+        max_in_flight: u32,
+    ) -> Result<Self, IoError> {
+        let pool = Pool::builder()
+            .max_size(max_in_flight)
+            .min_idle(Some(1))
+            .build(manager.clone())
+            .await?;
+
+        Ok(Self { pool, manager })
+    }
+
+    pub fn session_id(&self) -> Option<i64> {
+        self.manager.session_id()
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub async fn execute(&self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError> {
+        check_stmt(stmt).map_err(|err| ExecuteError::ThriftError(err.into()))?;
+
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| ExecuteError::ThriftError(run_error(err).into()))?;
+        conn.execute(stmt).await
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub async fn execute_json(&self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError> {
+        check_stmt(stmt).map_err(|err| ExecuteJsonError::ThriftError(err.into()))?;
+
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| ExecuteJsonError::ThriftError(run_error(err).into()))?;
+        conn.execute_json(stmt).await
+    }
+
+    /// Like [`GraphSession::query_json_as`].
+    #[cfg(feature = "json")]
+    #[allow(clippy::ptr_arg)]
+    pub async fn query_json_as<D: DeserializeOwned>(
+        &self,
+        stmt: &Vec<u8>,
+    ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+        let json_error = |err: IoError| {
+            GraphQueryError::ExecuteJsonError(ExecuteJsonError::ThriftError(err.into()))
+        };
+        check_stmt(stmt).map_err(json_error)?;
+
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| json_error(run_error(err)))?;
+        conn.query_json_as(stmt).await
+    }
+
+    /// The connections; bb8's `connection_timeout` bounds how long an execute waits for one.
+    pub fn pool(&self) -> &Pool<SharedSessionManager<C, H>> {
+        &self.pool
+    }
+
+    /// Signs the session out over a new connection; executes on other clones fail afterwards.
+    pub async fn close(self) -> Result<(), IoError> {
+        let Self { pool, manager } = self;
+
+        let session_id = {
+            let mut state = manager.state();
+            state.closed = true;
+            state.session_id.take()
+        };
+        drop(pool);
+
+        match session_id {
+            Some(session_id) => manager
+                .client()
+                .await?
+                .signout(session_id)
+                .await
+                .map_err(IoError::other),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<C, H> GraphQuery for SharedGraphSession<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    async fn query_as<D: DeserializeOwned>(
+        &mut self,
+        stmt: &Vec<u8>,
+    ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+        let res = self
+            .execute(stmt)
+            .await
+            .map_err(GraphQueryError::ExecuteError)?;
+
+        if res.error_code != ErrorCode::SUCCEEDED {
+            return Err(GraphQueryError::ResponseError(
+                res.error_code,
+                res.error_msg,
+            ));
+        }
+
+        GraphQueryOutput::new(res)
+    }
+}
+
+/// Refuses `USE`, which would switch the space of every connection.
+fn check_stmt(stmt: &[u8]) -> Result<(), IoError> {
+    let is_use = stmt.split(|x| *x == b';').any(|part| {
+        let start = part
+            .iter()
+            .position(|x| !x.is_ascii_whitespace())
+            .unwrap_or(part.len());
+        let part = &part[start..];
+        part.len() > 3
+            && part[..3].eq_ignore_ascii_case(b"USE")
+            && (part[3].is_ascii_whitespace() || part[3] == b'`')
+    });
+
+    if is_use {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            "USE would switch the space of every connection of the shared session, configure the space instead",
+        ));
+    }
+
+    Ok(())
+}
+
+fn run_error(err: RunError<IoError>) -> IoError {
+    match err {
+        RunError::User(err) => err,
+        RunError::TimedOut => IoError::new(
+            std::io::ErrorKind::TimedOut,
+            "no connection of the shared session freed up in time",
+        ),
+    }
+}
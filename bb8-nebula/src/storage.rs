@@ -0,0 +1,330 @@
+use core::{marker::PhantomData, ops::Deref};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use bb8::{Builder, ManageConnection, Pool, PooledConnection, RunError};
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+use nebula_client::{
+    v3::{
+        scan_edge_in, scan_vertex_in, storage::scan::ScanError, Connector, MetaHandle,
+        MetaTransportResponseHandler, ScanClients, StorageClient, StorageHandle,
+        StorageTransportResponseHandler, ThriftProtocol,
+    },
+    Version, VersionV3,
+};
+use nebula_fbthrift_meta::v3::{
+    errors::meta_service::{
+        GetPartsAllocError, GetSpaceError, ListEdgesError, ListPartsError, ListSpacesError,
+        ListTagsError,
+    },
+    types::{
+        GetPartsAllocResp, GetSpaceResp, ListEdgesResp, ListPartsResp, ListSpacesResp, ListTagsResp,
+    },
+};
+use nebula_fbthrift_storage::v3::{
+    errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
+    types::{ScanEdgeRequest, ScanResponse, ScanVertexRequest},
+};
+
+use crate::meta::MetaConnectionManager;
+
+//
+/// Pooled storaged clients for the host at `addr`, see [`StoragePools`] for one pool per host.
+#[derive(Clone)]
+pub struct StorageConnectionManager<C, H, V>
+where
+    H: ResponseHandler,
+    V: Version,
+{
+    pub addr: String,
+    pub protocol: ThriftProtocol,
+    pub transport_configuration: AsyncTransportConfiguration<H>,
+    pub connector: C,
+    phantom: PhantomData<V>,
+}
+
+impl<C, H, V> StorageConnectionManager<C, H, V>
+where
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+    V: Version,
+{
+    pub fn new(
+        addr: String,
+        transport_configuration: AsyncTransportConfiguration<H>,
+        connector: C,
+    ) -> Self {
+        Self {
+            addr,
+            protocol: ThriftProtocol::default(),
+            transport_configuration,
+            connector,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_protocol(mut self, protocol: ThriftProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+}
+
+#[async_trait]
+impl<C, H> ManageConnection for StorageConnectionManager<C, H, VersionV3>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    type Connection = StorageClient<AsyncTransport<C::Stream, C::Sleep, H>>;
+    type Error = IoError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let transport = self
+            .connector
+            .transport(&self.addr, self.transport_configuration.clone())
+            .await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(self.addr.clone());
+
+        Ok(StorageClient::new_with_protocol(transport, self.protocol).with_addr(&self.addr))
+    }
+
+    /// storaged has no cheap request, so this only refuses connections whose last scan failed
+    /// at the transport level.
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        if conn.is_close_required() {
+            return Err(IoError::new(
+                IoErrorKind::BrokenPipe,
+                "storage connection must be closed",
+            ));
+        }
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        let broken = conn.is_close_required();
+
+        #[cfg(feature = "metrics")]
+        if broken {
+            crate::metrics::broken();
+        }
+
+        broken
+    }
+}
+
+//
+pub type StoragePool<C, H> = Pool<StorageConnectionManager<C, H, VersionV3>>;
+
+type BuildFn<M> = dyn Fn() -> Builder<M> + Send + Sync;
+
+/// One pool per storaged address, created on first checkout.
+pub struct StoragePools<C, H>
+where
+    C: Connector,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    protocol: ThriftProtocol,
+    transport_configuration: AsyncTransportConfiguration<H>,
+    connector: C,
+    builder: Arc<BuildFn<StorageConnectionManager<C, H, VersionV3>>>,
+    pools: Mutex<HashMap<String, StoragePool<C, H>>>,
+}
+
+impl<C, H> StoragePools<C, H>
+where
+    C: Connector + Clone,
+    H: ResponseHandler + Send + Sync + 'static + Unpin,
+{
+    pub fn new(transport_configuration: AsyncTransportConfiguration<H>, connector: C) -> Self {
+        Self {
+            protocol: ThriftProtocol::default(),
+            transport_configuration,
+            connector,
+            builder: Arc::new(Pool::builder),
+            pools: Default::default(),
+        }
+    }
+
+    pub fn with_protocol(mut self, protocol: ThriftProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Configures every per-host pool, e.g. `|| Pool::builder().max_size(4)`.
+    pub fn with_builder(
+        mut self,
+        builder: impl Fn() -> Builder<StorageConnectionManager<C, H, VersionV3>> + Send + Sync + 'static,
+    ) -> Self {
+        self.builder = Arc::new(builder);
+        self
+    }
+
+    /// The pool of `addr` (`host:port`).
+    pub fn pool(&self, addr: &str) -> StoragePool<C, H> {
+        let mut pools = self.pools.lock().expect("not poisoned");
+        pools
+            .entry(addr.to_owned())
+            .or_insert_with(|| {
+                let manager = StorageConnectionManager::new(
+                    addr.to_owned(),
+                    self.transport_configuration.clone(),
+                    self.connector.clone(),
+                )
+                .with_protocol(self.protocol);
+                (self.builder)().build_unchecked(manager)
+            })
+            .clone()
+    }
+
+    /// The addresses pooled so far.
+    pub fn addrs(&self) -> Vec<String> {
+        let pools = self.pools.lock().expect("not poisoned");
+        let mut addrs: Vec<_> = pools.keys().cloned().collect();
+        addrs.sort();
+        addrs
+    }
+}
+
+//
+/// Meta and storage pools for `nebula_client::v3::scan_vertex_in` and `scan_edge_in`.
+pub struct ScanPools<C>
+where
+    C: Connector,
+{
+    pub meta: Pool<MetaConnectionManager<C, MetaTransportResponseHandler, VersionV3>>,
+    pub storage: StoragePools<C, StorageTransportResponseHandler>,
+}
+
+impl<C> ScanPools<C>
+where
+    C: Connector + Clone,
+{
+    pub fn new(
+        meta: Pool<MetaConnectionManager<C, MetaTransportResponseHandler, VersionV3>>,
+        storage: StoragePools<C, StorageTransportResponseHandler>,
+    ) -> Self {
+        Self { meta, storage }
+    }
+
+    pub async fn scan_vertex(
+        &self,
+        space_name: String,
+        tag_name: String,
+    ) -> Result<Vec<Vec<String>>, ScanError> {
+        scan_vertex_in(self, space_name, tag_name).await
+    }
+
+    pub async fn scan_edge(
+        &self,
+        space_name: String,
+        edge_name: String,
+    ) -> Result<Vec<Vec<String>>, ScanError> {
+        scan_edge_in(self, space_name, edge_name).await
+    }
+}
+
+#[async_trait]
+impl<C> ScanClients for ScanPools<C>
+where
+    C: Connector + Clone,
+{
+    async fn meta(&self) -> Result<Box<dyn MetaHandle + '_>, IoError> {
+        let conn = self.meta.get().await.map_err(run_error)?;
+        Ok(Box::new(Pooled(conn)))
+    }
+
+    async fn storage(&self, addr: &str) -> Result<Box<dyn StorageHandle + '_>, IoError> {
+        let conn = self
+            .storage
+            .pool(addr)
+            .get_owned()
+            .await
+            .map_err(run_error)?;
+        Ok(Box::new(Pooled(conn)))
+    }
+}
+
+fn run_error(err: RunError<IoError>) -> IoError {
+    match err {
+        RunError::User(err) => err,
+        RunError::TimedOut => IoError::new(IoErrorKind::TimedOut, "pool checkout timed out"),
+    }
+}
+
+//
+/// A checked out connection; the handle traits cannot be implemented on `PooledConnection` here.
+struct Pooled<'a, M: ManageConnection>(PooledConnection<'a, M>);
+
+impl<M: ManageConnection> Deref for Pooled<'_, M> {
+    type Target = M::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl<M> MetaHandle for Pooled<'_, M>
+where
+    M: ManageConnection,
+    M::Connection: MetaHandle,
+{
+    async fn list_spaces(&self) -> Result<ListSpacesResp, ListSpacesError> {
+        (**self).list_spaces().await
+    }
+
+    async fn get_space(&self, space_name: Vec<u8>) -> Result<GetSpaceResp, GetSpaceError> {
+        (**self).get_space(space_name).await
+    }
+
+    async fn list_parts(
+        &self,
+        space_id: i32,
+        part_ids: Vec<i32>,
+    ) -> Result<ListPartsResp, ListPartsError> {
+        (**self).list_parts(space_id, part_ids).await
+    }
+
+    async fn list_tags(&self, space_id: i32) -> Result<ListTagsResp, ListTagsError> {
+        (**self).list_tags(space_id).await
+    }
+
+    async fn list_edges(&self, space_id: i32) -> Result<ListEdgesResp, ListEdgesError> {
+        (**self).list_edges(space_id).await
+    }
+
+    async fn get_parts(&self, space_id: i32) -> Result<GetPartsAllocResp, GetPartsAllocError> {
+        (**self).get_parts(space_id).await
+    }
+
+    fn is_close_required(&self) -> bool {
+        MetaHandle::is_close_required(&**self)
+    }
+}
+
+#[async_trait]
+impl<M> StorageHandle for Pooled<'_, M>
+where
+    M: ManageConnection,
+    M::Connection: StorageHandle,
+{
+    async fn scan_vertex(&self, req: &ScanVertexRequest) -> Result<ScanResponse, ScanVertexError> {
+        (**self).scan_vertex(req).await
+    }
+
+    async fn scan_edge(&self, req: &ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeError> {
+        (**self).scan_edge(req).await
+    }
+
+    fn is_close_required(&self) -> bool {
+        StorageHandle::is_close_required(&**self)
+    }
+}
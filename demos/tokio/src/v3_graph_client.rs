@@ -6,7 +6,9 @@ cargo run -p nebula-demo-tokio --bin nebula_demo_tokio_v3_graph_client 192.168.1
 use std::env;
 
 use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
-use nebula_client::v3::{GraphClient, GraphQuery as _, GraphTransportResponseHandler};
+use nebula_client::v3::{
+    graph::query::Space, GraphClient, GraphQuery as _, GraphTransportResponseHandler,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -49,5 +51,10 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let res = session.show_hosts().await?;
     println!("{res:?}");
 
+    let res = session
+        .query_json_as::<Space>(&b"SHOW SPACES;".to_vec())
+        .await?;
+    println!("{res:?}");
+
     Ok(())
 }
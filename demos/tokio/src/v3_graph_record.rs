@@ -0,0 +1,84 @@
+/*
+cargo run -p nebula-demo-tokio --bin nebula_demo_tokio_v3_graph_record 127.0.0.1 9669 root 'password' basketballplayer
+*/
+
+use std::env;
+
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use nebula_client::v3::{
+    record::NebulaTag, GraphClient, GraphQuery as _, GraphTransportResponseHandler,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize, NebulaTag, Debug)]
+#[nebula(tag = "player")]
+struct Player {
+    #[nebula(vid)]
+    id: String,
+    name: String,
+    age: i64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run().await
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let domain = env::args()
+        .nth(1)
+        .unwrap_or_else(|| env::var("DOMAIN").unwrap_or_else(|_| "127.0.0.1".to_owned()));
+    let port: u16 = env::args()
+        .nth(2)
+        .unwrap_or_else(|| env::var("PORT").unwrap_or_else(|_| "9669".to_owned()))
+        .parse()
+        .unwrap();
+    let username = env::args()
+        .nth(3)
+        .unwrap_or_else(|| env::var("USERNAME").unwrap_or_else(|_| "root".to_owned()));
+    let password = env::args()
+        .nth(4)
+        .unwrap_or_else(|| env::var("PASSWORD").unwrap_or_else(|_| "password".to_owned()));
+    let space = env::args()
+        .nth(5)
+        .unwrap_or_else(|| env::var("SPACE").unwrap_or_else(|_| "basketballplayer".to_owned()));
+
+    println!("v3_graph_record {domain} {port} {username} {password} {space}",);
+
+    //
+    let addr = format!("{domain}:{port}");
+
+    //
+    let transport = AsyncTransport::with_tokio_tcp_connect(
+        addr,
+        AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+    )
+    .await?;
+    let client = GraphClient::new(transport);
+
+    let mut session = client
+        .authenticate(&username.as_bytes().to_vec(), &password.as_bytes().to_vec())
+        .await?;
+
+    session
+        .execute(&format!("USE {space}").as_bytes().to_vec())
+        .await?;
+
+    let player = Player {
+        id: "player999".to_owned(),
+        name: "Demo".to_owned(),
+        age: 30,
+    };
+    println!("{}", player.upsert_statement());
+    let res = session
+        .execute(&player.upsert_statement().into_bytes())
+        .await?;
+    println!("{:?}", res.error_code);
+
+    let res = session
+        .query_as::<Player>(&Player::fetch_statement("player999").into_bytes())
+        .await?;
+    println!("{res:?}");
+
+    Ok(())
+}
@@ -29,8 +29,9 @@ pub struct Timestamp(pub i64);
 impl Timestamp {
     #[cfg(feature = "chrono")]
     pub fn to_naive_date_time(&self) -> chrono::NaiveDateTime {
-        chrono::NaiveDateTime::from_timestamp_opt(self.0, 0)
-            .expect("chrono::NaiveDateTime::from_timestamp_opt")
+        chrono::DateTime::from_timestamp(self.0, 0)
+            .expect("chrono::DateTime::from_timestamp")
+            .naive_utc()
     }
 }
 
@@ -60,7 +60,7 @@ impl<'a> DataDeserializer<'a> {
 }
 
 // 为DataDeserializer<'de>设定一个反序列化器
-impl<'a, 'de> Deserializer<'de> for &'a mut DataDeserializer<'de> {
+impl<'de> Deserializer<'de> for &mut DataDeserializer<'de> {
     type Error = DataDeserializeError; //自定义error类型
 
     // deserialize_any 方法会始终导致一个错误，表示这个方法的功能尚未实现，需要在后续的代码中进行具体实现。
@@ -524,7 +524,7 @@ impl<'a, 'de> Deserializer<'de> for &'a mut DataDeserializer<'de> {
     }
 }
 
-impl<'a, 'de> MapAccess<'de> for &'a mut DataDeserializer<'de> {
+impl<'de> MapAccess<'de> for &mut DataDeserializer<'de> {
     type Error = DataDeserializeError;
 
     fn next_key_seed<K: DeserializeSeed<'de>>(
@@ -641,7 +641,7 @@ mod tests {
     use super::*; // 要用本文件的东西 DataDeserializer::new
 
     use float_cmp::approx_eq;
-    use nebula_fbthrift_graph_v3::dependencies::common::{double::Double, types};
+    use nebula_fbthrift_graph::v3::dependencies::common::{double::Double, types};
     use serde::{de::DeserializeOwned, Deserialize};
     // erde_repr 是一个用于 Serde 的宏扩展库，它的主要功能是为枚举类型提供一种更紧凑的表示方式，以便进行序列化和反序列化。
     use serde_repr::Deserialize_repr;
@@ -2,7 +2,7 @@ use nebula_fbthrift_graph::v3::dependencies::common::types::Value;
 
 
 
-pub fn scan_vertex_result(col_names:&Vec<String>,rows:&Vec<String>) ->Result<String, ProcessError> {
+pub fn scan_vertex_result(col_names:&[String],rows:&[String]) ->Result<String, ProcessError> {
     
     if col_names.len() != rows.len() {
         return Err(ProcessError(DataDealError::VertexResultError));
@@ -44,13 +44,10 @@ pub fn scan_vertex_result(col_names:&Vec<String>,rows:&Vec<String>) ->Result<Str
 
     result.push_str("})");
 
-    println!("{}", result);
-
-
     Ok(result)
 }
 
-pub fn scan_edge_result(col_names:&Vec<String>,rows:&Vec<String>) ->Result<String, ProcessError> {
+pub fn scan_edge_result(col_names:&[String],rows:&[String]) ->Result<String, ProcessError> {
     
     if col_names.len() != rows.len() {
         return Err(ProcessError(DataDealError::EdgeResultError));
@@ -76,7 +73,7 @@ pub fn scan_edge_result(col_names:&Vec<String>,rows:&Vec<String>) ->Result<Strin
     
     
     
-    let mut result = String::from(&format!("({})-[:{}@{}{{", rows.get(0).map(|s| s.as_str()).unwrap_or(""),first_part,rows.get(2).map(|s| s.as_str()).unwrap_or("")));
+    let mut result = String::from(&format!("({})-[:{}@{}{{", rows.first().map(|s| s.as_str()).unwrap_or(""),first_part,rows.get(2).map(|s| s.as_str()).unwrap_or("")));
 
 
     // 遍历 col_names 和 values，构建每对键值对
@@ -92,10 +89,6 @@ pub fn scan_edge_result(col_names:&Vec<String>,rows:&Vec<String>) ->Result<Strin
 
     result.push_str(&format!("}}]->({})", rows.get(3).map(|s| s.as_str()).unwrap_or("")));
 
-
-    println!("{}", result);
-
-
     Ok(result)
 }
 
@@ -124,7 +117,7 @@ pub fn process_value(value: &Value) -> Result<String, ProcessError> {
         }
         Value::sVal(binary_data) => {
             let decoded_string = String::from_utf8(binary_data.to_vec()).map_err(|_| ProcessError(DataDealError::BinaryDecodeError))?;
-            Ok(format!("{}", decoded_string))
+            Ok(decoded_string)
         }
         Value::dVal(date_value) => {
             Ok(format!("{}-{:02}-{:02}", date_value.year, date_value.month, date_value.day))
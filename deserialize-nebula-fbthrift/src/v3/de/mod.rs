@@ -4,7 +4,7 @@ pub mod datadeal;
 
 use nebula_fbthrift_graph::v3::ExecutionResponse;
 use nebula_fbthrift_storage::v3::ScanResponse;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::v3::de::data::{DataDeserializeError, DataDeserializer};
 use crate::v3::de::datadeal::{process_value,process_column_name,scan_vertex_result,scan_edge_result,ProcessError};
@@ -100,6 +100,38 @@ pub fn deserialize_scan_struct_response<'de, D: Deserialize<'de>>(
 }
 
 
+/// Same as [`deserialize_scan_struct_response`], but columns listed in `columns`
+/// as `(column name, field name)` are renamed first, e.g. `("player.name", "name")`.
+/// Other columns keep their name.
+pub fn deserialize_scan_struct_response_with_columns<D: DeserializeOwned>(
+    scan_response: &ScanResponse,
+    columns: &[(String, &str)],
+) -> Result<Vec<D>, DataDeserializeError> {
+    let (names, rows) = match &scan_response.props {
+        Some(set) if !set.column_names.is_empty() => (&set.column_names, &set.rows),
+        _ => return Ok(vec![]),
+    };
+
+    let names: Vec<Vec<u8>> = names
+        .iter()
+        .map(|name| {
+            columns
+                .iter()
+                .find(|(column, _)| column.as_bytes() == name.as_slice())
+                .map(|(_, field)| field.as_bytes().to_vec())
+                .unwrap_or_else(|| name.clone())
+        })
+        .collect();
+
+    let mut data_set: Vec<D> = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        let mut data_deserializer = DataDeserializer::new(&names, &row.values);
+
+        data_set.push(D::deserialize(&mut data_deserializer)?);
+    }
+
+    Ok(data_set)
+}
 
 
 pub fn deserialize_execution_response<'de, D: Deserialize<'de>>(
@@ -0,0 +1,255 @@
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
+
+use nebula_fbthrift_graph::v3::dependencies::common::types::{DataSet, Value};
+
+use super::AsDataSet;
+use crate::v3::literal::{is_null, Plain};
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub header: bool,
+    /// Written for `NULL` (and unset) values. Empty by default.
+    pub null: String,
+    /// Quote every field instead of only the ones that need it.
+    pub quote_all: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            null: String::new(),
+            quote_all: false,
+        }
+    }
+}
+
+/// Streams rows as RFC 4180 CSV.
+///
+/// Strings are written unquoted unless they contain the delimiter, a quote or
+/// a line break; nested values (vertices, edges, lists, ...) use nGQL literal
+/// syntax.
+pub struct CsvWriter<W: Write> {
+    inner: W,
+    options: CsvOptions,
+    column_names: Option<Vec<Vec<u8>>>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, CsvOptions::default())
+    }
+
+    pub fn with_options(inner: W, options: CsvOptions) -> Self {
+        Self {
+            inner,
+            options,
+            column_names: None,
+        }
+    }
+
+    /// Writes every row of the response. Responses without a data set are skipped.
+    pub fn write<D: AsDataSet>(&mut self, response: D) -> Result<(), IoError> {
+        match response.as_data_set() {
+            Some(data_set) => self.write_data_set(data_set),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_data_set(&mut self, data_set: &DataSet) -> Result<(), IoError> {
+        if data_set.column_names.is_empty() {
+            return Ok(());
+        }
+
+        match &self.column_names {
+            Some(column_names) => {
+                if column_names != &data_set.column_names {
+                    return Err(IoError::new(
+                        IoErrorKind::InvalidData,
+                        "column names differ from the ones already written",
+                    ));
+                }
+            }
+            None => {
+                if self.options.header {
+                    let names: Vec<String> = data_set
+                        .column_names
+                        .iter()
+                        .map(|x| String::from_utf8_lossy(x).to_string())
+                        .collect();
+                    self.write_record(names.iter().map(|x| x.as_str()))?;
+                }
+                self.column_names = Some(data_set.column_names.clone());
+            }
+        }
+
+        for row in data_set.rows.iter() {
+            if row.values.len() != data_set.column_names.len() {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    format!(
+                        "row has {} values but there are {} columns",
+                        row.values.len(),
+                        data_set.column_names.len()
+                    ),
+                ));
+            }
+
+            self.write_row(&row.values)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), IoError> {
+        let fields: Vec<String> = values
+            .iter()
+            .map(|value| {
+                if is_null(value) {
+                    self.options.null.clone()
+                } else {
+                    Plain(value).to_string()
+                }
+            })
+            .collect();
+
+        self.write_record(fields.iter().map(|x| x.as_str()))
+    }
+
+    fn write_record<'a>(&mut self, fields: impl Iterator<Item = &'a str>) -> Result<(), IoError> {
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                self.inner.write_all(&[self.options.delimiter])?;
+            }
+            self.write_field(field)?;
+        }
+        self.inner.write_all(b"\r\n")
+    }
+
+    fn write_field(&mut self, field: &str) -> Result<(), IoError> {
+        let delimiter = self.options.delimiter;
+        let needs_quotes = self.options.quote_all
+            || field
+                .bytes()
+                .any(|b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r');
+
+        if needs_quotes {
+            self.inner.write_all(b"\"")?;
+            self.inner
+                .write_all(field.replace('"', "\"\"").as_bytes())?;
+            self.inner.write_all(b"\"")
+        } else {
+            self.inner.write_all(field.as_bytes())
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), IoError> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(mut self) -> Result<W, IoError> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::{
+        dependencies::common::types::{NullType, Row},
+        ExecutionResponse,
+    };
+    use nebula_fbthrift_storage::v3::ScanResponse;
+
+    fn data_set(names: Vec<&str>, rows: Vec<Vec<Value>>) -> DataSet {
+        DataSet {
+            column_names: names.into_iter().map(|x| x.as_bytes().to_vec()).collect(),
+            rows: rows
+                .into_iter()
+                .map(|values| Row {
+                    values,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn with_execution_response() -> Result<(), Box<dyn std::error::Error>> {
+        let res = ExecutionResponse {
+            data: Some(data_set(
+                vec!["name", "age"],
+                vec![
+                    vec![Value::sVal(b"Tim".to_vec()), Value::iVal(42)],
+                    vec![
+                        Value::sVal(b"a,\"b\"".to_vec()),
+                        Value::nVal(NullType::__NULL__),
+                    ],
+                ],
+            )),
+            ..Default::default()
+        };
+
+        let mut writer = CsvWriter::with_options(
+            vec![],
+            CsvOptions {
+                null: "\\N".to_owned(),
+                ..Default::default()
+            },
+        );
+        writer.write(&res)?;
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()?)?,
+            "name,age\r\nTim,42\r\n\"a,\"\"b\"\"\",\\N\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_scan_responses() -> Result<(), Box<dyn std::error::Error>> {
+        let part = |vid: &str| ScanResponse {
+            props: Some(data_set(
+                vec!["_vid", "player.name"],
+                vec![vec![
+                    Value::sVal(vid.as_bytes().to_vec()),
+                    Value::sVal(b"x".to_vec()),
+                ]],
+            )),
+            ..Default::default()
+        };
+
+        let mut writer = CsvWriter::new(vec![]);
+        writer.write(part("p1"))?;
+        writer.write(part("p2"))?;
+        writer.write(ScanResponse::default())?;
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()?)?,
+            "_vid,player.name\r\np1,x\r\np2,x\r\n"
+        );
+
+        let mut writer = CsvWriter::new(vec![]);
+        writer.write(part("p1"))?;
+        let other = data_set(vec!["_vid"], vec![]);
+        assert_eq!(
+            writer.write(&other).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+
+        let mut writer = CsvWriter::new(vec![]);
+        let short = data_set(vec!["_vid", "player.name"], vec![vec![Value::iVal(1)]]);
+        assert_eq!(
+            writer.write(&short).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+
+        Ok(())
+    }
+}
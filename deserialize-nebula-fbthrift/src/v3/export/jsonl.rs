@@ -0,0 +1,258 @@
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
+
+use nebula_fbthrift_graph::v3::dependencies::common::types::{
+    DataSet, Geography, NullType, Tag, Value, Vertex,
+};
+use serde_json::{json, Map, Value as JsonValue};
+
+use super::AsDataSet;
+use crate::v3::literal::Literal;
+
+/// Streams rows as JSON Lines, one object per row keyed by column name.
+///
+/// Values that have no JSON counterpart keep their type through a tagged
+/// object, e.g. `{"type":"date","value":"2020-01-02"}` or
+/// `{"type":"vertex","vid":"p1","tags":{"player":{"name":"Tim"}}}`.
+/// Keys follow the column order of the data set.
+pub struct JsonLinesWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Writes every row of the response. Responses without a data set are skipped.
+    pub fn write<D: AsDataSet>(&mut self, response: D) -> Result<(), IoError> {
+        match response.as_data_set() {
+            Some(data_set) => self.write_data_set(data_set),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_data_set(&mut self, data_set: &DataSet) -> Result<(), IoError> {
+        let names: Vec<String> = data_set
+            .column_names
+            .iter()
+            .map(|x| String::from_utf8_lossy(x).to_string())
+            .collect();
+
+        for row in data_set.rows.iter() {
+            if row.values.len() != names.len() {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    format!(
+                        "row has {} values but there are {} columns",
+                        row.values.len(),
+                        names.len()
+                    ),
+                ));
+            }
+
+            // Written key by key so the column order survives without serde_json's preserve_order.
+            self.inner.write_all(b"{")?;
+            for (i, (name, value)) in names.iter().zip(row.values.iter()).enumerate() {
+                if i > 0 {
+                    self.inner.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut self.inner, name)?;
+                self.inner.write_all(b":")?;
+                serde_json::to_writer(&mut self.inner, &to_json(value))?;
+            }
+            self.inner.write_all(b"}\n")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), IoError> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(mut self) -> Result<W, IoError> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Converts a [`Value`] into its type-preserving JSON encoding.
+pub fn to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::nVal(NullType::__NULL__) | Value::UnknownField(_) => JsonValue::Null,
+        Value::nVal(_) => json!({ "type": "null", "value": Literal(value).to_string() }),
+        Value::bVal(v) => JsonValue::Bool(*v),
+        Value::iVal(v) => JsonValue::from(*v),
+        Value::fVal(v) => match serde_json::Number::from_f64(v.0) {
+            Some(n) => JsonValue::Number(n),
+            None => json!({ "type": "float", "value": v.0.to_string() }),
+        },
+        Value::sVal(v) => JsonValue::String(String::from_utf8_lossy(v).to_string()),
+        Value::dVal(_) => tagged("date", value),
+        Value::tVal(_) => tagged("time", value),
+        Value::dtVal(_) => tagged("datetime", value),
+        Value::duVal(v) => json!({
+            "type": "duration",
+            "months": v.months,
+            "seconds": v.seconds,
+            "microseconds": v.microseconds,
+        }),
+        Value::vVal(v) => vertex_to_json(v),
+        Value::eVal(v) => json!({
+            "type": "edge",
+            "src": to_json(&v.src),
+            "dst": to_json(&v.dst),
+            "edge_type": v.r#type,
+            "name": String::from_utf8_lossy(&v.name),
+            "rank": v.ranking,
+            "props": props_to_json(v.props.iter()),
+        }),
+        Value::pVal(v) => json!({
+            "type": "path",
+            "src": vertex_to_json(&v.src),
+            "steps": v.steps.iter().map(|step| json!({
+                "edge_type": step.r#type,
+                "name": String::from_utf8_lossy(&step.name),
+                "rank": step.ranking,
+                "props": props_to_json(step.props.iter()),
+                "dst": vertex_to_json(&step.dst),
+            })).collect::<Vec<_>>(),
+        }),
+        Value::lVal(v) => JsonValue::Array(v.values.iter().map(to_json).collect()),
+        Value::uVal(v) => json!({
+            "type": "set",
+            "values": v.values.iter().map(to_json).collect::<Vec<_>>(),
+        }),
+        Value::mVal(v) => json!({
+            "type": "map",
+            "values": props_to_json(v.kvs.iter()),
+        }),
+        Value::gVal(v) => json!({
+            "type": "dataset",
+            "columns": v.column_names.iter().map(|x| String::from_utf8_lossy(x)).collect::<Vec<_>>(),
+            "rows": v.rows.iter().map(|row| row.values.iter().map(to_json).collect::<Vec<_>>()).collect::<Vec<_>>(),
+        }),
+        Value::ggVal(v) => json!({
+            "type": match v {
+                Geography::ptVal(_) => "point",
+                Geography::lsVal(_) => "linestring",
+                Geography::pgVal(_) => "polygon",
+                Geography::UnknownField(_) => "geography",
+            },
+            "wkt": Literal(value).to_string(),
+        }),
+    }
+}
+
+fn tagged(r#type: &str, value: &Value) -> JsonValue {
+    json!({ "type": r#type, "value": Literal(value).to_string() })
+}
+
+fn props_to_json<'a>(props: impl Iterator<Item = (&'a Vec<u8>, &'a Value)>) -> JsonValue {
+    JsonValue::Object(
+        props
+            .map(|(k, v)| (String::from_utf8_lossy(k).to_string(), to_json(v)))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+fn tag_to_json(tag: &Tag) -> (String, JsonValue) {
+    (
+        String::from_utf8_lossy(&tag.name).to_string(),
+        props_to_json(tag.props.iter()),
+    )
+}
+
+fn vertex_to_json(v: &Vertex) -> JsonValue {
+    json!({
+        "type": "vertex",
+        "vid": to_json(&v.vid),
+        "tags": JsonValue::Object(v.tags.iter().map(tag_to_json).collect()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use nebula_fbthrift_graph::v3::{
+        dependencies::common::{
+            double::Double,
+            types::{Date, Edge, NMap, Row},
+        },
+        ExecutionResponse,
+    };
+
+    #[test]
+    fn with_execution_response() -> Result<(), Box<dyn std::error::Error>> {
+        let vertex = Vertex {
+            vid: Box::new(Value::sVal(b"p1".to_vec())),
+            tags: vec![Tag {
+                name: b"player".to_vec(),
+                props: BTreeMap::from([(b"age".to_vec(), Value::iVal(42))]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let edge = Edge {
+            src: Box::new(Value::iVal(1)),
+            dst: Box::new(Value::iVal(2)),
+            r#type: 3,
+            name: b"serve".to_vec(),
+            ranking: 0,
+            props: BTreeMap::new(),
+            ..Default::default()
+        };
+        let res = ExecutionResponse {
+            data: Some(DataSet {
+                column_names: vec![
+                    b"v".to_vec(),
+                    b"e".to_vec(),
+                    b"d".to_vec(),
+                    b"f".to_vec(),
+                    b"m".to_vec(),
+                ],
+                rows: vec![Row {
+                    values: vec![
+                        Value::vVal(Box::new(vertex)),
+                        Value::eVal(edge),
+                        Value::dVal(Date {
+                            year: 2020,
+                            month: 1,
+                            day: 2,
+                            ..Default::default()
+                        }),
+                        Value::fVal(Double(1.5)),
+                        // A `type` key does not make it look like a tagged value.
+                        Value::mVal(NMap {
+                            kvs: BTreeMap::from([(b"type".to_vec(), Value::sVal(b"x".to_vec()))]),
+                            ..Default::default()
+                        }),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut writer = JsonLinesWriter::new(vec![]);
+        writer.write(&res)?;
+        let output = String::from_utf8(writer.into_inner()?)?;
+
+        assert_eq!(
+            output,
+            concat!(
+                r#"{"v":{"tags":{"player":{"age":42}},"type":"vertex","vid":"p1"},"#,
+                r#""e":{"dst":2,"edge_type":3,"name":"serve","props":{},"rank":0,"src":1,"type":"edge"},"#,
+                r#""d":{"type":"date","value":"2020-01-02"},"f":1.5,"#,
+                r#""m":{"type":"map","values":{"type":"x"}}}"#,
+                "\n"
+            )
+        );
+
+        Ok(())
+    }
+}
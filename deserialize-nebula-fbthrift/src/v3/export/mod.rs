@@ -0,0 +1,45 @@
+//! Writers that stream a [`DataSet`] out as CSV or JSON Lines.
+//!
+//! Both writers accept anything implementing [`AsDataSet`], so the same code
+//! exports `ExecutionResponse`s from graphd and the per-partition
+//! `ScanResponse`s from storaged. The header is written once, so several
+//! responses (e.g. one per partition) can be appended to the same output.
+
+pub mod csv;
+pub use self::csv::{CsvOptions, CsvWriter};
+
+#[cfg(feature = "json")]
+pub mod jsonl;
+#[cfg(feature = "json")]
+pub use self::jsonl::JsonLinesWriter;
+
+use nebula_fbthrift_graph::v3::{dependencies::common::types::DataSet, ExecutionResponse};
+use nebula_fbthrift_storage::v3::ScanResponse;
+
+pub trait AsDataSet {
+    fn as_data_set(&self) -> Option<&DataSet>;
+}
+
+impl AsDataSet for DataSet {
+    fn as_data_set(&self) -> Option<&DataSet> {
+        Some(self)
+    }
+}
+
+impl AsDataSet for ExecutionResponse {
+    fn as_data_set(&self) -> Option<&DataSet> {
+        self.data.as_ref()
+    }
+}
+
+impl AsDataSet for ScanResponse {
+    fn as_data_set(&self) -> Option<&DataSet> {
+        self.props.as_ref()
+    }
+}
+
+impl<T: AsDataSet> AsDataSet for &T {
+    fn as_data_set(&self) -> Option<&DataSet> {
+        (*self).as_data_set()
+    }
+}
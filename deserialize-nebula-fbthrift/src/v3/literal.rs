@@ -0,0 +1,348 @@
+use core::fmt::{self, Display, Formatter, Write as _};
+
+use nebula_fbthrift_graph::v3::dependencies::common::types::{
+    Coordinate, DataSet, Date, DateTime, Duration, Edge, Geography, NullType, Path, Tag, Time,
+    Value, Vertex,
+};
+
+/// Formats a [`Value`] in nGQL literal syntax, the way nebula-console prints it.
+///
+/// Strings are quoted, vertices render as `("vid" :tag{prop: value})`, edges as
+/// `[:edge "src"->"dst" @rank {prop: value}]` and paths as `<(..)-[..]->(..)>`.
+pub struct Literal<'a>(pub &'a Value);
+
+impl Display for Literal<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_value(self.0, f)
+    }
+}
+
+/// Same as [`Literal`], but a top-level string is written as-is instead of quoted.
+///
+/// This is what the CSV writer and the table renderer use for cells.
+pub struct Plain<'a>(pub &'a Value);
+
+impl Display for Plain<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Value::sVal(v) => f.write_str(&String::from_utf8_lossy(v)),
+            v => fmt_value(v, f),
+        }
+    }
+}
+
+pub fn is_null(value: &Value) -> bool {
+    matches!(value, Value::nVal(_) | Value::UnknownField(_))
+}
+
+fn fmt_value(value: &Value, f: &mut Formatter) -> fmt::Result {
+    match value {
+        Value::nVal(v) => fmt_null(v, f),
+        Value::bVal(v) => write!(f, "{v}"),
+        Value::iVal(v) => write!(f, "{v}"),
+        Value::fVal(v) => write!(f, "{:?}", v.0),
+        Value::sVal(v) => fmt_string(v, f),
+        Value::dVal(v) => fmt_date(v, f),
+        Value::tVal(v) => fmt_time(v, f),
+        Value::dtVal(v) => fmt_datetime(v, f),
+        Value::vVal(v) => fmt_vertex(v, f),
+        Value::eVal(v) => fmt_edge(v, f),
+        Value::pVal(v) => fmt_path(v, f),
+        Value::lVal(v) => fmt_seq(v.values.iter(), '[', ']', f),
+        Value::uVal(v) => fmt_seq(v.values.iter(), '{', '}', f),
+        Value::mVal(v) => {
+            f.write_char('{')?;
+            for (i, (k, v)) in v.kvs.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}: ", String::from_utf8_lossy(k))?;
+                fmt_value(v, f)?;
+            }
+            f.write_char('}')
+        }
+        Value::gVal(v) => fmt_data_set(v, f),
+        Value::ggVal(v) => fmt_geography(v, f),
+        Value::duVal(v) => fmt_duration(v, f),
+        Value::UnknownField(_) => f.write_str("__EMPTY__"),
+    }
+}
+
+fn fmt_null(null: &NullType, f: &mut Formatter) -> fmt::Result {
+    match *null {
+        NullType::__NULL__ => f.write_str("NULL"),
+        NullType::NaN => f.write_str("NaN"),
+        NullType::BAD_DATA => f.write_str("BAD_DATA"),
+        NullType::BAD_TYPE => f.write_str("BAD_TYPE"),
+        NullType::ERR_OVERFLOW => f.write_str("ERR_OVERFLOW"),
+        NullType::UNKNOWN_PROP => f.write_str("UNKNOWN_PROP"),
+        NullType::DIV_BY_ZERO => f.write_str("DIV_BY_ZERO"),
+        NullType::OUT_OF_RANGE => f.write_str("OUT_OF_RANGE"),
+        _ => f.write_str("NULL"),
+    }
+}
+
+fn fmt_string(bytes: &[u8], f: &mut Formatter) -> fmt::Result {
+    f.write_char('"')?;
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+pub(crate) fn fmt_date(v: &Date, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}-{:02}-{:02}", v.year, v.month, v.day)
+}
+
+pub(crate) fn fmt_time(v: &Time, f: &mut Formatter) -> fmt::Result {
+    write!(
+        f,
+        "{:02}:{:02}:{:02}.{:06}",
+        v.hour, v.minute, v.sec, v.microsec
+    )
+}
+
+pub(crate) fn fmt_datetime(v: &DateTime, f: &mut Formatter) -> fmt::Result {
+    write!(
+        f,
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+        v.year, v.month, v.day, v.hour, v.minute, v.sec, v.microsec
+    )
+}
+
+pub(crate) fn fmt_duration(v: &Duration, f: &mut Formatter) -> fmt::Result {
+    write!(f, "P{}MT{}.{:06}S", v.months, v.seconds, v.microseconds)
+}
+
+fn fmt_props<'a>(
+    props: impl Iterator<Item = (&'a Vec<u8>, &'a Value)>,
+    f: &mut Formatter,
+) -> fmt::Result {
+    f.write_char('{')?;
+    for (i, (k, v)) in props.enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}: ", String::from_utf8_lossy(k))?;
+        fmt_value(v, f)?;
+    }
+    f.write_char('}')
+}
+
+fn fmt_tag(tag: &Tag, f: &mut Formatter) -> fmt::Result {
+    write!(f, " :{}", String::from_utf8_lossy(&tag.name))?;
+    fmt_props(tag.props.iter(), f)
+}
+
+fn fmt_vertex(v: &Vertex, f: &mut Formatter) -> fmt::Result {
+    f.write_char('(')?;
+    fmt_value(&v.vid, f)?;
+    for tag in v.tags.iter() {
+        fmt_tag(tag, f)?;
+    }
+    f.write_char(')')
+}
+
+fn fmt_edge(v: &Edge, f: &mut Formatter) -> fmt::Result {
+    // A negative type means the edge was read in its reverse direction.
+    let (src, dst) = if v.r#type < 0 {
+        (&v.dst, &v.src)
+    } else {
+        (&v.src, &v.dst)
+    };
+
+    write!(f, "[:{} ", String::from_utf8_lossy(&v.name))?;
+    fmt_value(src, f)?;
+    f.write_str("->")?;
+    fmt_value(dst, f)?;
+    write!(f, " @{} ", v.ranking)?;
+    fmt_props(v.props.iter(), f)?;
+    f.write_char(']')
+}
+
+fn fmt_path(v: &Path, f: &mut Formatter) -> fmt::Result {
+    f.write_char('<')?;
+    fmt_vertex(&v.src, f)?;
+    for step in v.steps.iter() {
+        let name = String::from_utf8_lossy(&step.name);
+        if step.r#type < 0 {
+            write!(f, "<-[:{}@{} ", name, step.ranking)?;
+            fmt_props(step.props.iter(), f)?;
+            f.write_str("]-")?;
+        } else {
+            write!(f, "-[:{}@{} ", name, step.ranking)?;
+            fmt_props(step.props.iter(), f)?;
+            f.write_str("]->")?;
+        }
+        fmt_vertex(&step.dst, f)?;
+    }
+    f.write_char('>')
+}
+
+fn fmt_seq<'a>(
+    values: impl Iterator<Item = &'a Value>,
+    open: char,
+    close: char,
+    f: &mut Formatter,
+) -> fmt::Result {
+    f.write_char(open)?;
+    for (i, v) in values.enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        fmt_value(v, f)?;
+    }
+    f.write_char(close)
+}
+
+fn fmt_data_set(v: &DataSet, f: &mut Formatter) -> fmt::Result {
+    f.write_char('[')?;
+    for (i, row) in v.rows.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        fmt_seq(row.values.iter(), '[', ']', f)?;
+    }
+    f.write_char(']')
+}
+
+fn fmt_coordinate(v: &Coordinate, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} {}", v.x.0, v.y.0)
+}
+
+fn fmt_coordinates(v: &[Coordinate], f: &mut Formatter) -> fmt::Result {
+    for (i, c) in v.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        fmt_coordinate(c, f)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn fmt_geography(v: &Geography, f: &mut Formatter) -> fmt::Result {
+    match v {
+        Geography::ptVal(v) => {
+            f.write_str("POINT(")?;
+            fmt_coordinate(&v.coord, f)?;
+            f.write_char(')')
+        }
+        Geography::lsVal(v) => {
+            f.write_str("LINESTRING(")?;
+            fmt_coordinates(&v.coordList, f)?;
+            f.write_char(')')
+        }
+        Geography::pgVal(v) => {
+            f.write_str("POLYGON(")?;
+            for (i, ring) in v.coordListList.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                f.write_char('(')?;
+                fmt_coordinates(ring, f)?;
+                f.write_char(')')?;
+            }
+            f.write_char(')')
+        }
+        Geography::UnknownField(_) => f.write_str("__EMPTY__"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use nebula_fbthrift_graph::v3::dependencies::common::{double::Double, types::Step};
+
+    fn player(vid: &str, name: &str) -> Vertex {
+        Vertex {
+            vid: Box::new(Value::sVal(vid.as_bytes().to_vec())),
+            tags: vec![Tag {
+                name: b"player".to_vec(),
+                props: BTreeMap::from([(b"name".to_vec(), Value::sVal(name.as_bytes().to_vec()))]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn with_scalars() {
+        assert_eq!(
+            Literal(&Value::nVal(NullType::__NULL__)).to_string(),
+            "NULL"
+        );
+        assert_eq!(Literal(&Value::iVal(1)).to_string(), "1");
+        assert_eq!(Literal(&Value::fVal(Double(1_f64))).to_string(), "1.0");
+        assert_eq!(
+            Literal(&Value::sVal(b"a \"b\"".to_vec())).to_string(),
+            r#""a \"b\"""#
+        );
+        assert_eq!(
+            Plain(&Value::sVal(b"a \"b\"".to_vec())).to_string(),
+            "a \"b\""
+        );
+        assert_eq!(
+            Literal(&Value::dtVal(DateTime {
+                year: 2020,
+                month: 1,
+                day: 2,
+                hour: 3,
+                minute: 4,
+                sec: 5,
+                microsec: 6,
+                ..Default::default()
+            }))
+            .to_string(),
+            "2020-01-02T03:04:05.000006"
+        );
+    }
+
+    #[test]
+    fn with_vertex_edge_path() {
+        assert_eq!(
+            Literal(&Value::vVal(Box::new(player("p1", "Tim")))).to_string(),
+            r#"("p1" :player{name: "Tim"})"#
+        );
+
+        let edge = Edge {
+            src: Box::new(Value::sVal(b"p1".to_vec())),
+            dst: Box::new(Value::sVal(b"p2".to_vec())),
+            r#type: 1,
+            name: b"follow".to_vec(),
+            ranking: 0,
+            props: BTreeMap::from([(b"degree".to_vec(), Value::iVal(95))]),
+            ..Default::default()
+        };
+        assert_eq!(
+            Literal(&Value::eVal(edge)).to_string(),
+            r#"[:follow "p1"->"p2" @0 {degree: 95}]"#
+        );
+
+        let path = Path {
+            src: player("p1", "Tim"),
+            steps: vec![Step {
+                dst: player("p2", "Tony"),
+                r#type: -1,
+                name: b"follow".to_vec(),
+                ranking: 0,
+                props: BTreeMap::new(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            Literal(&Value::pVal(path)).to_string(),
+            r#"<("p1" :player{name: "Tim"})<-[:follow@0 {}]-("p2" :player{name: "Tony"})>"#
+        );
+    }
+}
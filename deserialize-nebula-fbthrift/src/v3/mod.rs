@@ -1 +1,5 @@
 pub mod de;
+
+pub mod export;
+pub mod literal;
+pub mod table;
@@ -0,0 +1,360 @@
+//! Renders a [`DataSet`] as an aligned box table, the way nebula-console prints results.
+//!
+//! `DataSet`, `ExecutionResponse` and `ScanResponse` are foreign types, so the
+//! table is a [`Display`] wrapper obtained with [`DisplayTable::table`]:
+//!
+//! ```ignore
+//! use deserialize_nebula_fbthrift::v3::table::DisplayTable as _;
+//!
+//! println!("{}", res.table());
+//! ```
+//!
+//! ```text
+//! +--------------+-----+
+//! | name         | age |
+//! +--------------+-----+
+//! | Tim Duncan   | 42  |
+//! | Tony Parker  | 36  |
+//! +--------------+-----+
+//! ```
+
+use core::fmt::{self, Display, Formatter};
+
+use nebula_fbthrift_graph::v3::dependencies::common::types::{DataSet, NullType, Value};
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
+
+use super::{export::AsDataSet, literal::Plain};
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Cut the cell and end it with `…`.
+    Truncate,
+    /// Continue the cell on the next lines.
+    Wrap,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    /// Maximum width of a column, in terminal cells. Default 64.
+    pub max_column_width: Option<usize>,
+    /// Maximum width of the whole table. The widest columns shrink to fit,
+    /// down to a few cells each.
+    pub max_width: Option<usize>,
+    pub overflow: Overflow,
+    /// Written for `NULL` values. Default `__NULL__`.
+    pub null: String,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            max_column_width: Some(64),
+            max_width: None,
+            overflow: Overflow::Truncate,
+            null: "__NULL__".to_owned(),
+        }
+    }
+}
+
+const MIN_COLUMN_WIDTH: usize = 3;
+
+//
+pub struct Table<'a> {
+    data_set: Option<&'a DataSet>,
+    options: TableOptions,
+}
+
+impl<'a> Table<'a> {
+    pub fn new<D: AsDataSet + ?Sized>(response: &'a D) -> Self {
+        Self {
+            data_set: response.as_data_set(),
+            options: TableOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: TableOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn max_column_width(mut self, width: impl Into<Option<usize>>) -> Self {
+        self.options.max_column_width = width.into();
+        self
+    }
+
+    pub fn max_width(mut self, width: impl Into<Option<usize>>) -> Self {
+        self.options.max_width = width.into();
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.options.overflow = overflow;
+        self
+    }
+
+    pub fn null(mut self, null: impl Into<String>) -> Self {
+        self.options.null = null.into();
+        self
+    }
+
+    fn cell(&self, value: &Value) -> String {
+        match value {
+            Value::nVal(NullType::__NULL__) => self.options.null.clone(),
+            value => Plain(value).to_string(),
+        }
+    }
+
+    fn widths(&self, header: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+        let mut widths: Vec<usize> = header.iter().map(|x| text_width(x)).collect();
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(text_width(cell));
+            }
+        }
+
+        if let Some(max) = self.options.max_column_width {
+            let max = max.max(MIN_COLUMN_WIDTH);
+            widths.iter_mut().for_each(|x| *x = (*x).min(max));
+        }
+
+        if let Some(max_width) = self.options.max_width {
+            // `| a | b |` takes 3 cells per column plus one.
+            let budget = max_width.saturating_sub(3 * widths.len() + 1);
+            while widths.iter().sum::<usize>() > budget {
+                match widths.iter_mut().filter(|x| **x > MIN_COLUMN_WIDTH).max() {
+                    Some(widest) => *widest -= 1,
+                    None => break,
+                }
+            }
+        }
+
+        widths
+    }
+}
+
+impl Display for Table<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let data_set = match self.data_set {
+            Some(data_set) if !data_set.column_names.is_empty() => data_set,
+            _ => return Ok(()),
+        };
+
+        let header: Vec<String> = data_set
+            .column_names
+            .iter()
+            .map(|x| String::from_utf8_lossy(x).into_owned())
+            .collect();
+        let rows: Vec<Vec<String>> = data_set
+            .rows
+            .iter()
+            .map(|row| row.values.iter().map(|x| self.cell(x)).collect())
+            .collect();
+        let widths = self.widths(&header, &rows);
+
+        let separator = separator(&widths);
+        f.write_str(&separator)?;
+        self.fmt_row(f, &widths, &header)?;
+        f.write_str(&separator)?;
+        if !rows.is_empty() {
+            for row in rows.iter() {
+                self.fmt_row(f, &widths, row)?;
+            }
+            f.write_str(&separator)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Table<'_> {
+    fn fmt_row(&self, f: &mut Formatter, widths: &[usize], row: &[String]) -> fmt::Result {
+        let cells: Vec<Vec<String>> = widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| {
+                let cell = row.get(i).map(|x| x.as_str()).unwrap_or_default();
+                cell.lines()
+                    .flat_map(|line| match self.options.overflow {
+                        Overflow::Truncate => vec![truncate(line, *width)],
+                        Overflow::Wrap => wrap(line, *width),
+                    })
+                    .collect()
+            })
+            .collect();
+        let height = cells.iter().map(|x| x.len()).max().unwrap_or(0).max(1);
+
+        for line in 0..height {
+            f.write_str("|")?;
+            for (cell, width) in cells.iter().zip(widths.iter()) {
+                let text = cell.get(line).map(|x| x.as_str()).unwrap_or_default();
+                write!(f, " {text}{} |", " ".repeat(width.saturating_sub(text.width())))?;
+            }
+            f.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds [`table`](DisplayTable::table) to `DataSet`, `ExecutionResponse` and `ScanResponse`.
+pub trait DisplayTable: AsDataSet {
+    fn table(&self) -> Table<'_> {
+        Table::new(self)
+    }
+}
+
+impl<T: AsDataSet + ?Sized> DisplayTable for T {}
+
+//
+fn separator(widths: &[usize]) -> String {
+    let mut out = String::from("+");
+    for width in widths {
+        out.push_str(&"-".repeat(width + 2));
+        out.push('+');
+    }
+    out.push('\n');
+    out
+}
+
+fn text_width(text: &str) -> usize {
+    text.lines().map(|x| x.width()).max().unwrap_or(0)
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    if line.width() <= width {
+        return line.to_owned();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for c in line.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width - 1 {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut out = vec![String::new()];
+    let mut used = 0;
+    for c in line.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            out.push(String::new());
+            used = 0;
+        }
+        out.last_mut().expect("not empty").push(c);
+        used += w;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::{
+        dependencies::common::types::{Row, Vertex},
+        ExecutionResponse,
+    };
+
+    fn data_set(names: Vec<&str>, rows: Vec<Vec<Value>>) -> DataSet {
+        DataSet {
+            column_names: names.into_iter().map(|x| x.as_bytes().to_vec()).collect(),
+            rows: rows
+                .into_iter()
+                .map(|values| Row {
+                    values,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn with_execution_response() {
+        let res = ExecutionResponse {
+            data: Some(data_set(
+                vec!["name", "age", "v"],
+                vec![
+                    vec![
+                        Value::sVal("Tim Duncan".as_bytes().to_vec()),
+                        Value::iVal(42),
+                        Value::vVal(Box::new(Vertex {
+                            vid: Box::new(Value::sVal(b"p1".to_vec())),
+                            ..Default::default()
+                        })),
+                    ],
+                    vec![
+                        Value::sVal("姚明".as_bytes().to_vec()),
+                        Value::nVal(NullType::__NULL__),
+                        Value::nVal(NullType::BAD_TYPE),
+                    ],
+                ],
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            res.table().to_string(),
+            "\
++------------+----------+----------+
+| name       | age      | v        |
++------------+----------+----------+
+| Tim Duncan | 42       | (\"p1\")   |
+| 姚明       | __NULL__ | BAD_TYPE |
++------------+----------+----------+
+"
+        );
+
+        assert_eq!(ExecutionResponse::default().table().to_string(), "");
+    }
+
+    #[test]
+    fn with_limits() {
+        let ds = data_set(
+            vec!["id", "description"],
+            vec![vec![
+                Value::iVal(1),
+                Value::sVal(b"abcdefghij\nxy".to_vec()),
+            ]],
+        );
+
+        assert_eq!(
+            ds.table().max_column_width(6).to_string(),
+            "\
++----+--------+
+| id | descr… |
++----+--------+
+| 1  | abcde… |
+|    | xy     |
++----+--------+
+"
+        );
+
+        assert_eq!(
+            ds.table()
+                .max_width(16)
+                .overflow(Overflow::Wrap)
+                .null("-")
+                .to_string(),
+            "\
++----+---------+
+| id | descrip |
+|    | tion    |
++----+---------+
+| 1  | abcdefg |
+|    | hij     |
+|    | xy      |
++----+---------+
+"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+// Lets the derive macros' `::nebula_client` paths resolve inside this crate too.
+extern crate self as nebula_client;
+
 //
 pub mod v3;
 
@@ -0,0 +1,504 @@
+//! Synchronous clients, for tools that do not run an async runtime.
+//!
+//! ```ignore
+//! use nebula_client::v3::blocking::BlockingGraphClient;
+//!
+//! let mut session = BlockingGraphClient::connect("127.0.0.1:9669")?
+//!     .authenticate(&b"root".to_vec(), &b"nebula".to_vec())?;
+//! let spaces = session.show_spaces()?;
+//! ```
+//!
+//! Each client owns a current-thread tokio runtime, shared with the session it
+//! authenticates, and blocks on the async client it wraps. They can be moved
+//! between threads, but their methods panic when called from within an async
+//! runtime; use the async clients there.
+
+use std::{io::Error as IoError, sync::Arc};
+
+use bytes::Bytes;
+use fbthrift::{BinaryProtocol, Transport};
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Result<Arc<Runtime>, IoError> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map(Arc::new)
+}
+
+//
+//
+//
+#[cfg(feature = "graph")]
+pub use self::graph::{BlockingGraphClient, BlockingGraphSession, GraphTransport};
+
+#[cfg(feature = "graph")]
+mod graph {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::{
+        errors::graph_service::{AuthenticateError, ExecuteError, ExecuteJsonError, SignoutError},
+        types::ExecutionResponse,
+    };
+    use serde::de::DeserializeOwned;
+
+    use crate::v3::{
+        graph::{
+            query::{Host, Space},
+            GraphInterceptor, GraphQueryError, GraphQueryOutput,
+        },
+        GraphClient, GraphQuery as _, GraphSession, GraphTransportResponseHandler, TraceConfig,
+    };
+
+    /// The transport of [`BlockingGraphClient::connect`].
+    pub type GraphTransport = crate::v3::graph::TokioGraphTransport;
+
+    /// A blocking [`GraphClient`].
+    pub struct BlockingGraphClient<T>
+    where
+        T: Transport,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        runtime: Arc<Runtime>,
+        client: GraphClient<T>,
+    }
+
+    impl BlockingGraphClient<GraphTransport> {
+        /// Connects to graphd at `addr` (`host:port`).
+        pub fn connect(addr: impl Into<String>) -> Result<Self, IoError> {
+            let addr = addr.into();
+            let runtime = runtime()?;
+            let transport = runtime.block_on(AsyncTransport::with_tokio_tcp_connect(
+                addr.clone(),
+                AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+            ))?;
+
+            Ok(Self {
+                runtime,
+                client: GraphClient::new(transport).with_addr(addr),
+            })
+        }
+    }
+
+    impl<T> BlockingGraphClient<T>
+    where
+        T: Transport + Send + Sync,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        /// For transports that do not need a runtime to be created, such as mocks.
+        pub fn new(client: GraphClient<T>) -> Result<Self, IoError> {
+            Ok(Self {
+                runtime: runtime()?,
+                client,
+            })
+        }
+
+        pub fn with_trace_config(mut self, trace_config: TraceConfig) -> Self {
+            self.client = self.client.with_trace_config(trace_config);
+            self
+        }
+
+        pub fn with_interceptor(mut self, interceptor: Arc<dyn GraphInterceptor>) -> Self {
+            self.client = self.client.with_interceptor(interceptor);
+            self
+        }
+
+        #[allow(clippy::ptr_arg)]
+        pub fn authenticate(
+            self,
+            username: &Vec<u8>,
+            password: &Vec<u8>,
+        ) -> Result<BlockingGraphSession<T>, AuthenticateError> {
+            let session = self
+                .runtime
+                .block_on(self.client.authenticate(username, password))?;
+
+            Ok(BlockingGraphSession {
+                runtime: self.runtime,
+                session,
+            })
+        }
+    }
+
+    /// A blocking [`GraphSession`].
+    pub struct BlockingGraphSession<T>
+    where
+        T: Transport,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        runtime: Arc<Runtime>,
+        session: GraphSession<T>,
+    }
+
+    impl<T> BlockingGraphSession<T>
+    where
+        T: Transport + Send + Sync,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        pub fn session_id(&self) -> i64 {
+            self.session.session_id()
+        }
+
+        pub fn space_name(&self) -> Option<&[u8]> {
+            self.session.space_name()
+        }
+
+        /// Returns once graphd handled the signout, unlike the one-way async `signout`.
+        pub fn signout(self) -> Result<(), SignoutError> {
+            self.runtime.block_on(self.session.signout_and_wait())
+        }
+
+        #[allow(clippy::ptr_arg)]
+        pub fn execute(&mut self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError> {
+            self.runtime.block_on(self.session.execute(stmt))
+        }
+
+        #[allow(clippy::ptr_arg)]
+        pub fn execute_json(&mut self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError> {
+            self.runtime.block_on(self.session.execute_json(stmt))
+        }
+
+        #[allow(clippy::ptr_arg)]
+        pub fn query_as<D: DeserializeOwned>(
+            &mut self,
+            stmt: &Vec<u8>,
+        ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+            self.runtime.block_on(self.session.query_as(stmt))
+        }
+
+        #[allow(clippy::ptr_arg)]
+        pub fn query(&mut self, stmt: &Vec<u8>) -> Result<GraphQueryOutput<()>, GraphQueryError> {
+            self.runtime.block_on(self.session.query(stmt))
+        }
+
+        #[cfg(feature = "json")]
+        #[allow(clippy::ptr_arg)]
+        pub fn query_json_as<D: DeserializeOwned>(
+            &mut self,
+            stmt: &Vec<u8>,
+        ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+            self.runtime.block_on(self.session.query_json_as(stmt))
+        }
+
+        pub fn show_hosts(&mut self) -> Result<GraphQueryOutput<Host>, GraphQueryError> {
+            self.runtime.block_on(self.session.show_hosts())
+        }
+
+        pub fn show_spaces(&mut self) -> Result<GraphQueryOutput<Space>, GraphQueryError> {
+            self.runtime.block_on(self.session.show_spaces())
+        }
+
+        pub fn is_close_required(&self) -> bool {
+            self.session.is_close_required()
+        }
+    }
+}
+
+//
+//
+//
+#[cfg(feature = "meta")]
+pub use self::meta::{BlockingMetaClient, MetaTransport};
+
+#[cfg(feature = "meta")]
+mod meta {
+    use super::*;
+
+    use nebula_fbthrift_meta::v3::{
+        errors::meta_service::{
+            GetPartsAllocError, GetSpaceError, ListEdgesError, ListPartsError, ListSpacesError,
+            ListTagsError,
+        },
+        types::{
+            GetPartsAllocResp, GetSpaceResp, ListEdgesResp, ListPartsResp, ListSpacesResp,
+            ListTagsResp,
+        },
+    };
+
+    use crate::v3::{MetaClient, MetaTransportResponseHandler};
+
+    /// The transport of [`BlockingMetaClient::connect`].
+    pub type MetaTransport = crate::v3::meta::TokioMetaTransport;
+
+    /// A blocking [`MetaClient`].
+    pub struct BlockingMetaClient<T>
+    where
+        T: Transport,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        runtime: Arc<Runtime>,
+        client: MetaClient<T>,
+    }
+
+    impl BlockingMetaClient<MetaTransport> {
+        /// Connects to metad at `addr` (`host:port`).
+        pub fn connect(addr: impl Into<String>) -> Result<Self, IoError> {
+            let addr = addr.into();
+            let runtime = runtime()?;
+            let transport = runtime.block_on(AsyncTransport::with_tokio_tcp_connect(
+                addr.clone(),
+                AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+            ))?;
+
+            Ok(Self {
+                runtime,
+                client: MetaClient::new(transport).with_addr(addr),
+            })
+        }
+    }
+
+    impl<T> BlockingMetaClient<T>
+    where
+        T: Transport,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        /// For transports that do not need a runtime to be created, such as mocks.
+        pub fn new(client: MetaClient<T>) -> Result<Self, IoError> {
+            Ok(Self {
+                runtime: runtime()?,
+                client,
+            })
+        }
+
+        pub fn list_spaces(&self) -> Result<ListSpacesResp, ListSpacesError> {
+            self.runtime.block_on(self.client.list_spaces())
+        }
+
+        pub fn get_space(&self, space_name: Vec<u8>) -> Result<GetSpaceResp, GetSpaceError> {
+            self.runtime.block_on(self.client.get_space(space_name))
+        }
+
+        pub fn list_parts(
+            &self,
+            space_id: i32,
+            part_ids: Vec<i32>,
+        ) -> Result<ListPartsResp, ListPartsError> {
+            self.runtime
+                .block_on(self.client.list_parts(space_id, part_ids))
+        }
+
+        pub fn list_tags(&self, space_id: i32) -> Result<ListTagsResp, ListTagsError> {
+            self.runtime.block_on(self.client.list_tags(space_id))
+        }
+
+        pub fn list_edges(&self, space_id: i32) -> Result<ListEdgesResp, ListEdgesError> {
+            self.runtime.block_on(self.client.list_edges(space_id))
+        }
+
+        pub fn get_parts(&self, space_id: i32) -> Result<GetPartsAllocResp, GetPartsAllocError> {
+            self.runtime.block_on(self.client.get_parts(space_id))
+        }
+    }
+}
+
+//
+//
+//
+#[cfg(feature = "storage")]
+pub use self::storage::{scan_edge, scan_vertex, BlockingStorageClient, StorageTransport};
+
+#[cfg(feature = "storage")]
+mod storage {
+    use super::*;
+
+    use nebula_fbthrift_storage::v3::{
+        errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
+        types::{ScanEdgeRequest, ScanResponse, ScanVertexRequest},
+    };
+    use serde::de::DeserializeOwned;
+
+    use crate::v3::{
+        storage::{scan::ScanError, StorageQuery as _, StorageQueryError, StorageQueryOutput},
+        StorageClient, StorageTransportResponseHandler,
+    };
+
+    /// The transport of [`BlockingStorageClient::connect`].
+    pub type StorageTransport = crate::v3::storage::TokioStorageTransport;
+
+    /// A blocking [`StorageClient`].
+    pub struct BlockingStorageClient<T>
+    where
+        T: Transport,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        runtime: Arc<Runtime>,
+        client: StorageClient<T>,
+    }
+
+    impl BlockingStorageClient<StorageTransport> {
+        /// Connects to storaged at `addr` (`host:port`).
+        pub fn connect(addr: impl Into<String>) -> Result<Self, IoError> {
+            let addr = addr.into();
+            let runtime = runtime()?;
+            let transport = runtime.block_on(AsyncTransport::with_tokio_tcp_connect(
+                addr.clone(),
+                AsyncTransportConfiguration::new(StorageTransportResponseHandler),
+            ))?;
+
+            Ok(Self {
+                runtime,
+                client: StorageClient::new(transport).with_addr(addr),
+            })
+        }
+    }
+
+    impl<T> BlockingStorageClient<T>
+    where
+        T: Transport + Send + Sync,
+        Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+        ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+            ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+    {
+        /// For transports that do not need a runtime to be created, such as mocks.
+        pub fn new(client: StorageClient<T>) -> Result<Self, IoError> {
+            Ok(Self {
+                runtime: runtime()?,
+                client,
+            })
+        }
+
+        pub fn scan_vertex(
+            &self,
+            req: &ScanVertexRequest,
+        ) -> Result<ScanResponse, ScanVertexError> {
+            self.runtime.block_on(self.client.scan_vertex(req))
+        }
+
+        pub fn scan_edge(&self, req: &ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeError> {
+            self.runtime.block_on(self.client.scan_edge(req))
+        }
+
+        pub fn query_vertex<D: DeserializeOwned>(
+            &mut self,
+            req: &ScanVertexRequest,
+        ) -> Result<StorageQueryOutput<D>, StorageQueryError> {
+            self.runtime.block_on(self.client.query_vertex(req))
+        }
+
+        pub fn query_edge<D: DeserializeOwned>(
+            &mut self,
+            req: &ScanEdgeRequest,
+        ) -> Result<StorageQueryOutput<D>, StorageQueryError> {
+            self.runtime.block_on(self.client.query_edge(req))
+        }
+    }
+
+    /// Blocking [`crate::v3::scan_vertex`], on a runtime of its own.
+    pub fn scan_vertex(
+        maddr: String,
+        space_name: String,
+        tag_name: String,
+    ) -> Result<Vec<Vec<String>>, ScanError> {
+        runtime()
+            .map_err(|e| ScanError::Custom(format!("Runtime Error: {}", e)))?
+            .block_on(crate::v3::scan_vertex(maddr, space_name, tag_name))
+    }
+
+    /// Blocking [`crate::v3::scan_edge`], on a runtime of its own.
+    pub fn scan_edge(
+        maddr: String,
+        space_name: String,
+        edge_name: String,
+    ) -> Result<Vec<Vec<String>>, ScanError> {
+        runtime()
+            .map_err(|e| ScanError::Custom(format!("Runtime Error: {}", e)))?
+            .block_on(crate::v3::scan_edge(maddr, space_name, edge_name))
+    }
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::dependencies::common::types::{PropertyType, Value};
+
+    use crate::v3::{
+        mock::{MockCluster, MockGraph, Reply},
+        MetaClient,
+    };
+
+    fn s(x: &str) -> Value {
+        Value::sVal(x.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn with_graph() -> Result<(), Box<dyn std::error::Error>> {
+        let graph = MockGraph::new();
+        graph.on("SHOW SPACES", Reply::rows(&["Name"], vec![vec![s("nba")]]));
+
+        // The mock is served by another runtime, as a remote graphd would be.
+        let server_runtime = Runtime::new()?;
+        let server = server_runtime.block_on(graph.serve())?;
+
+        let mut session = BlockingGraphClient::connect(server.addr().to_string())?
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())?;
+        let spaces = session.show_spaces()?;
+        assert_eq!(
+            spaces
+                .data_set
+                .into_iter()
+                .map(|x| x.name)
+                .collect::<Vec<_>>(),
+            vec!["nba"]
+        );
+        assert!(session.execute(&b"SHOW HOSTS".to_vec()).is_ok());
+
+        let session_id = session.session_id();
+        session.signout()?;
+        assert_eq!(graph.signed_out(), vec![session_id]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_meta_and_storage() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster.space("nba", 2, PropertyType::FIXED_STRING).tag(
+            "nba",
+            "player",
+            &[("name", PropertyType::STRING)],
+        );
+        for (vid, name) in [("p1", "Tim"), ("p2", "Tony")] {
+            cluster.insert_vertex("nba", "player", s(vid), vec![s(name)]);
+        }
+
+        let server_runtime = Runtime::new()?;
+        let meta = server_runtime.block_on(cluster.serve_meta())?;
+        let _storage = server_runtime.block_on(cluster.serve_storage())?;
+
+        let mclient = BlockingMetaClient::new(MetaClient::new(cluster.meta().transport()))?;
+        let space = mclient.get_space(b"nba".to_vec())?;
+        assert_eq!(space.item.properties.partition_num, 2);
+
+        let mclient = BlockingMetaClient::connect(meta.addr().to_string())?;
+        assert_eq!(mclient.get_parts(space.item.space_id)?.parts.len(), 2);
+
+        let mut vertices: Vec<String> =
+            scan_vertex(meta.addr().to_string(), "nba".into(), "player".into())?
+                .into_iter()
+                .flatten()
+                .collect();
+        vertices.sort();
+        assert_eq!(
+            vertices,
+            vec!["(p1:player{name: Tim})", "(p2:player{name: Tony})"]
+        );
+
+        Ok(())
+    }
+}
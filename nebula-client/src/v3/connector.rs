@@ -0,0 +1,174 @@
+//! Opening transports without tying the clients to one async runtime.
+//!
+//! `impl_tokio` provides [`TokioConnector`] and `impl_async_io` provides
+//! [`AsyncIoConnector`], which runs on async-std and smol. Implement
+//! [`Connector`] to dial through anything else.
+
+use std::io::Error as IoError;
+
+use async_sleep::Sleepble;
+use async_trait::async_trait;
+use fbthrift_transport::{
+    fbthrift_transport_response_handler::ResponseHandler, AsyncTransport,
+    AsyncTransportConfiguration,
+};
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// Opens the stream a transport runs over.
+#[async_trait]
+pub trait Connector: Clone + Send + Sync + 'static {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static;
+    type Sleep: Sleepble + Send + Sync + 'static;
+
+    /// `addr` is `host:port`.
+    async fn connect(&self, addr: &str) -> Result<Self::Stream, IoError>;
+
+    /// Whether the streams are encrypted, for callers that must refuse plain TCP.
+    fn is_tls(&self) -> bool {
+        false
+    }
+
+    async fn transport<H>(
+        &self,
+        addr: &str,
+        configuration: AsyncTransportConfiguration<H>,
+    ) -> Result<AsyncTransport<Self::Stream, Self::Sleep, H>, IoError>
+    where
+        H: ResponseHandler + Unpin + Send + 'static,
+    {
+        let stream = self.connect(addr).await?;
+        Ok(AsyncTransport::new(stream, configuration))
+    }
+}
+
+//
+#[cfg(feature = "impl_tokio")]
+pub use self::impl_tokio::TokioConnector;
+
+#[cfg(feature = "impl_tokio")]
+mod impl_tokio {
+    use super::*;
+
+    use fbthrift_transport::impl_tokio::{tcp_connect, TokioSleep, TokioTcpStream};
+
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TokioConnector;
+
+    #[async_trait]
+    impl Connector for TokioConnector {
+        type Stream = TokioTcpStream;
+        type Sleep = TokioSleep;
+
+        async fn connect(&self, addr: &str) -> Result<Self::Stream, IoError> {
+            tcp_connect(addr).await
+        }
+    }
+}
+
+//
+#[cfg(feature = "impl_async_io")]
+pub use self::impl_async_io::AsyncIoConnector;
+
+#[cfg(feature = "impl_async_io")]
+mod impl_async_io {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use fbthrift_transport::impl_async_io::{tcp_connect, AsyncIoSleep, AsyncIoTcpStream};
+
+    /// For async-std and smol, which both run on async-io.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct AsyncIoConnector;
+
+    #[async_trait]
+    impl Connector for AsyncIoConnector {
+        type Stream = AsyncIoTcpStream;
+        type Sleep = AsyncIoSleep;
+
+        async fn connect(&self, addr: &str) -> Result<Self::Stream, IoError> {
+            let mut err = None;
+            for addr in async_net::resolve(addr).await? {
+                match tcp_connect(addr).await {
+                    Ok(stream) => return Ok(stream),
+                    Err(e) => err = Some(e),
+                }
+            }
+
+            Err(err.unwrap_or_else(|| {
+                IoError::new(ErrorKind::InvalidInput, format!("no address for {addr}"))
+            }))
+        }
+    }
+}
+
+/// What [`scan_vertex`](crate::v3::scan_vertex) and [`scan_edge`](crate::v3::scan_edge)
+/// connect with; tokio when both runtimes are enabled.
+#[cfg(feature = "impl_tokio")]
+pub type DefaultConnector = TokioConnector;
+#[cfg(all(feature = "impl_async_io", not(feature = "impl_tokio")))]
+pub type DefaultConnector = AsyncIoConnector;
+
+#[cfg(all(test, feature = "impl_async_io", feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::dependencies::common::types::{PropertyType, Value};
+
+    use crate::v3::{
+        mock::{MockCluster, MockGraph, Reply},
+        scan_vertex_with, GraphClient, GraphQuery as _, GraphTransportResponseHandler,
+    };
+
+    #[test]
+    fn with_async_io() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on("SHOW HOSTS", Reply::Response(Default::default()));
+        let runtime = tokio::runtime::Runtime::new()?;
+        let server = runtime.block_on(mock.serve())?;
+
+        let cluster = MockCluster::new();
+        cluster
+            .space("nba", 1, PropertyType::FIXED_STRING)
+            .tag("nba", "player", &[("name", PropertyType::STRING)])
+            .insert_vertex(
+                "nba",
+                "player",
+                Value::sVal(b"p1".to_vec()),
+                vec![Value::sVal(b"Tim".to_vec())],
+            );
+        let meta = runtime.block_on(cluster.serve_meta())?;
+        let _storage = runtime.block_on(cluster.serve_storage())?;
+
+        async_io::block_on(async {
+            let addr = format!("localhost:{}", server.addr().port());
+            let transport = AsyncIoConnector
+                .transport(
+                    &addr,
+                    AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+                )
+                .await?;
+            let mut session = GraphClient::new(transport)
+                .with_addr(addr)
+                .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+                .await?;
+            session.show_hosts().await?;
+
+            assert!(AsyncIoConnector.connect("127.0.0.1:1").await.is_err());
+
+            let rows = scan_vertex_with(
+                &AsyncIoConnector,
+                meta.addr().to_string(),
+                "nba".into(),
+                "player".into(),
+            )
+            .await?;
+            assert_eq!(rows.concat(), vec!["(p1:player{name: Tim})"]);
+
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+        mock.assert_received("SHOW HOSTS");
+
+        Ok(())
+    }
+}
@@ -1,8 +1,18 @@
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use core::marker::PhantomData;
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::Arc,
+    time::Instant,
+};
 
+use async_sleep::Sleepble;
 use async_trait::async_trait;
 use bytes::Bytes;
-use fbthrift::{ApplicationException, ApplicationExceptionErrorCode, BinaryProtocol, Transport};
+use fbthrift::{
+    ApplicationException, ApplicationExceptionErrorCode, BinaryProtocol, CompactProtocol, Transport,
+};
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use futures_io::{AsyncRead, AsyncWrite};
 use nebula_fbthrift_graph::v3::{
     client::{GraphService, GraphServiceImpl},
     dependencies::common::types::ErrorCode,
@@ -10,8 +20,20 @@ use nebula_fbthrift_graph::v3::{
     types::ExecutionResponse,
 };
 use serde::de::DeserializeOwned;
+use tracing::{field::Empty, Instrument as _, Span};
 
-use super::query::{GraphQuery, GraphQueryError, GraphQueryOutput};
+use super::{
+    interceptor::{ExecuteContext, ExecuteOutcome, ExecuteResult, GraphInterceptor, Interceptors},
+    query::{GraphQuery, GraphQueryError, GraphQueryOutput},
+    GraphTransportResponseHandler,
+};
+#[cfg(feature = "metrics")]
+use crate::v3::metrics::{self, TRANSPORT_ERROR};
+use crate::v3::{
+    protocol::ThriftProtocol,
+    trace::{lossy, TraceConfig},
+    Connector,
+};
 
 //
 //
@@ -23,7 +45,9 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    service: GraphServiceImpl<BinaryProtocol, T>,
+    service: Box<dyn GraphService + Sync>,
+    addr: Option<String>,
+    _transport: PhantomData<T>,
 }
 
 impl<T> GraphConnection<T>
@@ -33,9 +57,19 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    fn new(transport: T) -> Self {
+    fn new(transport: T, protocol: ThriftProtocol) -> Self {
+        let service: Box<dyn GraphService + Sync> = match protocol {
+            ThriftProtocol::Binary => {
+                Box::new(GraphServiceImpl::<BinaryProtocol, _>::new(transport))
+            }
+            ThriftProtocol::Compact => {
+                Box::new(GraphServiceImpl::<CompactProtocol, _>::new(transport))
+            }
+        };
         Self {
-            service: GraphServiceImpl::<BinaryProtocol, _>::new(transport),
+            service,
+            addr: None,
+            _transport: PhantomData,
         }
     }
 }
@@ -51,6 +85,29 @@ where
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
     connection: GraphConnection<T>,
+    trace_config: TraceConfig,
+    interceptors: Interceptors,
+}
+
+impl<S, SLEEP> GraphClient<AsyncTransport<S, SLEEP, GraphTransportResponseHandler>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    SLEEP: Sleepble + Send + Sync + 'static,
+{
+    /// Connects to graphd at `addr` (`host:port`) through `connector`, such as a TLS one.
+    pub async fn connect<C>(connector: &C, addr: &str) -> Result<Self, IoError>
+    where
+        C: Connector<Stream = S, Sleep = SLEEP>,
+    {
+        let transport = connector
+            .transport(
+                addr,
+                AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+            )
+            .await?;
+
+        Ok(Self::new(transport).with_addr(addr))
+    }
 }
 
 impl<T> GraphClient<T>
@@ -61,23 +118,95 @@ where
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
     pub fn new(transport: T) -> Self {
+        Self::new_with_protocol(transport, ThriftProtocol::Binary)
+    }
+
+    /// Compact shrinks large responses; servers reply in whichever protocol they are called with.
+    pub fn new_with_protocol(transport: T, protocol: ThriftProtocol) -> Self {
         Self {
-            connection: GraphConnection::new(transport),
+            connection: GraphConnection::new(transport, protocol),
+            trace_config: TraceConfig::default(),
+            interceptors: Interceptors::default(),
         }
     }
 
+    /// Records the graphd address on spans, the transport itself does not know it.
+    pub fn with_addr(mut self, addr: impl Into<String>) -> Self {
+        self.connection.addr = Some(addr.into());
+        self
+    }
+
+    pub fn with_trace_config(mut self, trace_config: TraceConfig) -> Self {
+        self.trace_config = trace_config;
+        self
+    }
+
+    /// Adds an interceptor to every session authenticated by this client.
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn GraphInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    pub fn with_interceptors(
+        mut self,
+        interceptors: impl IntoIterator<Item = Arc<dyn GraphInterceptor>>,
+    ) -> Self {
+        self.interceptors.extend(interceptors);
+        self
+    }
+
+    /// Resumes `session_id`, authenticated on another connection; graphd keeps sessions across connections.
+    pub fn attach(self, session_id: i64) -> GraphSession<T> {
+        GraphSession::new(
+            self.connection,
+            session_id,
+            self.trace_config,
+            self.interceptors,
+        )
+    }
+
+    /// Signs out `session_id`, which does not have to be attached to this connection.
+    pub async fn signout(&self, session_id: i64) -> Result<(), SignoutError> {
+        let span = tracing::info_span!(
+            "nebula.graph",
+            method = "signout",
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            session_id,
+        );
+
+        self.connection
+            .service
+            .signout(session_id)
+            .instrument(span)
+            .await
+    }
+
     #[allow(clippy::ptr_arg)]
     pub async fn authenticate(
         self,
         username: &Vec<u8>,
         password: &Vec<u8>,
     ) -> Result<GraphSession<T>, AuthenticateError> {
+        let span = tracing::info_span!(
+            "nebula.graph",
+            method = "authenticate",
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            username = %String::from_utf8_lossy(username),
+            session_id = Empty,
+            error_code = Empty,
+        );
+
         let res = self
             .connection
             .service
             .authenticate(username, password)
-            .await?;
+            .instrument(span.clone())
+            .await
+            .inspect_err(
+                |err| tracing::warn!(parent: &span, error = %err, "authenticate failed"),
+            )?;
 
+        span.record("error_code", tracing::field::display(res.error_code));
         if res.error_code != ErrorCode::SUCCEEDED {
             return Err(ApplicationException::new(
                 ApplicationExceptionErrorCode::Unknown,
@@ -93,8 +222,14 @@ where
                 "Missing session_id".to_owned(),
             )
         })?;
+        span.record("session_id", session_id);
 
-        Ok(GraphSession::new(self.connection, session_id))
+        Ok(GraphSession::new(
+            self.connection,
+            session_id,
+            self.trace_config,
+            self.interceptors,
+        ))
     }
 }
 
@@ -111,6 +246,11 @@ where
     connection: GraphConnection<T>,
     session_id: i64,
     close_required: bool,
+    session_invalid: bool,
+    last_used: Instant,
+    space_name: Option<Vec<u8>>,
+    trace_config: TraceConfig,
+    interceptors: Interceptors,
 }
 
 impl<T> GraphSession<T>
@@ -120,73 +260,311 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    fn new(connection: GraphConnection<T>, session_id: i64) -> Self {
+    fn new(
+        connection: GraphConnection<T>,
+        session_id: i64,
+        trace_config: TraceConfig,
+        interceptors: Interceptors,
+    ) -> Self {
         Self {
             connection,
             session_id,
             close_required: false,
+            session_invalid: false,
+            last_used: Instant::now(),
+            space_name: None,
+            trace_config,
+            interceptors,
         }
     }
 
+    pub fn session_id(&self) -> i64 {
+        self.session_id
+    }
+
+    /// When the last statement was sent, or the session was opened.
+    pub fn last_used(&self) -> Instant {
+        self.last_used
+    }
+
+    /// The space of the last response, i.e. the one selected by the latest `USE`.
+    pub fn space_name(&self) -> Option<&[u8]> {
+        self.space_name.as_deref()
+    }
+
+    pub fn set_trace_config(&mut self, trace_config: TraceConfig) {
+        self.trace_config = trace_config;
+    }
+
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn GraphInterceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    fn span(&self, method: &'static str, stmt: &[u8]) -> Span {
+        tracing::info_span!(
+            "nebula.graph",
+            method,
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            session_id = self.session_id,
+            space = lossy(&self.space_name),
+            statement = self.trace_config.statement(stmt),
+            latency_us = Empty,
+            error_code = Empty,
+        )
+    }
+
     pub async fn signout(self) -> Result<(), SignoutError> {
-        self.connection.service.signout(self.session_id).await
+        let span = tracing::info_span!(
+            "nebula.graph",
+            method = "signout",
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            session_id = self.session_id,
+        );
+
+        self.connection
+            .service
+            .signout(self.session_id)
+            .instrument(span)
+            .await
+    }
+
+    /// `signout` is one-way; this also waits for a reply to a statement sent after it on the
+    /// same connection, which graphd answers with `E_SESSION_INVALID` once it handled the signout.
+    #[cfg(feature = "blocking")]
+    pub(crate) async fn signout_and_wait(self) -> Result<(), SignoutError> {
+        let session_id = self.session_id;
+        let service = &self.connection.service;
+
+        service.signout(session_id).await?;
+        let _ = service.execute(session_id, &b"YIELD 1".to_vec()).await;
+        Ok(())
     }
 
     #[allow(clippy::ptr_arg)]
     pub async fn execute(&mut self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError> {
-        let res = match self.connection.service.execute(self.session_id, stmt).await {
+        if self.interceptors.is_empty() {
+            return self.execute_inner(stmt).await;
+        }
+
+        let interceptors = self.interceptors.clone();
+        let space_name = self.space_name.clone();
+        let ctx = ExecuteContext {
+            session_id: self.session_id,
+            space_name: space_name.as_deref(),
+            json: false,
+        };
+
+        let stmt = interceptors
+            .before(&ctx, stmt)
+            .map_err(|err| ExecuteError::ThriftError(err.into()))?;
+
+        let now = Instant::now();
+        let res = self.execute_inner(&stmt).await;
+        let result = match &res {
+            Ok(res) => ExecuteResult::Response(res),
+            Err(err) => ExecuteResult::Error(err),
+        };
+        interceptors.after(
+            &ctx,
+            &stmt,
+            &ExecuteOutcome {
+                elapsed: now.elapsed(),
+                result,
+            },
+        );
+
+        res
+    }
+
+    #[allow(clippy::ptr_arg)]
+    async fn execute_inner(&mut self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError> {
+        let span = self.span("execute", stmt);
+        #[cfg(feature = "metrics")]
+        let request = metrics::Request::new("graph", "execute");
+        let now = Instant::now();
+        self.last_used = now;
+
+        let res = match self
+            .connection
+            .service
+            .execute(self.session_id, stmt)
+            .instrument(span.clone())
+            .await
+        {
             Ok(res) => res,
             Err(ExecuteError::ThriftError(err)) => {
+                tracing::warn!(parent: &span, error = %err, "execute failed");
+
                 if let Some(io_err) = err.downcast_ref::<IoError>() {
                     // "ExecuteError Broken pipe (os error 32)"
                     if io_err.kind() == IoErrorKind::BrokenPipe {
                         self.close_required = true;
+                        #[cfg(feature = "metrics")]
+                        metrics::session_broken();
                     }
                 }
 
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
                 return Err(ExecuteError::ThriftError(err));
             }
-            Err(err) => return Err(err),
+            Err(err) => {
+                tracing::warn!(parent: &span, error = %err, "execute failed");
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
+                return Err(err);
+            }
         };
 
+        span.record("latency_us", res.latency_in_us);
+        span.record("error_code", tracing::field::display(res.error_code));
+        if res.error_code != ErrorCode::SUCCEEDED {
+            tracing::debug!(
+                parent: &span,
+                error_msg = lossy(&res.error_msg),
+                "execute returned an error"
+            );
+        }
+
+        if res.space_name.is_some() {
+            self.space_name.clone_from(&res.space_name);
+        }
+
         match res.error_code {
             ErrorCode::E_SESSION_INVALID | ErrorCode::E_SESSION_TIMEOUT => {
                 self.close_required = true;
+                self.session_invalid = true;
+                #[cfg(feature = "metrics")]
+                metrics::session_broken();
             }
             _ => {}
         }
 
+        #[cfg(feature = "metrics")]
+        request.finish(
+            Some(res.latency_in_us),
+            (res.error_code != ErrorCode::SUCCEEDED).then(|| res.error_code.to_string()),
+        );
+
         Ok(res)
     }
 
     #[allow(clippy::ptr_arg)]
     pub async fn execute_json(&mut self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError> {
+        if self.interceptors.is_empty() {
+            return self.execute_json_inner(stmt).await;
+        }
+
+        let interceptors = self.interceptors.clone();
+        let space_name = self.space_name.clone();
+        let ctx = ExecuteContext {
+            session_id: self.session_id,
+            space_name: space_name.as_deref(),
+            json: true,
+        };
+
+        let stmt = interceptors
+            .before(&ctx, stmt)
+            .map_err(|err| ExecuteJsonError::ThriftError(err.into()))?;
+
+        let now = Instant::now();
+        let res = self.execute_json_inner(&stmt).await;
+        let result = match &res {
+            Ok(res) => ExecuteResult::Json(res),
+            Err(err) => ExecuteResult::Error(err),
+        };
+        interceptors.after(
+            &ctx,
+            &stmt,
+            &ExecuteOutcome {
+                elapsed: now.elapsed(),
+                result,
+            },
+        );
+
+        res
+    }
+
+    #[allow(clippy::ptr_arg)]
+    async fn execute_json_inner(&mut self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError> {
+        let span = self.span("execute_json", stmt);
+        #[cfg(feature = "metrics")]
+        let request = metrics::Request::new("graph", "execute_json");
+        let now = Instant::now();
+        self.last_used = now;
+
         let res = match self
             .connection
             .service
             .executeJson(self.session_id, stmt)
+            .instrument(span.clone())
             .await
         {
             Ok(res) => res,
             Err(ExecuteJsonError::ThriftError(err)) => {
+                tracing::warn!(parent: &span, error = %err, "execute_json failed");
+
                 if let Some(io_err) = err.downcast_ref::<IoError>() {
                     // "ExecuteJsonError Broken pipe (os error 32)"
                     if io_err.kind() == IoErrorKind::BrokenPipe {
                         self.close_required = true;
+                        #[cfg(feature = "metrics")]
+                        metrics::session_broken();
                     }
                 }
 
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
                 return Err(ExecuteJsonError::ThriftError(err));
             }
-            Err(err) => return Err(err),
+            Err(err) => {
+                tracing::warn!(parent: &span, error = %err, "execute_json failed");
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
+                return Err(err);
+            }
         };
 
+        #[cfg(feature = "metrics")]
+        request.finish(None, None);
         Ok(res)
     }
 
+    /// Runs the statement through `executeJson` and deserializes each row into `D` by column name.
+    #[cfg(feature = "json")]
+    #[allow(clippy::ptr_arg)]
+    pub async fn query_json_as<D: DeserializeOwned>(
+        &mut self,
+        stmt: &Vec<u8>,
+    ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+        let bytes = self
+            .execute_json(stmt)
+            .await
+            .map_err(GraphQueryError::ExecuteJsonError)?;
+
+        let output = GraphQueryOutput::from_json_slice(&bytes);
+        if let Err(GraphQueryError::ResponseError(
+            ErrorCode::E_SESSION_INVALID | ErrorCode::E_SESSION_TIMEOUT,
+            _,
+        )) = output
+        {
+            self.close_required = true;
+            self.session_invalid = true;
+            #[cfg(feature = "metrics")]
+            metrics::session_broken();
+        }
+
+        output
+    }
+
     pub fn is_close_required(&self) -> bool {
         self.close_required
     }
+
+    /// Set when graphd no longer knows the session, as opposed to a broken connection.
+    pub fn is_session_invalid(&self) -> bool {
+        self.session_invalid
+    }
 }
 
 //
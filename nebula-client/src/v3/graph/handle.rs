@@ -0,0 +1,186 @@
+//! Sessions without transport generics, to keep in application state or replace with a fake.
+//!
+//! ```ignore
+//! use nebula_client::v3::{connector::TokioConnector, graph::{BoxGraphSession, TokioGraphClient}, GraphQuery as _};
+//!
+//! struct App {
+//!     session: BoxGraphSession,
+//! }
+//!
+//! let session = TokioGraphClient::connect(&TokioConnector, "graphd:9669")
+//!     .await?
+//!     .authenticate(&username, &password)
+//!     .await?;
+//! let mut app = App { session: Box::new(session) };
+//! app.session.show_spaces().await?;
+//! ```
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use fbthrift::{BinaryProtocol, Transport};
+use nebula_fbthrift_graph::v3::{
+    dependencies::common::types::ErrorCode,
+    errors::graph_service::{ExecuteError, ExecuteJsonError, SignoutError},
+    types::ExecutionResponse,
+};
+use serde::de::DeserializeOwned;
+
+use super::{GraphQuery, GraphQueryError, GraphQueryOutput, GraphSession};
+
+/// The object-safe part of [`GraphSession`]; [`GraphQuery`] is implemented for `dyn GraphHandle`.
+#[async_trait]
+pub trait GraphHandle: Send + Sync {
+    fn session_id(&self) -> i64;
+
+    fn space_name(&self) -> Option<&[u8]>;
+
+    fn is_close_required(&self) -> bool;
+
+    #[allow(clippy::ptr_arg)]
+    async fn execute(&mut self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError>;
+
+    #[allow(clippy::ptr_arg)]
+    async fn execute_json(&mut self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError>;
+
+    async fn signout(self: Box<Self>) -> Result<(), SignoutError>;
+}
+
+pub type BoxGraphSession = Box<dyn GraphHandle>;
+
+#[async_trait]
+impl<T> GraphHandle for GraphSession<T>
+where
+    T: Transport + Send + Sync,
+    Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+    ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+        ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+{
+    fn session_id(&self) -> i64 {
+        GraphSession::session_id(self)
+    }
+
+    fn space_name(&self) -> Option<&[u8]> {
+        GraphSession::space_name(self)
+    }
+
+    fn is_close_required(&self) -> bool {
+        GraphSession::is_close_required(self)
+    }
+
+    async fn execute(&mut self, stmt: &Vec<u8>) -> Result<ExecutionResponse, ExecuteError> {
+        GraphSession::execute(self, stmt).await
+    }
+
+    async fn execute_json(&mut self, stmt: &Vec<u8>) -> Result<Vec<u8>, ExecuteJsonError> {
+        GraphSession::execute_json(self, stmt).await
+    }
+
+    async fn signout(self: Box<Self>) -> Result<(), SignoutError> {
+        GraphSession::signout(*self).await
+    }
+}
+
+#[async_trait]
+impl<'a> GraphQuery for dyn GraphHandle + 'a {
+    async fn query_as<D: DeserializeOwned>(
+        &mut self,
+        stmt: &Vec<u8>,
+    ) -> Result<GraphQueryOutput<D>, GraphQueryError> {
+        let res = self
+            .execute(stmt)
+            .await
+            .map_err(GraphQueryError::ExecuteError)?;
+
+        if res.error_code != ErrorCode::SUCCEEDED {
+            return Err(GraphQueryError::ResponseError(
+                res.error_code,
+                res.error_msg,
+            ));
+        }
+
+        GraphQueryOutput::new(res)
+    }
+}
+
+//
+#[cfg(feature = "impl_tokio")]
+pub use self::impl_tokio::{TokioGraphClient, TokioGraphSession, TokioGraphTransport};
+
+#[cfg(feature = "impl_tokio")]
+mod impl_tokio {
+    use fbthrift_transport::{
+        impl_tokio::{TokioSleep, TokioTcpStream},
+        AsyncTransport,
+    };
+
+    use super::super::{GraphClient, GraphSession, GraphTransportResponseHandler};
+
+    pub type TokioGraphTransport =
+        AsyncTransport<TokioTcpStream, TokioSleep, GraphTransportResponseHandler>;
+    pub type TokioGraphClient = GraphClient<TokioGraphTransport>;
+    pub type TokioGraphSession = GraphSession<TokioGraphTransport>;
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::dependencies::common::types::Value;
+
+    use crate::v3::{
+        mock::{MockGraph, Reply},
+        GraphClient,
+    };
+
+    struct App {
+        session: BoxGraphSession,
+    }
+
+    #[tokio::test]
+    async fn with_mock() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on(
+            "SHOW SPACES;",
+            Reply::rows(&["Name"], vec![vec![Value::sVal(b"nba".to_vec())]]),
+        );
+
+        let session = GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let mut app = App {
+            session: Box::new(session),
+        };
+        let session_id = app.session.session_id();
+
+        let spaces = app.session.show_spaces().await?;
+        assert_eq!(spaces.data_set[0].name, "nba");
+        app.session.query(&b"USE nba".to_vec()).await?;
+        assert_eq!(app.session.space_name(), Some(&b"nba"[..]));
+        assert!(app.session.query(&b"SHOW HOSTS".to_vec()).await.is_err());
+        assert!(!app.session.is_close_required());
+
+        app.session.signout().await?;
+        assert_eq!(mock.signed_out(), vec![session_id]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "impl_tokio")]
+    #[tokio::test]
+    async fn with_tokio_aliases() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        let server = mock.serve().await?;
+
+        let session: TokioGraphSession = TokioGraphClient::connect(
+            &crate::v3::connector::TokioConnector,
+            &server.addr().to_string(),
+        )
+        .await?
+        .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+        .await?;
+        let session: BoxGraphSession = Box::new(session);
+        assert_eq!(mock.sessions(), vec![session.session_id()]);
+
+        Ok(())
+    }
+}
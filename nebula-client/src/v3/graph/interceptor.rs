@@ -0,0 +1,223 @@
+use core::{fmt, time::Duration};
+use std::{borrow::Cow, error, sync::Arc};
+
+use nebula_fbthrift_graph::v3::types::ExecutionResponse;
+
+/// Hooks run around every `execute` / `execute_json` of a [`GraphSession`](super::GraphSession).
+///
+/// Interceptors run in the order they were added. The first one to reject a
+/// statement stops the chain and nothing is sent to graphd; `after_execute`
+/// is then not called.
+pub trait GraphInterceptor: Send + Sync {
+    /// Called before the statement is sent. Return a new statement to rewrite it,
+    /// `None` to keep it, or an error to reject it.
+    fn before_execute(
+        &self,
+        ctx: &ExecuteContext<'_>,
+        stmt: &[u8],
+    ) -> Result<Option<Vec<u8>>, ExecuteRejected> {
+        let _ = (ctx, stmt);
+        Ok(None)
+    }
+
+    /// Called with the statement that was actually sent, after its response or error.
+    fn after_execute(&self, ctx: &ExecuteContext<'_>, stmt: &[u8], outcome: &ExecuteOutcome<'_>) {
+        let _ = (ctx, stmt, outcome);
+    }
+}
+
+//
+#[derive(Debug, Clone, Copy)]
+pub struct ExecuteContext<'a> {
+    pub session_id: i64,
+    /// The space in use before this statement.
+    pub space_name: Option<&'a [u8]>,
+    /// `true` for `execute_json`.
+    pub json: bool,
+}
+
+//
+#[derive(Debug)]
+pub struct ExecuteOutcome<'a> {
+    /// Wall time measured by the client, including the network round trip.
+    pub elapsed: Duration,
+    pub result: ExecuteResult<'a>,
+}
+
+#[derive(Debug)]
+pub enum ExecuteResult<'a> {
+    /// Responses with a non-zero `error_code` land here too.
+    Response(&'a ExecutionResponse),
+    Json(&'a [u8]),
+    Error(&'a (dyn error::Error + Send + Sync)),
+}
+
+impl ExecuteOutcome<'_> {
+    /// Server side latency, only known for `execute`.
+    pub fn server_latency(&self) -> Option<Duration> {
+        match self.result {
+            ExecuteResult::Response(res) => Some(Duration::from_micros(res.latency_in_us as u64)),
+            _ => None,
+        }
+    }
+}
+
+/// Returned by an interceptor to stop a statement.
+///
+/// It reaches the caller as `ExecuteError::ThriftError` / `ExecuteJsonError::ThriftError`,
+/// use `downcast_ref::<ExecuteRejected>()` on the inner error to tell it apart.
+#[derive(Debug, Clone)]
+pub struct ExecuteRejected(pub String);
+
+impl fmt::Display for ExecuteRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "statement rejected: {}", self.0)
+    }
+}
+
+impl error::Error for ExecuteRejected {}
+
+//
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn GraphInterceptor>>);
+
+impl Interceptors {
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn GraphInterceptor>) {
+        self.0.push(interceptor)
+    }
+
+    pub(crate) fn extend(
+        &mut self,
+        interceptors: impl IntoIterator<Item = Arc<dyn GraphInterceptor>>,
+    ) {
+        self.0.extend(interceptors)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // The generated client takes `&Vec<u8>`.
+    #[allow(clippy::owned_cow)]
+    pub(crate) fn before<'a>(
+        &self,
+        ctx: &ExecuteContext<'_>,
+        stmt: &'a Vec<u8>,
+    ) -> Result<Cow<'a, Vec<u8>>, ExecuteRejected> {
+        let mut stmt = Cow::Borrowed(stmt);
+        for interceptor in self.0.iter() {
+            if let Some(rewritten) = interceptor.before_execute(ctx, &stmt)? {
+                stmt = Cow::Owned(rewritten);
+            }
+        }
+        Ok(stmt)
+    }
+
+    pub(crate) fn after(
+        &self,
+        ctx: &ExecuteContext<'_>,
+        stmt: &[u8],
+        outcome: &ExecuteOutcome<'_>,
+    ) {
+        for interceptor in self.0.iter() {
+            interceptor.after_execute(ctx, stmt, outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    struct ReadOnly;
+
+    impl GraphInterceptor for ReadOnly {
+        fn before_execute(
+            &self,
+            _ctx: &ExecuteContext<'_>,
+            stmt: &[u8],
+        ) -> Result<Option<Vec<u8>>, ExecuteRejected> {
+            if stmt.starts_with(b"INSERT") {
+                return Err(ExecuteRejected("read only".to_owned()));
+            }
+            Ok(None)
+        }
+    }
+
+    struct Prefix;
+
+    impl GraphInterceptor for Prefix {
+        fn before_execute(
+            &self,
+            _ctx: &ExecuteContext<'_>,
+            stmt: &[u8],
+        ) -> Result<Option<Vec<u8>>, ExecuteRejected> {
+            Ok(Some([b"USE test; ".as_slice(), stmt].concat()))
+        }
+    }
+
+    #[derive(Default)]
+    struct Audit(Mutex<Vec<(Vec<u8>, Option<Duration>)>>);
+
+    impl GraphInterceptor for Audit {
+        fn after_execute(
+            &self,
+            _ctx: &ExecuteContext<'_>,
+            stmt: &[u8],
+            outcome: &ExecuteOutcome<'_>,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((stmt.to_vec(), outcome.server_latency()));
+        }
+    }
+
+    #[test]
+    fn with_chain() {
+        let ctx = ExecuteContext {
+            session_id: 1,
+            space_name: None,
+            json: false,
+        };
+        let audit = Arc::new(Audit::default());
+
+        let mut interceptors = Interceptors::default();
+        interceptors.extend([
+            Arc::new(ReadOnly) as Arc<dyn GraphInterceptor>,
+            Arc::new(Prefix),
+            audit.clone(),
+        ]);
+
+        let stmt = b"SHOW SPACES".to_vec();
+        let stmt = interceptors.before(&ctx, &stmt).unwrap();
+        assert_eq!(stmt.as_slice(), b"USE test; SHOW SPACES");
+
+        let err = interceptors
+            .before(&ctx, &b"INSERT VERTEX".to_vec())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "statement rejected: read only");
+
+        let res = ExecutionResponse {
+            latency_in_us: 1500,
+            ..Default::default()
+        };
+        interceptors.after(
+            &ctx,
+            &stmt,
+            &ExecuteOutcome {
+                elapsed: Duration::from_millis(3),
+                result: ExecuteResult::Response(&res),
+            },
+        );
+        assert_eq!(
+            audit.0.lock().unwrap().as_slice(),
+            &[(
+                b"USE test; SHOW SPACES".to_vec(),
+                Some(Duration::from_micros(1500))
+            )]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use core::time::Duration;
+
+use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{Map, Value};
+
+use super::query::{GraphQueryError, GraphQueryOutput};
+
+//
+// The envelope returned by `GraphService.executeJson`.
+//
+#[derive(Deserialize, Debug, Default)]
+pub struct JsonResponse {
+    #[serde(default)]
+    pub errors: Vec<JsonError>,
+    #[serde(default)]
+    pub results: Vec<JsonResult>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct JsonError {
+    #[serde(default)]
+    pub code: i32,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct JsonResult {
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub data: Vec<JsonRow>,
+    #[serde(rename = "latencyInUs", default)]
+    pub latency_in_us: i64,
+    #[serde(rename = "spaceName", default)]
+    pub space_name: Option<String>,
+    #[serde(rename = "planDesc", default)]
+    pub plan_desc: Option<Value>,
+    #[serde(default)]
+    pub errors: Option<JsonError>,
+}
+
+/// The `meta` entries next to `row` (ids of vertices and edges in the row) are not parsed.
+#[derive(Deserialize, Debug, Default)]
+pub struct JsonRow {
+    #[serde(default)]
+    pub row: Vec<Value>,
+}
+
+impl JsonError {
+    fn into_query_error(self) -> Option<GraphQueryError> {
+        if self.code == ErrorCode::SUCCEEDED.0 {
+            return None;
+        }
+
+        Some(GraphQueryError::ResponseError(
+            ErrorCode(self.code),
+            self.message.map(String::into_bytes),
+        ))
+    }
+}
+
+impl JsonResponse {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, GraphQueryError> {
+        serde_json::from_slice(bytes).map_err(GraphQueryError::JsonDeserializeError)
+    }
+
+    /// Returns the first error entry with a non-zero code, top-level errors first.
+    pub fn into_result(self) -> Result<Option<JsonResult>, GraphQueryError> {
+        if let Some(err) = self
+            .errors
+            .into_iter()
+            .find_map(JsonError::into_query_error)
+        {
+            return Err(err);
+        }
+
+        let mut result = self.results.into_iter().next();
+        if let Some(err) = result
+            .as_mut()
+            .and_then(|x| x.errors.take())
+            .and_then(JsonError::into_query_error)
+        {
+            return Err(err);
+        }
+
+        Ok(result)
+    }
+}
+
+impl<D> GraphQueryOutput<D>
+where
+    D: DeserializeOwned,
+{
+    /// Builds the output from the raw bytes of an `executeJson` response,
+    /// deserializing each row into `D` by column name.
+    pub fn from_json_slice(bytes: &[u8]) -> Result<Self, GraphQueryError> {
+        let result = match JsonResponse::from_slice(bytes)?.into_result()? {
+            Some(result) => result,
+            None => {
+                return Ok(Self {
+                    latency: Duration::default(),
+                    space_name: None,
+                    data_set: vec![],
+                })
+            }
+        };
+
+        let mut data_set = Vec::with_capacity(result.data.len());
+        for row in result.data.into_iter() {
+            let object: Map<String, Value> = result.columns.iter().cloned().zip(row.row).collect();
+
+            let data = D::deserialize(Value::Object(object))
+                .map_err(GraphQueryError::JsonDeserializeError)?;
+
+            data_set.push(data);
+        }
+
+        Ok(Self {
+            latency: Duration::from_micros(u64::try_from(result.latency_in_us).unwrap_or_default()),
+            space_name: result
+                .space_name
+                .filter(|x| !x.is_empty())
+                .map(String::into_bytes),
+            data_set,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Player {
+        name: String,
+        age: i64,
+    }
+
+    #[test]
+    fn with_rows() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = br#"{
+            "errors": [{"code": 0}],
+            "results": [{
+                "spaceName": "basketballplayer",
+                "latencyInUs": 1500,
+                "columns": ["name", "age"],
+                "data": [
+                    {"row": ["Tim Duncan", 42], "meta": [null, null]},
+                    {"row": ["Tony Parker", 36], "meta": [null, null]}
+                ]
+            }]
+        }"#;
+
+        let output = GraphQueryOutput::<Player>::from_json_slice(bytes)?;
+
+        assert_eq!(output.latency, Duration::from_micros(1500));
+        assert_eq!(output.space_name, Some(b"basketballplayer".to_vec()));
+        assert_eq!(
+            output.data_set,
+            vec![
+                Player {
+                    name: "Tim Duncan".to_owned(),
+                    age: 42
+                },
+                Player {
+                    name: "Tony Parker".to_owned(),
+                    age: 36
+                }
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_negative_latency() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = br#"{"results": [{"latencyInUs": -1, "columns": [], "data": []}]}"#;
+
+        let output = GraphQueryOutput::<Player>::from_json_slice(bytes)?;
+        assert_eq!(output.latency, Duration::ZERO);
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_errors() {
+        let bytes =
+            br#"{"errors": [{"code": -1009, "message": "SemanticError: `foo' not found"}]}"#;
+
+        match GraphQueryOutput::<Player>::from_json_slice(bytes) {
+            Err(GraphQueryError::ResponseError(code, Some(msg))) => {
+                assert_eq!(code, ErrorCode::E_SEMANTIC_ERROR);
+                assert_eq!(msg, b"SemanticError: `foo' not found");
+            }
+            x => panic!("{x:?}"),
+        }
+
+        match GraphQueryOutput::<Player>::from_json_slice(b"not json") {
+            Err(GraphQueryError::JsonDeserializeError(_)) => {}
+            x => panic!("{x:?}"),
+        }
+    }
+}
@@ -1,8 +1,27 @@
+pub mod builder;
+pub use builder::{GraphClientBuilder, GraphConnectError};
+
 pub mod client;
 pub use client::{GraphClient, GraphSession};
 
+pub mod handle;
+pub use handle::{BoxGraphSession, GraphHandle};
+#[cfg(feature = "impl_tokio")]
+pub use handle::{TokioGraphClient, TokioGraphSession, TokioGraphTransport};
+
+pub mod interceptor;
+pub use interceptor::{ExecuteContext, ExecuteOutcome, ExecuteRejected, GraphInterceptor};
+
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "json")]
+pub use json::JsonResponse;
+
 pub mod query;
 pub use query::{GraphQuery, GraphQueryError, GraphQueryOutput};
 
+pub mod script;
+pub use script::split_statements;
+
 pub mod transport_response_handler;
 pub use transport_response_handler::GraphTransportResponseHandler;
@@ -1,15 +1,17 @@
 use core::time::Duration;
 
 use async_trait::async_trait;
+#[cfg(feature = "json")]
+use nebula_fbthrift_graph::v3::errors::graph_service::ExecuteJsonError;
 use nebula_fbthrift_graph::v3::{
     dependencies::common::types::ErrorCode, errors::graph_service::ExecuteError,
     types::ExecutionResponse,
 };
 
-use serde::de::DeserializeOwned;
 use deserialize_nebula_fbthrift::v3::de::{
     data::DataDeserializeError, deserialize_execution_response,
 };
+use serde::de::DeserializeOwned;
 
 #[async_trait]
 pub trait GraphQuery {
@@ -63,10 +65,15 @@ where
 //
 //
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum GraphQueryError {
     ExecuteError(ExecuteError),
     ResponseError(ErrorCode, Option<Vec<u8>>),
     DataDeserializeError(DataDeserializeError),
+    #[cfg(feature = "json")]
+    ExecuteJsonError(ExecuteJsonError),
+    #[cfg(feature = "json")]
+    JsonDeserializeError(serde_json::Error),
 }
 
 impl core::fmt::Display for GraphQueryError {
@@ -77,6 +84,10 @@ impl core::fmt::Display for GraphQueryError {
                 write!(f, "ResponseError err_code:{err_code} err_msg:{err_msg:?}",)
             }
             Self::DataDeserializeError(err) => write!(f, "DataDeserializeError {err}"),
+            #[cfg(feature = "json")]
+            Self::ExecuteJsonError(err) => write!(f, "ExecuteJsonError {err}"),
+            #[cfg(feature = "json")]
+            Self::JsonDeserializeError(err) => write!(f, "JsonDeserializeError {err}"),
         }
     }
 }
@@ -87,6 +98,10 @@ impl std::error::Error for GraphQueryError {
             Self::ExecuteError(_) => "ExecuteError",
             Self::ResponseError(_, _) => "ResponseError",
             Self::DataDeserializeError(_) => "DataDeserializeError",
+            #[cfg(feature = "json")]
+            Self::ExecuteJsonError(_) => "ExecuteJsonError",
+            #[cfg(feature = "json")]
+            Self::JsonDeserializeError(_) => "JsonDeserializeError",
         }
     }
 }
@@ -103,8 +118,8 @@ pub struct Host {
     pub host: String,
     #[serde(rename(deserialize = "Port"))]
     pub port: u16,
-   // #[serde(rename(deserialize = "HTTP port"))]
-   // pub http_port: u16,
+    // #[serde(rename(deserialize = "HTTP port"))]
+    // pub http_port: u16,
     #[serde(rename(deserialize = "Status"))]
     pub status: String,
     #[serde(rename(deserialize = "Leader count"))]
@@ -128,7 +143,7 @@ pub struct Space {
 mod tests {
     use super::*;
 
-    use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+    use std::io::Error as IoError;
 
     #[test]
     fn impl_std_fmt_display() {
@@ -138,10 +153,10 @@ mod tests {
 
     #[test]
     fn impl_std_error_error() {
-        let err = IoError::new(
-            IoErrorKind::Other,
-            GraphQueryError::ResponseError(ErrorCode::E_DISCONNECTED, None),
-        );
+        let err = IoError::other(GraphQueryError::ResponseError(
+            ErrorCode::E_DISCONNECTED,
+            None,
+        ));
         println!("{err}");
     }
 }
@@ -0,0 +1,169 @@
+//! Splitting nGQL scripts into statements.
+
+/// Splits a script on `;`, skipping quoted strings and `#`, `--`, `//` and `/* */` comments.
+/// Comments are dropped and empty statements removed.
+pub fn split_statements(ngql: &str) -> Vec<String> {
+    let (mut statements, rest) = split_complete(ngql);
+    push_statement(&mut statements, &strip_comments(rest));
+    statements
+}
+
+/// Like [`split_statements`], but stops at the last `;`.
+///
+/// Returns the complete statements and the unterminated rest of the input, as
+/// written (comments included), e.g. to keep reading lines until it ends with a `;`.
+pub fn split_complete(ngql: &str) -> (Vec<String>, &str) {
+    let mut statements = vec![];
+    let mut start = 0;
+
+    let mut chars = ngql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' | '`' => {
+                let mut escaped = false;
+                for (_, x) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if x == '\\' {
+                        escaped = true;
+                    } else if x == c {
+                        break;
+                    }
+                }
+            }
+            '#' => skip_line(&mut chars),
+            // `(a)--(b)` is a MATCH pattern, a comment needs whitespace after `--`.
+            '-' if is_dash_comment(&chars) => skip_line(&mut chars),
+            '/' if chars.peek().map(|x| x.1) == Some('/') => skip_line(&mut chars),
+            '/' if chars.peek().map(|x| x.1) == Some('*') => {
+                chars.next();
+                let mut prev = '\0';
+                for (_, x) in chars.by_ref() {
+                    if prev == '*' && x == '/' {
+                        break;
+                    }
+                    prev = x;
+                }
+            }
+            ';' => {
+                push_statement(&mut statements, &strip_comments(&ngql[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    (statements, &ngql[start..])
+}
+
+type Chars<'a> = core::iter::Peekable<core::str::CharIndices<'a>>;
+
+fn is_dash_comment(chars: &Chars) -> bool {
+    let mut next = chars.clone().map(|x| x.1);
+    next.next() == Some('-') && next.next().is_none_or(char::is_whitespace)
+}
+
+fn skip_line(chars: &mut Chars) {
+    for (_, x) in chars.by_ref() {
+        if x == '\n' {
+            break;
+        }
+    }
+}
+
+/// Drops the comments of a statement without `;`, keeping quoted strings intact.
+fn strip_comments(statement: &str) -> String {
+    let mut out = String::with_capacity(statement.len());
+
+    let mut chars = statement.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' | '`' => {
+                let mut end = statement.len();
+                let mut escaped = false;
+                for (j, x) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if x == '\\' {
+                        escaped = true;
+                    } else if x == c {
+                        end = j + x.len_utf8();
+                        break;
+                    }
+                }
+                out.push_str(&statement[i..end]);
+            }
+            '#' => skip_line(&mut chars),
+            '-' if is_dash_comment(&chars) => skip_line(&mut chars),
+            '/' if chars.peek().map(|x| x.1) == Some('/') => skip_line(&mut chars),
+            '/' if chars.peek().map(|x| x.1) == Some('*') => {
+                chars.next();
+                let mut prev = '\0';
+                for (_, x) in chars.by_ref() {
+                    if prev == '*' && x == '/' {
+                        break;
+                    }
+                    prev = x;
+                }
+                out.push(' ');
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn push_statement(statements: &mut Vec<String>, statement: &str) {
+    let statement = statement.trim();
+    if !statement.is_empty() {
+        statements.push(statement.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_statements() {
+        let ngql = r#"
+            # players
+            CREATE TAG IF NOT EXISTS player(name string, age int); -- inline
+            /* multi
+               line; */
+            INSERT VERTEX player(name, age) VALUES "p1":("a;b", 42);
+            // trailing
+            CREATE EDGE `fol;low`(degree int);
+            MATCH (a)--(b) RETURN a
+        "#;
+
+        assert_eq!(
+            split_statements(ngql),
+            vec![
+                "CREATE TAG IF NOT EXISTS player(name string, age int)",
+                r#"INSERT VERTEX player(name, age) VALUES "p1":("a;b", 42)"#,
+                "CREATE EDGE `fol;low`(degree int)",
+                "MATCH (a)--(b) RETURN a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_complete() {
+        assert_eq!(
+            split_complete("SHOW HOSTS; SHOW\n"),
+            (vec!["SHOW HOSTS".to_owned()], " SHOW\n")
+        );
+        assert_eq!(split_complete("YIELD \"a;\n"), (vec![], "YIELD \"a;\n"));
+        assert_eq!(
+            split_complete("YIELD 1 /* ; */ -- x;\n"),
+            (vec![], "YIELD 1 /* ; */ -- x;\n")
+        );
+        assert_eq!(
+            split_complete("YIELD 1; # done\n"),
+            (vec!["YIELD 1".to_owned()], " # done\n")
+        );
+        assert!(split_statements(" # done\n").is_empty());
+    }
+}
@@ -1,8 +1,9 @@
-use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind};
+use std::io::{Cursor, Error as IoError};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use fbthrift::{
     binary_protocol::{BinaryProtocolDeserializer, BinaryProtocolSerializer},
+    compact_protocol::{CompactProtocolDeserializer, CompactProtocolSerializer},
     ApplicationException, Deserialize, MessageType, ProtocolReader, ProtocolWriter, Serialize,
 };
 use fbthrift_transport_response_handler::ResponseHandler;
@@ -10,6 +11,8 @@ use nebula_fbthrift_graph::v3::services::graph_service::{
     AuthenticateExn, ExecuteExn, ExecuteJsonExn, SignoutExn,
 };
 
+use crate::v3::protocol::{frame_len, ThriftProtocol};
+
 #[derive(Clone)]
 pub struct GraphTransportResponseHandler;
 
@@ -23,107 +26,122 @@ impl ResponseHandler for GraphTransportResponseHandler {
         match fn_name {
             b"GraphService.authenticate" => Ok(None),
             b"GraphService.signout" => {
-                let mut des = BinaryProtocolDeserializer::new(Cursor::new(request_bytes));
-                let (name, message_type, seqid) = des
-                    .read_message_begin(|v| v.to_vec())
-                    .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
-
-                if name != b"signout" {
-                    return Err(IoError::new(
-                        IoErrorKind::Other,
-                        format!("Unexpected name {name:?}"),
-                    ));
-                }
-
-                if message_type != MessageType::Call {
-                    return Err(IoError::new(
-                        IoErrorKind::Other,
-                        format!("Unexpected message type {message_type:?}"),
-                    ));
-                }
-
                 let buf = BytesMut::with_capacity(1024);
-                let mut ser = BinaryProtocolSerializer::<BytesMut>::with_buffer(buf);
-
-                ser.write_message_begin("signout", MessageType::Reply, seqid);
-                ser.write_message_end();
-
-                SignoutExn::Success(()).write(&mut ser);
-
-                let res_buf = ser.finish().to_vec();
+                let res_buf = match ThriftProtocol::detect(request_bytes) {
+                    Some(ThriftProtocol::Compact) => signout_reply(
+                        CompactProtocolDeserializer::new(Cursor::new(request_bytes)),
+                        CompactProtocolSerializer::with_buffer(buf),
+                    )?,
+                    _ => signout_reply(
+                        BinaryProtocolDeserializer::new(Cursor::new(request_bytes)),
+                        BinaryProtocolSerializer::with_buffer(buf),
+                    )?,
+                };
 
                 Ok(Some(res_buf))
             }
             b"GraphService.execute" => Ok(None),
             b"GraphService.executeJson" => Ok(None),
-            _ => Err(IoError::new(
-                IoErrorKind::Other,
-                format!("Unknown method {}", String::from_utf8_lossy(fn_name)),
-            )),
+            _ => Err(IoError::other(format!(
+                "Unknown method {}",
+                String::from_utf8_lossy(fn_name)
+            ))),
         }
     }
 
     fn parse_response_bytes(&mut self, response_bytes: &[u8]) -> Result<Option<usize>, IoError> {
-        let mut des = BinaryProtocolDeserializer::new(Cursor::new(response_bytes));
-        let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
-
-        match &name[..] {
-            b"authenticate" => {}
-            b"signout" => unreachable!(),
-            b"execute" => {}
-            b"executeJson" => {}
-            _ => return Ok(None),
-        };
-
-        match message_type {
-            MessageType::Reply => {
-                match &name[..] {
-                    b"authenticate" => {
-                        let _: AuthenticateExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"execute" => {
-                        let _: ExecuteExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"executeJson" => {
-                        let _: ExecuteJsonExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    _ => unreachable!(),
-                };
-            }
-            MessageType::Exception => {
-                let _: ApplicationException = match Deserialize::read(&mut des) {
-                    Ok(v) => v,
-                    Err(_) => return Ok(None),
-                };
-            }
-            MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
-        }
+        Ok(frame_len(response_bytes, parse_response, parse_response))
+    }
+}
+
+fn signout_reply<D, S>(mut des: D, mut ser: S) -> Result<Vec<u8>, IoError>
+where
+    D: ProtocolReader,
+    S: ProtocolWriter<Final = Bytes>,
+{
+    let (name, message_type, seqid) = des
+        .read_message_begin(|v| v.to_vec())
+        .map_err(IoError::other)?;
 
-        match des.read_message_end() {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
+    if name != b"signout" {
+        return Err(IoError::other(format!("Unexpected name {name:?}")));
+    }
 
-        Ok(Some(des.into_inner().position() as usize))
+    if message_type != MessageType::Call {
+        return Err(IoError::other(format!(
+            "Unexpected message type {message_type:?}"
+        )));
     }
+
+    ser.write_message_begin("signout", MessageType::Reply, seqid);
+    ser.write_message_end();
+
+    SignoutExn::Success(()).write(&mut ser);
+
+    Ok(ser.finish().to_vec())
+}
+
+fn parse_response<D: ProtocolReader>(des: &mut D) -> Option<()> {
+    let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
+
+    match &name[..] {
+        b"authenticate" => {}
+        b"signout" => unreachable!(),
+        b"execute" => {}
+        b"executeJson" => {}
+        _ => return None,
+    };
+
+    match message_type {
+        MessageType::Reply => {
+            match &name[..] {
+                b"authenticate" => {
+                    let _: AuthenticateExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"execute" => {
+                    let _: ExecuteExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"executeJson" => {
+                    let _: ExecuteJsonExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                _ => unreachable!(),
+            };
+        }
+        MessageType::Exception => {
+            let _: ApplicationException = match Deserialize::read(des) {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+        }
+        MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
+    }
+
+    match des.read_message_end() {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
+
+    Some(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::io::ErrorKind as IoErrorKind;
+
     #[test]
     fn test_try_make_static_response_bytes() -> Result<(), Box<dyn std::error::Error>> {
         let mut handler = GraphTransportResponseHandler;
@@ -196,4 +214,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_signout_with_compact() -> Result<(), Box<dyn std::error::Error>> {
+        let mut handler = GraphTransportResponseHandler;
+
+        let request = ::fbthrift::serialize!(::fbthrift::CompactProtocol, |p| {
+            p.write_message_begin("signout", ::fbthrift::MessageType::Call, 7);
+
+            p.write_struct_begin("args");
+            p.write_field_begin("arg_sessionId", ::fbthrift::TType::I64, 1i16);
+            ::fbthrift::Serialize::write(&1i64, p);
+            p.write_field_end();
+            p.write_field_stop();
+            p.write_struct_end();
+
+            p.write_message_end();
+        });
+
+        let response = handler
+            .try_make_static_response_bytes(b"GraphService", b"GraphService.signout", &request[..])?
+            .expect("static response");
+        assert_eq!(
+            ThriftProtocol::detect(&response),
+            Some(ThriftProtocol::Compact)
+        );
+
+        let mut des = CompactProtocolDeserializer::new(Cursor::new(&response[..]));
+        let (name, message_type, seqid) = des.read_message_begin(|v| v.to_vec())?;
+        assert_eq!(name, b"signout");
+        assert_eq!(message_type, MessageType::Reply);
+        assert_eq!(seqid, 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_response_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let mut handler = GraphTransportResponseHandler;
+
+        let binary = ::fbthrift::serialize!(::fbthrift::BinaryProtocol, |p| {
+            p.write_message_begin("execute", MessageType::Reply, 1);
+            ExecuteExn::Success(Default::default()).write(p);
+            p.write_message_end();
+        });
+        let compact = ::fbthrift::serialize!(::fbthrift::CompactProtocol, |p| {
+            p.write_message_begin("execute", MessageType::Reply, 1);
+            ExecuteExn::Success(Default::default()).write(p);
+            p.write_message_end();
+        });
+
+        for bytes in [binary, compact] {
+            assert_eq!(handler.parse_response_bytes(&bytes)?, Some(bytes.len()));
+            assert_eq!(
+                handler.parse_response_bytes(&bytes[..bytes.len() - 1])?,
+                None
+            );
+        }
+
+        Ok(())
+    }
 }
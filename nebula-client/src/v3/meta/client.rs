@@ -1,13 +1,34 @@
+use core::{
+    future::Future,
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::io::Error as IoError;
+
+use async_sleep::Sleepble;
 use bytes::Bytes;
-use fbthrift::{BinaryProtocol, Transport};
+use fbthrift::{BinaryProtocol, CompactProtocol, NonthrowingFunctionError, Transport};
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use futures_io::{AsyncRead, AsyncWrite};
 use nebula_fbthrift_meta::v3::{
     client::{MetaService, MetaServiceImpl},
-    errors::meta_service::{GetSpaceError, ListPartsError, ListSpacesError, ListTagsError,ListEdgesError,GetPartsAllocError},
+    dependencies::common::types::ErrorCode,
+    errors::meta_service::{
+        GetPartsAllocError, GetSpaceError, ListEdgesError, ListPartsError, ListSpacesError,
+        ListTagsError,
+    },
     types::{
-        GetSpaceReq, GetSpaceResp, ListEdgesReq, ListEdgesResp, ListPartsReq,GetPartsAllocReq,ListPartsResp,
-        ListSpacesReq, ListSpacesResp, ListTagsReq, ListTagsResp,GetPartsAllocResp,
+        GetPartsAllocReq, GetPartsAllocResp, GetSpaceReq, GetSpaceResp, ListEdgesReq,
+        ListEdgesResp, ListPartsReq, ListPartsResp, ListSpacesReq, ListSpacesResp, ListTagsReq,
+        ListTagsResp,
     },
 };
+use tracing::{field::Empty, Instrument as _};
+
+use super::MetaTransportResponseHandler;
+#[cfg(feature = "metrics")]
+use crate::v3::metrics::{self, TRANSPORT_ERROR};
+use crate::v3::{protocol::ThriftProtocol, Connector};
 
 //
 //
@@ -19,7 +40,10 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    service: MetaServiceImpl<BinaryProtocol, T>,
+    service: Box<dyn MetaService + Sync>,
+    addr: Option<String>,
+    close_required: AtomicBool,
+    _transport: PhantomData<T>,
 }
 
 impl<T> MetaConnection<T>
@@ -29,9 +53,20 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    fn new(transport: T) -> Self {
+    fn new(transport: T, protocol: ThriftProtocol) -> Self {
+        let service: Box<dyn MetaService + Sync> = match protocol {
+            ThriftProtocol::Binary => {
+                Box::new(MetaServiceImpl::<BinaryProtocol, _>::new(transport))
+            }
+            ThriftProtocol::Compact => {
+                Box::new(MetaServiceImpl::<CompactProtocol, _>::new(transport))
+            }
+        };
         Self {
-            service: MetaServiceImpl::<BinaryProtocol, _>::new(transport),
+            service,
+            addr: None,
+            close_required: AtomicBool::new(false),
+            _transport: PhantomData,
         }
     }
 }
@@ -49,6 +84,27 @@ where
     connection: MetaConnection<T>,
 }
 
+impl<S, SLEEP> MetaClient<AsyncTransport<S, SLEEP, MetaTransportResponseHandler>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    SLEEP: Sleepble + Send + Sync + 'static,
+{
+    /// Connects to metad at `addr` (`host:port`) through `connector`, such as a TLS one.
+    pub async fn connect<C>(connector: &C, addr: &str) -> Result<Self, IoError>
+    where
+        C: Connector<Stream = S, Sleep = SLEEP>,
+    {
+        let transport = connector
+            .transport(
+                addr,
+                AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+            )
+            .await?;
+
+        Ok(Self::new(transport).with_addr(addr))
+    }
+}
+
 impl<T> MetaClient<T>
 where
     T: Transport,
@@ -57,77 +113,151 @@ where
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
     pub fn new(transport: T) -> Self {
+        Self::new_with_protocol(transport, ThriftProtocol::Binary)
+    }
+
+    /// Compact shrinks large responses; servers reply in whichever protocol they are called with.
+    pub fn new_with_protocol(transport: T, protocol: ThriftProtocol) -> Self {
         Self {
-            connection: MetaConnection::new(transport),
+            connection: MetaConnection::new(transport, protocol),
+        }
+    }
+
+    /// Records the metad address on spans, the transport itself does not know it.
+    pub fn with_addr(mut self, addr: impl Into<String>) -> Self {
+        self.connection.addr = Some(addr.into());
+        self
+    }
+
+    /// Set once a call failed in the transport; the connection should be dropped.
+    pub fn is_close_required(&self) -> bool {
+        self.connection.close_required.load(Ordering::Relaxed)
+    }
+
+    async fn call<R>(
+        &self,
+        method: &'static str,
+        space_id: Option<i32>,
+        fut: impl Future<Output = Result<R, NonthrowingFunctionError>>,
+        code: impl Fn(&R) -> ErrorCode,
+    ) -> Result<R, NonthrowingFunctionError> {
+        let span = tracing::info_span!(
+            "nebula.meta",
+            method,
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            space_id,
+            error_code = Empty,
+        );
+        #[cfg(feature = "metrics")]
+        let request = metrics::Request::new("meta", method);
+
+        match fut.instrument(span.clone()).await {
+            Ok(res) => {
+                let code = code(&res);
+                span.record("error_code", tracing::field::display(code));
+                #[cfg(feature = "metrics")]
+                request.finish(
+                    None,
+                    (code != ErrorCode::SUCCEEDED).then(|| code.to_string()),
+                );
+                Ok(res)
+            }
+            Err(err) => {
+                tracing::warn!(parent: &span, error = %err, "meta request failed");
+                if let NonthrowingFunctionError::ThriftError(_) = err {
+                    self.connection
+                        .close_required
+                        .store(true, Ordering::Relaxed);
+                }
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
+                Err(err)
+            }
         }
     }
 
     pub async fn list_spaces(&self) -> Result<ListSpacesResp, ListSpacesError> {
-        self.connection
-            .service
-            .listSpaces(&ListSpacesReq {
+        self.call(
+            "list_spaces",
+            None,
+            self.connection.service.listSpaces(&ListSpacesReq {
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
 
     // 这里可以获得 GraphSpaceID
     pub async fn get_space(&self, space_name: Vec<u8>) -> Result<GetSpaceResp, GetSpaceError> {
-        self.connection
-            .service
-            .getSpace(&GetSpaceReq {
-                space_name: space_name,
+        self.call(
+            "get_space",
+            None,
+            self.connection.service.getSpace(&GetSpaceReq {
+                space_name,
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
 
-    
-
     // 根据spacename 获得所有part fn getPartsAlloc( 返回的所有parts 在BTreeMap里
     pub async fn list_parts(
         &self,
         space_id: i32,
         part_ids: Vec<i32>,
     ) -> Result<ListPartsResp, ListPartsError> {
-        self.connection
-            .service
-            .listParts(&ListPartsReq {
+        self.call(
+            "list_parts",
+            Some(space_id),
+            self.connection.service.listParts(&ListPartsReq {
                 space_id,
                 part_ids,
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
-    
+
     //  TagID ，不要用这个  props从ColumnDef::name获取
     pub async fn list_tags(&self, space_id: i32) -> Result<ListTagsResp, ListTagsError> {
-        self.connection
-            .service
-            .listTags(&ListTagsReq {
+        self.call(
+            "list_tags",
+            Some(space_id),
+            self.connection.service.listTags(&ListTagsReq {
                 space_id,
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
 
     pub async fn list_edges(&self, space_id: i32) -> Result<ListEdgesResp, ListEdgesError> {
-        self.connection
-            .service
-            .listEdges(&ListEdgesReq {
+        self.call(
+            "list_edges",
+            Some(space_id),
+            self.connection.service.listEdges(&ListEdgesReq {
                 space_id,
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
 
     pub async fn get_parts(&self, space_id: i32) -> Result<GetPartsAllocResp, GetPartsAllocError> {
-        self.connection
-            .service
-            .getPartsAlloc(&GetPartsAllocReq {
+        self.call(
+            "get_parts",
+            Some(space_id),
+            self.connection.service.getPartsAlloc(&GetPartsAllocReq {
                 space_id,
                 ..Default::default()
-            })
-            .await
+            }),
+            |res| res.code,
+        )
+        .await
     }
 }
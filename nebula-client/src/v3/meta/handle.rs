@@ -0,0 +1,99 @@
+//! Meta clients without transport generics, to keep in application state or replace with a fake.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use fbthrift::{BinaryProtocol, Transport};
+use nebula_fbthrift_meta::v3::{
+    errors::meta_service::{
+        GetPartsAllocError, GetSpaceError, ListEdgesError, ListPartsError, ListSpacesError,
+        ListTagsError,
+    },
+    types::{
+        GetPartsAllocResp, GetSpaceResp, ListEdgesResp, ListPartsResp, ListSpacesResp, ListTagsResp,
+    },
+};
+
+use super::MetaClient;
+
+/// The methods of [`MetaClient`], object-safe.
+#[async_trait]
+pub trait MetaHandle: Send + Sync {
+    async fn list_spaces(&self) -> Result<ListSpacesResp, ListSpacesError>;
+
+    async fn get_space(&self, space_name: Vec<u8>) -> Result<GetSpaceResp, GetSpaceError>;
+
+    async fn list_parts(
+        &self,
+        space_id: i32,
+        part_ids: Vec<i32>,
+    ) -> Result<ListPartsResp, ListPartsError>;
+
+    async fn list_tags(&self, space_id: i32) -> Result<ListTagsResp, ListTagsError>;
+
+    async fn list_edges(&self, space_id: i32) -> Result<ListEdgesResp, ListEdgesError>;
+
+    async fn get_parts(&self, space_id: i32) -> Result<GetPartsAllocResp, GetPartsAllocError>;
+
+    fn is_close_required(&self) -> bool;
+}
+
+pub type BoxMetaClient = Box<dyn MetaHandle>;
+
+#[async_trait]
+impl<T> MetaHandle for MetaClient<T>
+where
+    T: Transport + Send + Sync,
+    Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+    ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+        ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+{
+    async fn list_spaces(&self) -> Result<ListSpacesResp, ListSpacesError> {
+        MetaClient::list_spaces(self).await
+    }
+
+    async fn get_space(&self, space_name: Vec<u8>) -> Result<GetSpaceResp, GetSpaceError> {
+        MetaClient::get_space(self, space_name).await
+    }
+
+    async fn list_parts(
+        &self,
+        space_id: i32,
+        part_ids: Vec<i32>,
+    ) -> Result<ListPartsResp, ListPartsError> {
+        MetaClient::list_parts(self, space_id, part_ids).await
+    }
+
+    async fn list_tags(&self, space_id: i32) -> Result<ListTagsResp, ListTagsError> {
+        MetaClient::list_tags(self, space_id).await
+    }
+
+    async fn list_edges(&self, space_id: i32) -> Result<ListEdgesResp, ListEdgesError> {
+        MetaClient::list_edges(self, space_id).await
+    }
+
+    async fn get_parts(&self, space_id: i32) -> Result<GetPartsAllocResp, GetPartsAllocError> {
+        MetaClient::get_parts(self, space_id).await
+    }
+
+    fn is_close_required(&self) -> bool {
+        MetaClient::is_close_required(self)
+    }
+}
+
+//
+#[cfg(feature = "impl_tokio")]
+pub use self::impl_tokio::{TokioMetaClient, TokioMetaTransport};
+
+#[cfg(feature = "impl_tokio")]
+mod impl_tokio {
+    use fbthrift_transport::{
+        impl_tokio::{TokioSleep, TokioTcpStream},
+        AsyncTransport,
+    };
+
+    use super::super::{MetaClient, MetaTransportResponseHandler};
+
+    pub type TokioMetaTransport =
+        AsyncTransport<TokioTcpStream, TokioSleep, MetaTransportResponseHandler>;
+    pub type TokioMetaClient = MetaClient<TokioMetaTransport>;
+}
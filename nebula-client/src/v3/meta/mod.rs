@@ -1,5 +1,10 @@
 pub mod client;
 pub use client::MetaClient;
 
+pub mod handle;
+pub use handle::{BoxMetaClient, MetaHandle};
+#[cfg(feature = "impl_tokio")]
+pub use handle::{TokioMetaClient, TokioMetaTransport};
+
 pub mod transport_response_handler;
 pub use transport_response_handler::MetaTransportResponseHandler;
@@ -1,14 +1,13 @@
-use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind};
+use std::io::Error as IoError;
 
-use fbthrift::{
-    binary_protocol::BinaryProtocolDeserializer, ApplicationException, Deserialize, MessageType,
-    ProtocolReader,
-};
+use fbthrift::{ApplicationException, Deserialize, MessageType, ProtocolReader};
 use fbthrift_transport_response_handler::ResponseHandler;
 use nebula_fbthrift_meta::v3::services::meta_service::{
-    GetSpaceExn, ListEdgesExn, ListPartsExn, ListSpacesExn, ListTagsExn,GetPartsAllocExn,
+    GetPartsAllocExn, GetSpaceExn, ListEdgesExn, ListPartsExn, ListSpacesExn, ListTagsExn,
 };
 
+use crate::v3::protocol::frame_len;
+
 #[derive(Clone)]
 pub struct MetaTransportResponseHandler;
 
@@ -24,91 +23,97 @@ impl ResponseHandler for MetaTransportResponseHandler {
             | b"MetaService.getSpace"
             | b"MetaService.listParts"
             | b"MetaService.listTags"
-            | b"MetaService.listEdges" 
+            | b"MetaService.listEdges"
             | b"MetaService.getPartsAlloc" => Ok(None),
-            _ => Err(IoError::new(
-                IoErrorKind::Other,
-                format!("Unknown method {}", String::from_utf8_lossy(fn_name)),
-            )),
+            _ => Err(IoError::other(format!(
+                "Unknown method {}",
+                String::from_utf8_lossy(fn_name)
+            ))),
         }
     }
 
     fn parse_response_bytes(&mut self, response_bytes: &[u8]) -> Result<Option<usize>, IoError> {
-        let mut des = BinaryProtocolDeserializer::new(Cursor::new(response_bytes));
-        let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
-
-        match &name[..] {
-            b"listSpaces" | b"getSpace" | b"listParts" | b"listTags" | b"listEdges" | b"getPartsAlloc"=> {}
-            _ => return Ok(None),
-        };
-
-        match message_type {
-            MessageType::Reply => {
-                match &name[..] {
-                    b"listSpaces" => {
-                        let _: ListSpacesExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"getSpace" => {
-                        let _: GetSpaceExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"listParts" => {
-                        let _: ListPartsExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"listTags" => {
-                        let _: ListTagsExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"listEdges" => {
-                        let _: ListEdgesExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"getPartsAlloc" => {
-                        let _: GetPartsAllocExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    _ => unreachable!(),
-                };
-            }
-            MessageType::Exception => {
-                let _: ApplicationException = match Deserialize::read(&mut des) {
-                    Ok(v) => v,
-                    Err(_) => return Ok(None),
-                };
-            }
-            MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
-        }
+        Ok(frame_len(response_bytes, parse_response, parse_response))
+    }
+}
 
-        match des.read_message_end() {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
+fn parse_response<D: ProtocolReader>(des: &mut D) -> Option<()> {
+    let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
 
-        Ok(Some(des.into_inner().position() as usize))
+    match &name[..] {
+        b"listSpaces" | b"getSpace" | b"listParts" | b"listTags" | b"listEdges"
+        | b"getPartsAlloc" => {}
+        _ => return None,
+    };
+
+    match message_type {
+        MessageType::Reply => {
+            match &name[..] {
+                b"listSpaces" => {
+                    let _: ListSpacesExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"getSpace" => {
+                    let _: GetSpaceExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"listParts" => {
+                    let _: ListPartsExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"listTags" => {
+                    let _: ListTagsExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"listEdges" => {
+                    let _: ListEdgesExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"getPartsAlloc" => {
+                    let _: GetPartsAllocExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                _ => unreachable!(),
+            };
+        }
+        MessageType::Exception => {
+            let _: ApplicationException = match Deserialize::read(des) {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+        }
+        MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
     }
+
+    match des.read_message_end() {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
+
+    Some(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::io::ErrorKind as IoErrorKind;
+
     #[test]
     fn test_try_make_static_response_bytes() -> Result<(), Box<dyn std::error::Error>> {
         let mut handler = MetaTransportResponseHandler;
@@ -0,0 +1,138 @@
+//! Client metrics, emitted through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! Only built with the `metrics` feature; nothing is recorded until a recorder
+//! (e.g. `metrics-exporter-prometheus`) is installed. Every metric has the
+//! `service` (`graph`, `meta`, `storage`) and `method` labels.
+
+use core::time::Duration;
+use std::time::Instant;
+
+/// Requests sent, counter.
+pub const REQUESTS_TOTAL: &str = "nebula_client_requests_total";
+/// Failed requests, counter with an extra `error_code` label.
+/// Transport and protocol failures use the `transport` error code.
+pub const ERRORS_TOTAL: &str = "nebula_client_errors_total";
+/// Latency observed by the client, histogram in seconds.
+pub const REQUEST_DURATION_SECONDS: &str = "nebula_client_request_duration_seconds";
+/// `latency_in_us` reported by the server, histogram in seconds.
+pub const SERVER_LATENCY_SECONDS: &str = "nebula_client_server_latency_seconds";
+/// Graph sessions flagged for closing after a broken pipe or an invalid session, counter.
+pub const SESSIONS_BROKEN_TOTAL: &str = "nebula_client_sessions_broken_total";
+
+/// Registers units and descriptions of the metrics above with the installed recorder.
+pub fn describe() {
+    use metrics::{describe_counter, describe_histogram, Unit};
+
+    describe_counter!(REQUESTS_TOTAL, "Requests sent to Nebula Graph");
+    describe_counter!(ERRORS_TOTAL, "Requests that failed, by error code");
+    describe_histogram!(
+        REQUEST_DURATION_SECONDS,
+        Unit::Seconds,
+        "Request latency observed by the client"
+    );
+    describe_histogram!(
+        SERVER_LATENCY_SECONDS,
+        Unit::Seconds,
+        "Request latency reported by the server"
+    );
+    describe_counter!(
+        SESSIONS_BROKEN_TOTAL,
+        "Graph sessions that must be closed and reopened"
+    );
+}
+
+//
+pub(crate) struct Request {
+    service: &'static str,
+    method: &'static str,
+    start: Instant,
+}
+
+impl Request {
+    pub(crate) fn new(service: &'static str, method: &'static str) -> Self {
+        Self {
+            service,
+            method,
+            start: Instant::now(),
+        }
+    }
+
+    /// `server_latency_us` is the response's `latency_in_us`, `error_code` is `None` on success.
+    pub(crate) fn finish(&self, server_latency_us: Option<i64>, error_code: Option<String>) {
+        let labels = [("service", self.service), ("method", self.method)];
+
+        metrics::counter!(REQUESTS_TOTAL, &labels).increment(1);
+        metrics::histogram!(REQUEST_DURATION_SECONDS, &labels)
+            .record(self.start.elapsed().as_secs_f64());
+        if let Some(server_latency_us) = server_latency_us {
+            let server_latency =
+                Duration::from_micros(u64::try_from(server_latency_us).unwrap_or_default());
+            metrics::histogram!(SERVER_LATENCY_SECONDS, &labels)
+                .record(server_latency.as_secs_f64());
+        }
+        if let Some(error_code) = error_code {
+            metrics::counter!(
+                ERRORS_TOTAL,
+                "service" => self.service,
+                "method" => self.method,
+                "error_code" => error_code
+            )
+            .increment(1);
+        }
+    }
+}
+
+#[cfg(feature = "graph")]
+pub(crate) fn session_broken() {
+    metrics::counter!(SESSIONS_BROKEN_TOTAL).increment(1);
+}
+
+pub(crate) const TRANSPORT_ERROR: &str = "transport";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_finish() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        metrics::with_local_recorder(&recorder, || {
+            let request = Request::new("graph", "execute");
+            request.finish(Some(1000), None);
+            request.finish(Some(1000), Some("E_SEMANTIC_ERROR".to_owned()));
+        });
+
+        let mut found = vec![];
+        for (key, _, _, value) in snapshotter.snapshot().into_vec() {
+            let key = key.key();
+            let labels: Vec<String> = key
+                .labels()
+                .map(|x| format!("{}={}", x.key(), x.value()))
+                .collect();
+            match value {
+                DebugValue::Counter(n) => {
+                    found.push(format!("{} {} {}", key.name(), labels.join(","), n))
+                }
+                DebugValue::Histogram(v) => {
+                    found.push(format!("{} {} {}", key.name(), labels.join(","), v.len()))
+                }
+                DebugValue::Gauge(_) => {}
+            }
+        }
+        found.sort();
+
+        assert_eq!(
+            found,
+            vec![
+                "nebula_client_errors_total service=graph,method=execute,error_code=E_SEMANTIC_ERROR 1",
+                "nebula_client_request_duration_seconds service=graph,method=execute 2",
+                "nebula_client_requests_total service=graph,method=execute 2",
+                "nebula_client_server_latency_seconds service=graph,method=execute 2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest as _, Sha256};
+
+use super::MigrateError;
+use crate::v3::graph::split_statements;
+
+//
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub statements: Vec<String>,
+    /// Hex SHA-256 of the file content.
+    pub checksum: String,
+    pub path: Option<PathBuf>,
+}
+
+impl Migration {
+    pub fn new(version: u64, name: impl Into<String>, ngql: &str) -> Self {
+        Self {
+            version,
+            name: name.into(),
+            statements: split_statements(ngql),
+            checksum: checksum(ngql),
+            path: None,
+        }
+    }
+
+    /// Reads `V<version>__<name>.ngql`, e.g. `V3__add_follow_edge.ngql`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, MigrateError> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        let (version, name) =
+            parse_file_name(file_name).ok_or_else(|| MigrateError::InvalidFileName(path.into()))?;
+
+        let ngql = fs::read_to_string(path).map_err(|err| MigrateError::Io(path.into(), err))?;
+
+        Ok(Self {
+            path: Some(path.into()),
+            ..Self::new(version, name, &ngql)
+        })
+    }
+}
+
+/// Loads every `V<version>__<name>.ngql` of `dir`, sorted by version.
+/// Other files are ignored, duplicate versions are an error.
+pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Migration>, MigrateError> {
+    let dir = dir.as_ref();
+
+    let mut migrations = vec![];
+    for entry in fs::read_dir(dir).map_err(|err| MigrateError::Io(dir.into(), err))? {
+        let path = entry
+            .map_err(|err| MigrateError::Io(dir.into(), err))?
+            .path();
+        if path.extension().and_then(|x| x.to_str()) != Some("ngql") {
+            continue;
+        }
+        migrations.push(Migration::from_file(path)?);
+    }
+
+    migrations.sort_by_key(|x| x.version);
+    if let Some(pair) = migrations.windows(2).find(|x| x[0].version == x[1].version) {
+        return Err(MigrateError::DuplicateVersion(pair[0].version));
+    }
+
+    Ok(migrations)
+}
+
+fn parse_file_name(file_name: &str) -> Option<(u64, String)> {
+    let stem = file_name.strip_suffix(".ngql")?;
+    let rest = stem.strip_prefix('V').or_else(|| stem.strip_prefix('v'))?;
+    let (version, name) = rest.split_once("__")?;
+    let version = version.parse().ok()?;
+
+    Some((version, name.replace('_', " ")))
+}
+
+fn checksum(ngql: &str) -> String {
+    Sha256::digest(ngql.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Statements that change the schema and need time to reach every graphd and storaged.
+pub fn is_schema_statement(statement: &str) -> bool {
+    let words: Vec<String> = statement
+        .split_whitespace()
+        .take(2)
+        .map(|x| x.to_ascii_uppercase())
+        .collect();
+
+    match words.first().map(|x| x.as_str()) {
+        Some("CREATE" | "ALTER" | "DROP") => matches!(
+            words.get(1).map(|x| x.as_str()),
+            Some("TAG" | "EDGE" | "INDEX" | "FULLTEXT")
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            parse_file_name("V12__add_follow_edge.ngql"),
+            Some((12, "add follow edge".to_owned()))
+        );
+        assert_eq!(parse_file_name("V12_add.ngql"), None);
+        assert_eq!(parse_file_name("README.md"), None);
+
+        assert!(is_schema_statement("create tag player(name string)"));
+        assert!(is_schema_statement("ALTER EDGE follow ADD (x int)"));
+        assert!(!is_schema_statement(
+            "INSERT VERTEX player(name) VALUES 1:(\"a\")"
+        ));
+        assert!(!is_schema_statement("CREATE SPACE s(vid_type=INT64)"));
+    }
+}
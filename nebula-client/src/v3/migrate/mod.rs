@@ -0,0 +1,656 @@
+//! Versioned schema migrations.
+//!
+//! Migrations are `V<version>__<name>.ngql` files applied in version order to
+//! one space. Applied versions are recorded as vertices of the `__migration`
+//! tag in that space, together with the file checksum, so a migration that
+//! was edited after being applied is reported instead of silently skipped.
+//!
+//! Schema changes (`CREATE TAG`, `ALTER EDGE`, ...) only reach every graphd and
+//! storaged after a heartbeat, so the runner waits
+//! [`MigratorConfig::propagation_wait`] between a schema statement and the
+//! next statement that is not one. Set it to the cluster's heartbeat interval
+//! or more.
+//!
+//! In `FIXED_STRING(<len>)` spaces the history vertex ids are
+//! `__migration_<version>`, so `<len>` must fit them; a shorter length is
+//! reported before anything runs.
+
+use core::{fmt, time::Duration};
+use std::{io::Error as IoError, path::PathBuf};
+
+use serde::Deserialize;
+
+use super::graph::{GraphQuery, GraphQueryError};
+
+pub mod migration;
+pub use migration::{load_dir, Migration};
+
+use migration::is_schema_statement;
+
+const HISTORY_TAG: &str = "__migration";
+
+//
+#[derive(Debug, Clone)]
+pub struct MigratorConfig {
+    /// Default 20s, twice the default `heartbeat_interval_secs`.
+    pub propagation_wait: Duration,
+    /// Only report what would run.
+    pub dry_run: bool,
+}
+
+impl Default for MigratorConfig {
+    fn default() -> Self {
+        Self {
+            propagation_wait: Duration::from_secs(20),
+            dry_run: false,
+        }
+    }
+}
+
+impl MigratorConfig {
+    pub fn with_propagation_wait(mut self, propagation_wait: Duration) -> Self {
+        self.propagation_wait = propagation_wait;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Pending,
+    Applied {
+        /// Unix seconds.
+        applied_at: i64,
+    },
+    /// Applied, but the file changed since.
+    ChecksumMismatch {
+        applied_checksum: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: u64,
+    pub name: String,
+    pub state: MigrationState,
+}
+
+/// A migration applied by [`Migrator::migrate`], or only planned on a dry run.
+#[derive(Debug, Clone)]
+pub struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    pub statements: Vec<String>,
+}
+
+//
+pub struct Migrator<'a, G> {
+    session: &'a mut G,
+    space: String,
+    config: MigratorConfig,
+}
+
+impl<'a, G> Migrator<'a, G>
+where
+    G: GraphQuery + Send,
+{
+    pub fn new(session: &'a mut G, space: impl Into<String>) -> Self {
+        Self::with_config(session, space, MigratorConfig::default())
+    }
+
+    pub fn with_config(
+        session: &'a mut G,
+        space: impl Into<String>,
+        config: MigratorConfig,
+    ) -> Self {
+        Self {
+            session,
+            space: space.into(),
+            config,
+        }
+    }
+
+    pub async fn status(
+        &mut self,
+        migrations: &[Migration],
+    ) -> Result<Vec<MigrationStatus>, MigrateError> {
+        self.run(&format!("USE `{}`", self.space)).await?;
+        let vid_type = self.vid_type().await?;
+
+        let history = if self.history_exists().await? {
+            self.history(migrations, vid_type).await?
+        } else {
+            vec![]
+        };
+
+        Ok(migrations
+            .iter()
+            .map(|migration| {
+                let state = match history
+                    .iter()
+                    .find(|x| x.version == migration.version as i64)
+                {
+                    Some(row) if row.checksum == migration.checksum => MigrationState::Applied {
+                        applied_at: row.applied_at,
+                    },
+                    Some(row) => MigrationState::ChecksumMismatch {
+                        applied_checksum: row.checksum.clone(),
+                    },
+                    None => MigrationState::Pending,
+                };
+
+                MigrationStatus {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                    state,
+                }
+            })
+            .collect())
+    }
+
+    /// Applies every pending migration in version order, stopping at the first failure.
+    ///
+    /// Fails before running anything when an applied migration changed, or when a
+    /// pending migration is older than an applied one.
+    pub async fn migrate(
+        &mut self,
+        migrations: &[Migration],
+    ) -> Result<Vec<AppliedMigration>, MigrateError> {
+        let statuses = self.status(migrations).await?;
+
+        if let Some(status) = statuses
+            .iter()
+            .find(|x| matches!(x.state, MigrationState::ChecksumMismatch { .. }))
+        {
+            return Err(MigrateError::ChecksumMismatch(status.version));
+        }
+        let last_applied = statuses
+            .iter()
+            .filter(|x| matches!(x.state, MigrationState::Applied { .. }))
+            .map(|x| x.version)
+            .max();
+        if let Some(last_applied) = last_applied {
+            if let Some(status) = statuses
+                .iter()
+                .find(|x| x.state == MigrationState::Pending && x.version < last_applied)
+            {
+                return Err(MigrateError::OutOfOrder(status.version, last_applied));
+            }
+        }
+
+        let pending: Vec<&Migration> = migrations
+            .iter()
+            .zip(statuses.iter())
+            .filter(|(_, status)| status.state == MigrationState::Pending)
+            .map(|(migration, _)| migration)
+            .collect();
+
+        if self.config.dry_run || pending.is_empty() {
+            return Ok(pending.into_iter().map(AppliedMigration::from).collect());
+        }
+
+        let vid_type = self.vid_type().await?;
+        let vids = pending
+            .iter()
+            .map(|x| history_vid(x.version, vid_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !self.history_exists().await? {
+            self.run(&format!(
+                "CREATE TAG IF NOT EXISTS {HISTORY_TAG}(version int, name string, checksum string, applied_at int)"
+            ))
+            .await?;
+            self.wait().await;
+        }
+
+        let mut applied = vec![];
+        for (migration, vid) in pending.into_iter().zip(vids) {
+            let mut schema_changed = false;
+            for statement in migration.statements.iter() {
+                let is_schema = is_schema_statement(statement);
+                if schema_changed && !is_schema {
+                    self.wait().await;
+                    schema_changed = false;
+                }
+
+                self.run(statement)
+                    .await
+                    .map_err(|err| MigrateError::Statement {
+                        version: migration.version,
+                        statement: statement.clone(),
+                        source: Box::new(err),
+                    })?;
+                schema_changed |= is_schema;
+            }
+            if schema_changed {
+                self.wait().await;
+            }
+
+            self.run(&format!(
+                "INSERT VERTEX {HISTORY_TAG}(version, name, checksum, applied_at) VALUES {}:({}, \"{}\", \"{}\", timestamp())",
+                vid,
+                migration.version,
+                migration.name.replace('\\', "\\\\").replace('"', "\\\""),
+                migration.checksum,
+            ))
+            .await?;
+
+            applied.push(AppliedMigration::from(migration));
+        }
+
+        Ok(applied)
+    }
+
+    async fn run(&mut self, statement: &str) -> Result<(), MigrateError> {
+        self.session
+            .query(&statement.as_bytes().to_vec())
+            .await
+            .map(|_| ())
+            .map_err(MigrateError::Query)
+    }
+
+    async fn wait(&self) {
+        tokio::time::sleep(self.config.propagation_wait).await
+    }
+
+    async fn vid_type(&mut self) -> Result<VidType, MigrateError> {
+        #[derive(Deserialize)]
+        struct Row {
+            #[serde(rename(deserialize = "Vid Type"))]
+            vid_type: String,
+        }
+
+        let output = self
+            .session
+            .query_as::<Row>(&format!("DESCRIBE SPACE `{}`", self.space).into_bytes())
+            .await
+            .map_err(MigrateError::Query)?;
+
+        Ok(output
+            .data_set
+            .first()
+            .map(|x| VidType::parse(&x.vid_type))
+            .unwrap_or(VidType::FixedString(None)))
+    }
+
+    async fn history_exists(&mut self) -> Result<bool, MigrateError> {
+        #[derive(Deserialize)]
+        struct Row {
+            #[serde(rename(deserialize = "Name"))]
+            name: String,
+        }
+
+        let output = self
+            .session
+            .query_as::<Row>(&b"SHOW TAGS".to_vec())
+            .await
+            .map_err(MigrateError::Query)?;
+
+        Ok(output.data_set.iter().any(|x| x.name == HISTORY_TAG))
+    }
+
+    async fn history(
+        &mut self,
+        migrations: &[Migration],
+        vid_type: VidType,
+    ) -> Result<Vec<HistoryRow>, MigrateError> {
+        if migrations.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let vids = migrations
+            .iter()
+            .map(|x| history_vid(x.version, vid_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        let statement = format!(
+            "FETCH PROP ON {HISTORY_TAG} {} YIELD properties(vertex).version AS version, properties(vertex).checksum AS checksum, properties(vertex).applied_at AS applied_at",
+            vids.join(", ")
+        );
+
+        let output = self
+            .session
+            .query_as::<HistoryRow>(&statement.into_bytes())
+            .await
+            .map_err(MigrateError::Query)?;
+
+        Ok(output.data_set)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct HistoryRow {
+    version: i64,
+    checksum: String,
+    applied_at: i64,
+}
+
+/// `Vid Type` of `DESCRIBE SPACE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VidType {
+    Int,
+    /// `None` when the length could not be read.
+    FixedString(Option<usize>),
+}
+
+impl VidType {
+    /// `INT64`, `FIXED_STRING(32)`.
+    fn parse(s: &str) -> Self {
+        let s = s.to_ascii_uppercase();
+        if s.contains("INT") {
+            return Self::Int;
+        }
+        Self::FixedString(
+            s.split_once('(')
+                .and_then(|(_, x)| x.trim_end_matches(')').trim().parse().ok()),
+        )
+    }
+}
+
+/// Vertex id of a history entry. INT64 spaces use ids counted up from `i64::MIN`,
+/// away from the ids applications usually pick.
+fn history_vid(version: u64, vid_type: VidType) -> Result<String, MigrateError> {
+    match vid_type {
+        VidType::Int => Ok((i64::MIN + version as i64).to_string()),
+        VidType::FixedString(len) => {
+            let vid = format!("{HISTORY_TAG}_{version}");
+            match len {
+                Some(len) if vid.len() > len => Err(MigrateError::HistoryVidTooLong(vid, len)),
+                _ => Ok(format!("\"{vid}\"")),
+            }
+        }
+    }
+}
+
+impl From<&Migration> for AppliedMigration {
+    fn from(migration: &Migration) -> Self {
+        Self {
+            version: migration.version,
+            name: migration.name.clone(),
+            statements: migration.statements.clone(),
+        }
+    }
+}
+
+//
+//
+//
+#[derive(Debug)]
+pub enum MigrateError {
+    Io(PathBuf, IoError),
+    InvalidFileName(PathBuf),
+    DuplicateVersion(u64),
+    ChecksumMismatch(u64),
+    /// A pending version older than the last applied one.
+    OutOfOrder(u64, u64),
+    /// The history vertex id does not fit the `FIXED_STRING` length of the space.
+    HistoryVidTooLong(String, usize),
+    Query(GraphQueryError),
+    Statement {
+        version: u64,
+        statement: String,
+        source: Box<MigrateError>,
+    },
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "Io {} {err}", path.display()),
+            Self::InvalidFileName(path) => write!(
+                f,
+                "InvalidFileName {}, expected V<version>__<name>.ngql",
+                path.display()
+            ),
+            Self::DuplicateVersion(version) => write!(f, "DuplicateVersion {version}"),
+            Self::ChecksumMismatch(version) => {
+                write!(
+                    f,
+                    "ChecksumMismatch V{version} changed after it was applied"
+                )
+            }
+            Self::OutOfOrder(version, last_applied) => write!(
+                f,
+                "OutOfOrder V{version} is pending but V{last_applied} is already applied"
+            ),
+            Self::HistoryVidTooLong(vid, len) => write!(
+                f,
+                "HistoryVidTooLong {vid} does not fit the FIXED_STRING({len}) vertex ids of the space"
+            ),
+            Self::Query(err) => write!(f, "Query {err}"),
+            Self::Statement {
+                version,
+                statement,
+                source,
+            } => write!(f, "Statement V{version} `{statement}` {source}"),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            Self::Query(err) => Some(err),
+            Self::Statement { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::dependencies::common::types::Value;
+
+    use crate::v3::{
+        graph::GraphSession,
+        mock::{Matcher, MockGraph, MockTransport, Reply},
+        GraphClient,
+    };
+
+    fn mock(vid_type: &str, history: Vec<(&Migration, &str)>) -> MockGraph {
+        let mock = MockGraph::new();
+        mock.on(
+            "DESCRIBE SPACE `nba`",
+            Reply::rows(&["Vid Type"], vec![vec![Value::sVal(vid_type.into())]]),
+        );
+        let tags = if history.is_empty() {
+            vec![]
+        } else {
+            vec![vec![Value::sVal(HISTORY_TAG.into())]]
+        };
+        mock.on("SHOW TAGS", Reply::rows(&["Name"], tags));
+        mock.on(
+            Matcher::regex(&format!("^FETCH PROP ON {HISTORY_TAG} ")),
+            Reply::rows(
+                &["version", "checksum", "applied_at"],
+                history
+                    .into_iter()
+                    .map(|(migration, checksum)| {
+                        vec![
+                            Value::iVal(migration.version as i64),
+                            Value::sVal(checksum.into()),
+                            Value::iVal(1700000000),
+                        ]
+                    })
+                    .collect(),
+            ),
+        );
+        mock.on(
+            Matcher::regex("^(CREATE|INSERT)"),
+            Reply::Response(Default::default()),
+        );
+        mock
+    }
+
+    async fn session(mock: &MockGraph) -> GraphSession<MockTransport> {
+        GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await
+            .expect("authenticated")
+    }
+
+    fn config() -> MigratorConfig {
+        MigratorConfig::default().with_propagation_wait(Duration::ZERO)
+    }
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration::new(1, "add player", "CREATE TAG player(name string);"),
+            Migration::new(
+                2,
+                "add tim",
+                "INSERT VERTEX player(name) VALUES \"p1\":(\"Tim\");",
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn with_status() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock(
+            "FIXED_STRING(32)",
+            vec![(&migrations[0], &migrations[0].checksum)],
+        );
+        let mut session = session(&mock).await;
+
+        let statuses = Migrator::with_config(&mut session, "nba", config())
+            .status(&migrations)
+            .await?;
+        assert_eq!(
+            statuses.iter().map(|x| x.state.clone()).collect::<Vec<_>>(),
+            vec![
+                MigrationState::Applied {
+                    applied_at: 1700000000
+                },
+                MigrationState::Pending,
+            ]
+        );
+        mock.assert_received(format!(
+            "FETCH PROP ON {HISTORY_TAG} \"__migration_1\", \"__migration_2\" YIELD properties(vertex).version AS version, properties(vertex).checksum AS checksum, properties(vertex).applied_at AS applied_at"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_migrate() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock("INT64", vec![]);
+        let mut session = session(&mock).await;
+
+        let applied = Migrator::with_config(&mut session, "nba", config())
+            .migrate(&migrations)
+            .await?;
+        assert_eq!(
+            applied.iter().map(|x| x.version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let statements: Vec<String> = mock
+            .statements()
+            .into_iter()
+            .filter(|x| x.starts_with("CREATE") || x.starts_with("INSERT"))
+            .collect();
+        assert_eq!(statements.len(), 5);
+        assert!(statements[0].starts_with(&format!("CREATE TAG IF NOT EXISTS {HISTORY_TAG}(")));
+        assert_eq!(statements[1], "CREATE TAG player(name string)");
+        assert!(statements[2].starts_with(&format!(
+            "INSERT VERTEX {HISTORY_TAG}(version, name, checksum, applied_at) VALUES {}:(1, \"add player\", ",
+            i64::MIN + 1
+        )));
+        assert_eq!(
+            statements[3],
+            "INSERT VERTEX player(name) VALUES \"p1\":(\"Tim\")"
+        );
+        assert!(statements[4].contains(&format!("VALUES {}:(2, ", i64::MIN + 2)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_out_of_order() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock(
+            "FIXED_STRING(32)",
+            vec![(&migrations[1], &migrations[1].checksum)],
+        );
+        let mut session = session(&mock).await;
+
+        let err = Migrator::with_config(&mut session, "nba", config())
+            .migrate(&migrations)
+            .await
+            .expect_err("V1 is older than V2");
+        assert!(matches!(err, MigrateError::OutOfOrder(1, 2)));
+        mock.assert_not_received(Matcher::regex("^(CREATE|INSERT)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_checksum_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock("FIXED_STRING(32)", vec![(&migrations[0], "edited")]);
+        let mut session = session(&mock).await;
+
+        let mut migrator = Migrator::with_config(&mut session, "nba", config());
+        assert_eq!(
+            migrator.status(&migrations).await?[0].state,
+            MigrationState::ChecksumMismatch {
+                applied_checksum: "edited".to_owned()
+            }
+        );
+        let err = migrator.migrate(&migrations).await.expect_err("V1 changed");
+        assert!(matches!(err, MigrateError::ChecksumMismatch(1)));
+        mock.assert_not_received(Matcher::regex("^(CREATE|INSERT)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock(
+            "FIXED_STRING(32)",
+            vec![(&migrations[0], &migrations[0].checksum)],
+        );
+        let mut session = session(&mock).await;
+
+        let planned = Migrator::with_config(&mut session, "nba", config().with_dry_run(true))
+            .migrate(&migrations)
+            .await?;
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].version, 2);
+        assert_eq!(planned[0].statements, migrations[1].statements);
+        mock.assert_not_received(Matcher::regex("^(CREATE|INSERT)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_short_fixed_string() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = migrations();
+        let mock = mock("FIXED_STRING(8)", vec![]);
+        let mut session = session(&mock).await;
+
+        let err = Migrator::with_config(&mut session, "nba", config())
+            .migrate(&migrations)
+            .await
+            .expect_err("__migration_1 is longer than 8");
+        assert!(matches!(err, MigrateError::HistoryVidTooLong(_, 8)));
+        mock.assert_not_received(Matcher::regex("^(CREATE|INSERT)"));
+
+        assert_eq!(VidType::parse("INT64"), VidType::Int);
+        assert_eq!(
+            VidType::parse("FIXED_STRING(32)"),
+            VidType::FixedString(Some(32))
+        );
+
+        Ok(())
+    }
+}
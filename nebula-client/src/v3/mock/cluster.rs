@@ -0,0 +1,500 @@
+//! An in-memory metad and storaged, to test scans without a cluster.
+//!
+//! ```ignore
+//! use nebula_client::v3::{mock::MockCluster, scan_vertex};
+//!
+//! let cluster = MockCluster::new();
+//! cluster
+//!     .space("nba", 3, PropertyType::FIXED_STRING)
+//!     .tag("nba", "player", &[("name", PropertyType::STRING), ("age", PropertyType::INT64)])
+//!     .insert_vertex("nba", "player", Value::sVal(b"p1".to_vec()), vec![Value::sVal(b"Tim".to_vec()), Value::iVal(42)]);
+//!
+//! let meta = cluster.serve_meta().await?;
+//! let storage = [cluster.serve_storage().await?, cluster.serve_storage().await?];
+//!
+//! let rows = scan_vertex(meta.addr().to_string(), "nba".to_owned(), "player".to_owned()).await?;
+//! assert_eq!(cluster.scanned().len(), 3);
+//! ```
+//!
+//! Vertices are stored in the partition graphd routes their vid to, edges in
+//! the partition of their source. Each partition has a single replica, led by
+//! the host set with [`MockCluster::leader`], or else by the storage hosts in
+//! turn. A host asked for a partition it does not lead fails that partition
+//! with `E_LEADER_CHANGED`.
+//!
+//! Scans return at most [`page_size`](MockCluster::page_size) rows per
+//! partition and response; `next_cursor` holds the offset of the next row.
+
+use std::{
+    collections::BTreeMap,
+    io::Error as IoError,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use nebula_fbthrift_storage::v3::dependencies::common::types::{HostAddr, PropertyType, Value};
+
+use super::{MockMeta, MockServer, MockStorage};
+
+//
+pub(super) struct SchemaItem {
+    pub(super) id: i32,
+    pub(super) name: String,
+    pub(super) props: Vec<(String, PropertyType)>,
+}
+
+pub(super) struct Vertex {
+    pub(super) vid: Value,
+    /// Property values by tag id, in schema order.
+    pub(super) tags: BTreeMap<i32, Vec<Value>>,
+}
+
+pub(super) struct Edge {
+    pub(super) src: Value,
+    pub(super) edge_type: i32,
+    pub(super) rank: i64,
+    pub(super) dst: Value,
+    pub(super) props: Vec<Value>,
+}
+
+pub(super) struct Space {
+    pub(super) id: i32,
+    pub(super) name: String,
+    pub(super) partitions: i32,
+    pub(super) vid_type: PropertyType,
+    pub(super) tags: Vec<SchemaItem>,
+    pub(super) edge_types: Vec<SchemaItem>,
+    pub(super) leaders: BTreeMap<i32, HostAddr>,
+    /// By partition.
+    pub(super) vertices: BTreeMap<i32, Vec<Vertex>>,
+    /// By partition of the source.
+    pub(super) edges: BTreeMap<i32, Vec<Edge>>,
+}
+
+impl Space {
+    fn next_schema_id(&self) -> i32 {
+        (self.tags.len() + self.edge_types.len()) as i32 + 1
+    }
+
+    fn part_of(&self, vid: &Value) -> i32 {
+        let hash = match vid {
+            Value::iVal(x) => *x as u64,
+            Value::sVal(x) => murmur_hash2(x),
+            vid => panic!("unsupported vid {vid:?}"),
+        };
+        (hash % self.partitions as u64) as i32 + 1
+    }
+}
+
+/// A partition a storage host was asked to scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scanned {
+    /// `host:port` of the storage host.
+    pub host: String,
+    pub space_id: i32,
+    pub part_id: i32,
+    pub cursor: Option<Vec<u8>>,
+    /// `scanEdge` rather than `scanVertex`.
+    pub edge: bool,
+}
+
+#[derive(Default)]
+pub(super) struct State {
+    pub(super) spaces: Vec<Space>,
+    pub(super) hosts: Vec<HostAddr>,
+    pub(super) page_size: Option<usize>,
+    pub(super) scanned: Vec<Scanned>,
+}
+
+impl State {
+    pub(super) fn space(&self, space_id: i32) -> Option<&Space> {
+        self.spaces.iter().find(|x| x.id == space_id)
+    }
+
+    pub(super) fn leader(&self, space: &Space, part_id: i32) -> Option<HostAddr> {
+        match space.leaders.get(&part_id) {
+            Some(leader) => Some(leader.clone()),
+            None if self.hosts.is_empty() => None,
+            None => Some(self.hosts[(part_id - 1) as usize % self.hosts.len()].clone()),
+        }
+    }
+
+    /// Panics on an unknown space.
+    fn space_mut(&mut self, name: &str) -> &mut Space {
+        self.spaces
+            .iter_mut()
+            .find(|x| x.name == name)
+            .unwrap_or_else(|| panic!("unknown space {name}"))
+    }
+}
+
+/// The data behind [`MockMeta`] and [`MockStorage`]. Clones share the same data and history.
+///
+/// The builder methods panic on unknown spaces, tags and edge types, and on
+/// property values that do not match the schema.
+#[derive(Clone, Default)]
+pub struct MockCluster {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockCluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `vid_type` is `FIXED_STRING` (reported with length 32) or `INT64`.
+    pub fn space(&self, name: &str, partitions: i32, vid_type: PropertyType) -> &Self {
+        assert!(partitions > 0, "a space needs partitions");
+
+        let mut state = self.state();
+        let id = state.spaces.len() as i32 + 1;
+        state.spaces.push(Space {
+            id,
+            name: name.to_owned(),
+            partitions,
+            vid_type,
+            tags: vec![],
+            edge_types: vec![],
+            leaders: BTreeMap::new(),
+            vertices: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        });
+        self
+    }
+
+    pub fn tag(&self, space: &str, name: &str, props: &[(&str, PropertyType)]) -> &Self {
+        let mut state = self.state();
+        let space = state.space_mut(space);
+        let item = schema_item(space.next_schema_id(), name, props);
+        space.tags.push(item);
+        self
+    }
+
+    pub fn edge_type(&self, space: &str, name: &str, props: &[(&str, PropertyType)]) -> &Self {
+        let mut state = self.state();
+        let space = state.space_mut(space);
+        let item = schema_item(space.next_schema_id(), name, props);
+        space.edge_types.push(item);
+        self
+    }
+
+    /// Adds `tag` to the vertex, or replaces its values. `props` follow the tag schema.
+    pub fn insert_vertex(&self, space: &str, tag: &str, vid: Value, props: Vec<Value>) -> &Self {
+        let mut state = self.state();
+        let space = state.space_mut(space);
+        let tag_id = schema_id(&space.tags, tag, &props);
+        let part_id = space.part_of(&vid);
+
+        let vertices = space.vertices.entry(part_id).or_default();
+        match vertices.iter_mut().find(|x| x.vid == vid) {
+            Some(vertex) => {
+                vertex.tags.insert(tag_id, props);
+            }
+            None => vertices.push(Vertex {
+                vid,
+                tags: BTreeMap::from([(tag_id, props)]),
+            }),
+        }
+        self
+    }
+
+    /// Adds the edge, or replaces its values. `props` follow the edge type schema.
+    pub fn insert_edge(
+        &self,
+        space: &str,
+        edge_type: &str,
+        src: Value,
+        dst: Value,
+        rank: i64,
+        props: Vec<Value>,
+    ) -> &Self {
+        let mut state = self.state();
+        let space = state.space_mut(space);
+        let edge_type = schema_id(&space.edge_types, edge_type, &props);
+        let part_id = space.part_of(&src);
+
+        let edges = space.edges.entry(part_id).or_default();
+        edges.retain(|x| {
+            !(x.src == src && x.edge_type == edge_type && x.rank == rank && x.dst == dst)
+        });
+        edges.push(Edge {
+            src,
+            edge_type,
+            rank,
+            dst,
+            props,
+        });
+        self
+    }
+
+    /// Moves a partition to `addr` (`host:port`), which does not have to be a registered storage host.
+    pub fn leader(&self, space: &str, part_id: i32, addr: &str) -> &Self {
+        let mut state = self.state();
+        state
+            .space_mut(space)
+            .leaders
+            .insert(part_id, host_addr(addr));
+        self
+    }
+
+    /// Rows per partition and scan response, on top of the request `limit`. Unlimited by default.
+    pub fn page_size(&self, page_size: usize) -> &Self {
+        self.state().page_size = Some(page_size);
+        self
+    }
+
+    //
+    pub fn meta(&self) -> MockMeta {
+        MockMeta::new(self.clone())
+    }
+
+    /// The storage host at `addr` (`host:port`), registered on first use.
+    pub fn storage(&self, addr: &str) -> MockStorage {
+        let host = host_addr(addr);
+        let mut state = self.state();
+        if !state.hosts.contains(&host) {
+            state.hosts.push(host.clone());
+        }
+        MockStorage::new(self.clone(), host)
+    }
+
+    pub async fn serve_meta(&self) -> Result<MockServer, IoError> {
+        self.meta().serve().await
+    }
+
+    /// Serves a new storage host on a local port, registered under that address.
+    pub async fn serve_storage(&self) -> Result<MockServer, IoError> {
+        let this = self.clone();
+        MockServer::bind_with(move |addr| this.storage(&addr.to_string()).handler()).await
+    }
+
+    //
+    pub fn scanned(&self) -> Vec<Scanned> {
+        self.state().scanned.clone()
+    }
+
+    pub fn clear_scanned(&self) {
+        self.state().scanned.clear();
+    }
+
+    pub(super) fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("not poisoned")
+    }
+}
+
+fn schema_item(id: i32, name: &str, props: &[(&str, PropertyType)]) -> SchemaItem {
+    SchemaItem {
+        id,
+        name: name.to_owned(),
+        props: props
+            .iter()
+            .map(|(name, r#type)| (name.to_string(), *r#type))
+            .collect(),
+    }
+}
+
+fn schema_id(items: &[SchemaItem], name: &str, props: &[Value]) -> i32 {
+    let item = items
+        .iter()
+        .find(|x| x.name == name)
+        .unwrap_or_else(|| panic!("unknown schema {name}"));
+    assert_eq!(
+        item.props.len(),
+        props.len(),
+        "{name} has {} properties",
+        item.props.len()
+    );
+    item.id
+}
+
+fn host_addr(addr: &str) -> HostAddr {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        .unwrap_or_else(|| panic!("invalid address {addr}, expected host:port"));
+    HostAddr {
+        host: host.to_owned(),
+        port,
+        ..Default::default()
+    }
+}
+
+/// MurmurHash64A with the seed of nebula, which graphd routes string vids with.
+fn murmur_hash2(data: &[u8]) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    const SEED: u64 = 0xc70f_6907;
+
+    let mut h = SEED ^ (data.len() as u64).wrapping_mul(M);
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, b) in tail.iter().enumerate() {
+            h ^= (*b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+    use nebula_fbthrift_storage::v3::types::{ScanVertexRequest, VertexProp};
+
+    use crate::v3::{
+        scan_edge, scan_vertex, MetaClient, MetaTransportResponseHandler, StorageClient,
+        StorageTransportResponseHandler, ThriftProtocol,
+    };
+
+    fn s(x: &str) -> Value {
+        Value::sVal(x.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_part_of() {
+        let cluster = MockCluster::new();
+        cluster.space("nba", 3, PropertyType::INT64);
+        let state = cluster.state();
+        let space = &state.spaces[0];
+
+        assert_eq!(space.part_of(&Value::iVal(7)), 2);
+        assert_eq!(space.part_of(&Value::iVal(-1)), 1);
+        assert!((1..=3).contains(&space.part_of(&s("player100"))));
+    }
+
+    #[tokio::test]
+    async fn with_scan() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster
+            .space("nba", 3, PropertyType::FIXED_STRING)
+            .tag(
+                "nba",
+                "player",
+                &[("name", PropertyType::STRING), ("age", PropertyType::INT64)],
+            )
+            .edge_type("nba", "follow", &[("degree", PropertyType::INT64)]);
+        for (vid, name, age) in [("p1", "Tim", 42), ("p2", "Tony", 36), ("p3", "Manu", 41)] {
+            cluster.insert_vertex("nba", "player", s(vid), vec![s(name), Value::iVal(age)]);
+        }
+        cluster
+            .insert_edge("nba", "follow", s("p2"), s("p1"), 0, vec![Value::iVal(95)])
+            .insert_edge("nba", "follow", s("p3"), s("p1"), 0, vec![Value::iVal(90)]);
+
+        let meta = cluster.serve_meta().await?;
+        let storage = [
+            cluster.serve_storage().await?,
+            cluster.serve_storage().await?,
+        ];
+
+        let mut vertices: Vec<String> =
+            scan_vertex(meta.addr().to_string(), "nba".into(), "player".into())
+                .await?
+                .into_iter()
+                .flatten()
+                .collect();
+        vertices.sort();
+        assert_eq!(
+            vertices,
+            vec![
+                "(p1:player{name: Tim, age: 42})",
+                "(p2:player{name: Tony, age: 36})",
+                "(p3:player{name: Manu, age: 41})",
+            ]
+        );
+
+        let scanned = cluster.scanned();
+        assert_eq!(
+            scanned
+                .iter()
+                .map(|x| (x.host.clone(), x.part_id))
+                .collect::<Vec<_>>(),
+            vec![
+                (storage[0].addr().to_string(), 1),
+                (storage[1].addr().to_string(), 2),
+                (storage[0].addr().to_string(), 3),
+            ]
+        );
+
+        let mut edges: Vec<String> =
+            scan_edge(meta.addr().to_string(), "nba".into(), "follow".into())
+                .await?
+                .into_iter()
+                .flatten()
+                .collect();
+        edges.sort();
+        assert_eq!(
+            edges,
+            vec![
+                "(p2)-[:follow@0{degree: 95}]->(p1)",
+                "(p3)-[:follow@0{degree: 90}]->(p1)",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_compact() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster
+            .space("nba", 1, PropertyType::INT64)
+            .tag("nba", "player", &[("name", PropertyType::STRING)])
+            .insert_vertex("nba", "player", Value::iVal(1), vec![s("Tim")]);
+        let meta = cluster.serve_meta().await?;
+        let storage = cluster.serve_storage().await?;
+
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            meta.addr(),
+            AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+        )
+        .await?;
+        let client = MetaClient::new_with_protocol(transport, ThriftProtocol::Compact);
+        let res = client.get_space(b"nba".to_vec()).await?;
+        assert_eq!(res.item.properties.space_name, b"nba".to_vec());
+        let parts = client.get_parts(1).await?;
+        assert_eq!(parts.parts.len(), 1);
+
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            storage.addr(),
+            AsyncTransportConfiguration::new(StorageTransportResponseHandler),
+        )
+        .await?;
+        let client = StorageClient::new_with_protocol(transport, ThriftProtocol::Compact);
+        let res = client
+            .scan_vertex(&ScanVertexRequest {
+                space_id: 1,
+                parts: [(1, Default::default())].into(),
+                return_columns: vec![VertexProp {
+                    tag: 1,
+                    props: vec![b"name".to_vec()],
+                    ..Default::default()
+                }],
+                limit: 100,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(res.result.failed_parts.len(), 0);
+        assert_eq!(
+            res.props.map(|x| x.rows[0].values.clone()),
+            Some(vec![Value::iVal(1), s("Tim")])
+        );
+
+        Ok(())
+    }
+}
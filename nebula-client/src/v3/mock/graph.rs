@@ -0,0 +1,586 @@
+//! A scripted graphd.
+//!
+//! ```ignore
+//! use nebula_client::v3::{mock::{Matcher, MockGraph, Reply}, GraphClient, GraphQuery as _};
+//!
+//! let mock = MockGraph::new();
+//! mock.on("SHOW HOSTS", Reply::rows(&["Host", "Port"], vec![vec![Value::sVal(b"h1".to_vec()), Value::iVal(9779)]]));
+//! mock.on(Matcher::regex(r"^INSERT VERTEX"), Reply::Response(Default::default()));
+//! mock.on(Matcher::regex(r"^DROP"), Reply::Error(ErrorCode::E_BAD_PERMISSION, "read only".to_owned()));
+//!
+//! let mut session = GraphClient::new(mock.transport()).authenticate(&b"root".to_vec(), &b"nebula".to_vec()).await?;
+//! app_code(&mut session).await?;
+//!
+//! mock.assert_received("INSERT VERTEX player(name) VALUES \"p1\":(\"Tim\")");
+//! ```
+//!
+//! Rules are tried in the order they were added. A statement no rule matches
+//! gets an `E_EXECUTION_ERROR` response, except `USE <space>`, which succeeds
+//! and sets the space of the session.
+
+use std::{
+    collections::BTreeMap,
+    io::Error as IoError,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use fbthrift::{ApplicationException, ApplicationExceptionErrorCode};
+use futures::future::FutureExt as _;
+use nebula_fbthrift_graph::v3::{
+    dependencies::common::types::{DataSet, ErrorCode, Row, Value},
+    server::{make_GraphService_server, GraphService},
+    services::graph_service::{AuthenticateExn, ExecuteExn, ExecuteJsonExn, SignoutExn},
+    types::{AuthResponse, ExecutionResponse},
+};
+use regex::Regex;
+
+use super::{dispatch, protocol_id, MockReplyState, MockRequestContext, MockServer, MockTransport};
+
+//
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// Same statement, ignoring surrounding whitespace and a trailing `;`.
+    Exact(String),
+    Regex(Regex),
+    Any,
+}
+
+impl Matcher {
+    pub fn exact(statement: impl Into<String>) -> Self {
+        Self::Exact(statement.into())
+    }
+
+    /// Panics on an invalid pattern.
+    pub fn regex(pattern: &str) -> Self {
+        Self::Regex(Regex::new(pattern).expect("valid regex"))
+    }
+
+    pub fn matches(&self, statement: &str) -> bool {
+        match self {
+            Self::Exact(x) => normalize(x) == normalize(statement),
+            Self::Regex(x) => x.is_match(statement),
+            Self::Any => true,
+        }
+    }
+}
+
+impl From<&str> for Matcher {
+    fn from(statement: &str) -> Self {
+        Self::exact(statement)
+    }
+}
+
+impl From<String> for Matcher {
+    fn from(statement: String) -> Self {
+        Self::Exact(statement)
+    }
+}
+
+fn normalize(statement: &str) -> &str {
+    statement.trim().trim_end_matches(';').trim_end()
+}
+
+//
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Returned by `execute`. The space of the session is filled in when `space_name` is unset,
+    /// and `SUCCEEDED` when `error_code` is left to its default.
+    Response(ExecutionResponse),
+    /// Returned by `executeJson`.
+    Json(Vec<u8>),
+    /// A response with this error code and message, to `execute` and `executeJson`.
+    Error(ErrorCode, String),
+    /// A thrift `ApplicationException`, as if the call itself failed.
+    Exception(String),
+}
+
+impl Reply {
+    /// A successful response with a data set.
+    pub fn rows(column_names: &[&str], rows: Vec<Vec<Value>>) -> Self {
+        Self::Response(ExecutionResponse {
+            data: Some(DataSet {
+                column_names: column_names.iter().map(|x| x.as_bytes().to_vec()).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|values| Row {
+                        values,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+impl From<ExecutionResponse> for Reply {
+    fn from(res: ExecutionResponse) -> Self {
+        Self::Response(res)
+    }
+}
+
+/// A statement the mock received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Received {
+    pub session_id: i64,
+    pub statement: String,
+    /// Sent with `executeJson`.
+    pub json: bool,
+}
+
+//
+struct Rule {
+    matcher: Matcher,
+    reply: Reply,
+    /// `None` for every match.
+    remaining: Option<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    rules: Vec<Rule>,
+    credentials: Option<(Vec<u8>, Vec<u8>)>,
+    last_session_id: i64,
+    /// Open sessions and their space.
+    sessions: BTreeMap<i64, Option<Vec<u8>>>,
+    signed_out: Vec<i64>,
+    received: Vec<Received>,
+}
+
+/// A scripted `GraphService`. Clones share the same rules and history.
+#[derive(Clone, Default)]
+pub struct MockGraph {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accepts this user, others get `E_BAD_USERNAME_PASSWORD`. Any user is accepted by default.
+    pub fn with_credentials(self, username: &str, password: &str) -> Self {
+        self.state().credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Replies to every statement `matcher` matches.
+    pub fn on(&self, matcher: impl Into<Matcher>, reply: impl Into<Reply>) -> &Self {
+        self.push_rule(matcher.into(), reply.into(), None)
+    }
+
+    /// Replies to the next `times` statements `matcher` matches, then falls through to later rules.
+    pub fn on_times(
+        &self,
+        matcher: impl Into<Matcher>,
+        reply: impl Into<Reply>,
+        times: usize,
+    ) -> &Self {
+        self.push_rule(matcher.into(), reply.into(), Some(times))
+    }
+
+    fn push_rule(&self, matcher: Matcher, reply: Reply, remaining: Option<usize>) -> &Self {
+        self.state().rules.push(Rule {
+            matcher,
+            reply,
+            remaining,
+        });
+        self
+    }
+
+    pub fn transport(&self) -> MockTransport {
+        let this = self.clone();
+        MockTransport::new(Arc::new(move |req| this.clone().handle(req).boxed()))
+    }
+
+    /// Serves the mock on a local port, for code that connects by address.
+    pub async fn serve(&self) -> Result<MockServer, IoError> {
+        let this = self.clone();
+        MockServer::bind(Arc::new(move |req| this.clone().handle(req).boxed())).await
+    }
+
+    async fn handle(self, req: Bytes) -> anyhow::Result<Bytes> {
+        let service = make_GraphService_server::<Bytes, _, MockRequestContext, MockReplyState>(
+            protocol_id(&req),
+            self,
+        )?;
+        dispatch(service.as_ref(), req).await
+    }
+
+    //
+    pub fn received(&self) -> Vec<Received> {
+        self.state().received.clone()
+    }
+
+    pub fn statements(&self) -> Vec<String> {
+        self.state()
+            .received
+            .iter()
+            .map(|x| x.statement.clone())
+            .collect()
+    }
+
+    /// Session ids that are still signed in.
+    pub fn sessions(&self) -> Vec<i64> {
+        self.state().sessions.keys().copied().collect()
+    }
+
+    /// Drops the session as graphd does when it times out; its statements get `E_SESSION_INVALID`.
+    pub fn expire_session(&self, session_id: i64) {
+        self.state().sessions.remove(&session_id);
+    }
+
+    pub fn signed_out(&self) -> Vec<i64> {
+        self.state().signed_out.clone()
+    }
+
+    pub fn clear_received(&self) {
+        self.state().received.clear();
+    }
+
+    #[track_caller]
+    pub fn assert_received(&self, matcher: impl Into<Matcher>) {
+        let matcher = matcher.into();
+        let statements = self.statements();
+        assert!(
+            statements.iter().any(|x| matcher.matches(x)),
+            "no statement matches {matcher:?}, received {statements:#?}"
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_not_received(&self, matcher: impl Into<Matcher>) {
+        let matcher = matcher.into();
+        let statements = self.statements();
+        assert!(
+            !statements.iter().any(|x| matcher.matches(x)),
+            "a statement matches {matcher:?}, received {statements:#?}"
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_received_in_session(&self, session_id: i64, matcher: impl Into<Matcher>) {
+        let matcher = matcher.into();
+        let received = self.received();
+        assert!(
+            received
+                .iter()
+                .any(|x| x.session_id == session_id && matcher.matches(&x.statement)),
+            "no statement of session {session_id} matches {matcher:?}, received {received:#?}"
+        );
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("not poisoned")
+    }
+
+    fn reply(&self, session_id: i64, stmt: &[u8], json: bool) -> Reply {
+        let statement = String::from_utf8_lossy(stmt).into_owned();
+
+        let mut state = self.state();
+        state.received.push(Received {
+            session_id,
+            statement: statement.clone(),
+            json,
+        });
+
+        let space_name = match state.sessions.get(&session_id) {
+            Some(space_name) => space_name.clone(),
+            None => {
+                return Reply::Error(
+                    ErrorCode::E_SESSION_INVALID,
+                    format!("Session `{session_id}' not found"),
+                )
+            }
+        };
+
+        let rule = state
+            .rules
+            .iter_mut()
+            .find(|x| x.remaining != Some(0) && x.matcher.matches(&statement));
+        let reply = match rule {
+            Some(rule) => {
+                if let Some(remaining) = rule.remaining.as_mut() {
+                    *remaining -= 1;
+                }
+                rule.reply.clone()
+            }
+            None => match use_space(&statement) {
+                Some(space) => {
+                    state.sessions.insert(session_id, Some(space.into()));
+                    return Reply::Response(ExecutionResponse {
+                        error_code: ErrorCode::SUCCEEDED,
+                        space_name: Some(space.into()),
+                        ..Default::default()
+                    });
+                }
+                None => Reply::Error(
+                    ErrorCode::E_EXECUTION_ERROR,
+                    format!("no mock reply for `{statement}`"),
+                ),
+            },
+        };
+
+        match reply {
+            Reply::Response(mut res) => {
+                if res.error_code == ErrorCode::default() {
+                    res.error_code = ErrorCode::SUCCEEDED;
+                }
+                if res.space_name.is_none() {
+                    res.space_name = space_name;
+                }
+                Reply::Response(res)
+            }
+            reply => reply,
+        }
+    }
+}
+
+fn use_space(statement: &str) -> Option<&str> {
+    let statement = normalize(statement);
+    let (keyword, space) = statement.split_once(char::is_whitespace)?;
+    keyword
+        .eq_ignore_ascii_case("USE")
+        .then(|| space.trim().trim_matches('`'))
+}
+
+fn error_response(error_code: ErrorCode, error_msg: String) -> ExecutionResponse {
+    ExecutionResponse {
+        error_code,
+        error_msg: Some(error_msg.into_bytes()),
+        ..Default::default()
+    }
+}
+
+fn exception(msg: String) -> ApplicationException {
+    ApplicationException::new(ApplicationExceptionErrorCode::Unknown, msg)
+}
+
+#[async_trait]
+impl GraphService for MockGraph {
+    async fn authenticate(
+        &self,
+        username: Vec<u8>,
+        password: Vec<u8>,
+    ) -> Result<AuthResponse, AuthenticateExn> {
+        let mut state = self.state();
+        if let Some(credentials) = state.credentials.as_ref() {
+            if credentials != &(username, password) {
+                return Ok(AuthResponse {
+                    error_code: ErrorCode::E_BAD_USERNAME_PASSWORD,
+                    error_msg: Some(b"Invalid password".to_vec()),
+                    ..Default::default()
+                });
+            }
+        }
+
+        state.last_session_id += 1;
+        let session_id = state.last_session_id;
+        state.sessions.insert(session_id, None);
+
+        Ok(AuthResponse {
+            error_code: ErrorCode::SUCCEEDED,
+            session_id: Some(session_id),
+            ..Default::default()
+        })
+    }
+
+    async fn signout(&self, session_id: i64) -> Result<(), SignoutExn> {
+        let mut state = self.state();
+        if state.sessions.remove(&session_id).is_some() {
+            state.signed_out.push(session_id);
+        }
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        session_id: i64,
+        stmt: Vec<u8>,
+    ) -> Result<ExecutionResponse, ExecuteExn> {
+        match self.reply(session_id, &stmt, false) {
+            Reply::Response(res) => Ok(res),
+            Reply::Json(_) => Err(ExecuteExn::ApplicationException(exception(
+                "the mock reply is JSON, use executeJson".to_owned(),
+            ))),
+            Reply::Error(code, msg) => Ok(error_response(code, msg)),
+            Reply::Exception(msg) => Err(ExecuteExn::ApplicationException(exception(msg))),
+        }
+    }
+
+    async fn executeJson(&self, session_id: i64, stmt: Vec<u8>) -> Result<Vec<u8>, ExecuteJsonExn> {
+        let error = |code: ErrorCode, msg: &str| {
+            format!(
+                r#"{{"errors":[{{"code":{},"message":{:?}}}],"results":[]}}"#,
+                code.0, msg
+            )
+            .into_bytes()
+        };
+
+        match self.reply(session_id, &stmt, true) {
+            Reply::Json(json) => Ok(json),
+            Reply::Response(_) => Err(ExecuteJsonExn::ApplicationException(exception(
+                "the mock reply is not JSON, use execute".to_owned(),
+            ))),
+            Reply::Error(code, msg) => Ok(error(code, &msg)),
+            Reply::Exception(msg) => Err(ExecuteJsonExn::ApplicationException(exception(msg))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+
+    use crate::v3::{GraphClient, GraphQuery as _, GraphTransportResponseHandler, ThriftProtocol};
+
+    fn mock() -> MockGraph {
+        let mock = MockGraph::new().with_credentials("root", "nebula");
+        mock.on(
+            "SHOW SPACES",
+            Reply::rows(&["Name"], vec![vec![Value::sVal(b"nba".to_vec())]]),
+        );
+        mock.on_times(
+            Matcher::regex(r"^DROP"),
+            Reply::Error(ErrorCode::E_BAD_PERMISSION, "read only".to_owned()),
+            1,
+        );
+        mock.on(
+            Matcher::regex(r"^DROP"),
+            Reply::Exception("broken".to_owned()),
+        );
+        mock
+    }
+
+    #[tokio::test]
+    async fn with_transport() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = mock();
+
+        assert!(GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"x".to_vec())
+            .await
+            .is_err());
+
+        let mut session = GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let session_id = session.session_id();
+
+        let res = session.execute(&b"SHOW SPACES;".to_vec()).await?;
+        assert_eq!(res.data.map(|x| x.rows.len()), Some(1));
+
+        session.query(&b"USE nba".to_vec()).await?;
+        assert_eq!(session.space_name(), Some(&b"nba"[..]));
+
+        let res = session.execute(&b"DROP TAG player".to_vec()).await?;
+        assert_eq!(res.error_code, ErrorCode::E_BAD_PERMISSION);
+        assert!(session.execute(&b"DROP TAG player".to_vec()).await.is_err());
+
+        let res = session.execute(&b"MATCH (v) RETURN v".to_vec()).await?;
+        assert_eq!(res.error_code, ErrorCode::E_EXECUTION_ERROR);
+
+        mock.assert_received("SHOW SPACES");
+        mock.assert_received_in_session(session_id, Matcher::regex("^DROP TAG"));
+        mock.assert_not_received("DROP SPACE nba");
+        assert_eq!(mock.statements().len(), 5);
+
+        session.signout().await?;
+        assert_eq!(mock.signed_out(), vec![session_id]);
+        assert!(mock.sessions().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_attach() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = mock();
+
+        let session = GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let session_id = session.session_id();
+        drop(session);
+
+        let mut session = GraphClient::new(mock.transport()).attach(session_id);
+        session.query(&b"USE nba".to_vec()).await?;
+        mock.assert_received_in_session(session_id, "USE nba");
+
+        mock.expire_session(session_id);
+        let res = session.execute(&b"SHOW SPACES".to_vec()).await?;
+        assert_eq!(res.error_code, ErrorCode::E_SESSION_INVALID);
+        assert!(session.is_close_required());
+        assert!(session.is_session_invalid());
+
+        let session_id = GraphClient::new(mock.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?
+            .session_id();
+        GraphClient::new(mock.transport())
+            .signout(session_id)
+            .await?;
+        assert_eq!(mock.signed_out(), vec![session_id]);
+        assert!(mock.sessions().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_server() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = mock();
+        let server = mock.serve().await?;
+
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            server.addr(),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        )
+        .await?;
+        let mut session = GraphClient::new(transport)
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+
+        let res = session.execute(&b"SHOW SPACES".to_vec()).await?;
+        assert_eq!(res.data.map(|x| x.rows.len()), Some(1));
+        let res = session.execute(&b"SHOW HOSTS".to_vec()).await?;
+        assert_eq!(res.error_code, ErrorCode::E_EXECUTION_ERROR);
+
+        assert_eq!(mock.sessions(), vec![session.session_id()]);
+        assert_eq!(mock.statements(), vec!["SHOW SPACES", "SHOW HOSTS"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_compact() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = mock();
+        let server = mock.serve().await?;
+
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            server.addr(),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        )
+        .await?;
+        let mut session = GraphClient::new_with_protocol(transport, ThriftProtocol::Compact)
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let session_id = session.session_id();
+
+        let res = session.execute(&b"SHOW SPACES".to_vec()).await?;
+        assert_eq!(res.data.map(|x| x.rows.len()), Some(1));
+
+        session.signout().await?;
+        for _ in 0..100 {
+            if !mock.signed_out().is_empty() {
+                break;
+            }
+            tokio::time::sleep(core::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(mock.signed_out(), vec![session_id]);
+
+        Ok(())
+    }
+}
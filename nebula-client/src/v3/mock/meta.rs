@@ -0,0 +1,285 @@
+//! The `MetaService` calls scans make, answered from a [`MockCluster`].
+
+use std::{io::Error as IoError, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::FutureExt as _;
+use nebula_fbthrift_meta::v3::{
+    dependencies::common::types::{ErrorCode, HostAddr, PropertyType},
+    server::{make_MetaService_server, MetaService},
+    services::meta_service::{
+        GetPartsAllocExn, GetSpaceExn, ListEdgesExn, ListPartsExn, ListSpacesExn, ListTagsExn,
+    },
+    types::{
+        ColumnDef, ColumnTypeDef, EdgeItem, GetPartsAllocReq, GetPartsAllocResp, GetSpaceReq,
+        GetSpaceResp, IdName, ListEdgesReq, ListEdgesResp, ListPartsReq, ListPartsResp,
+        ListSpacesReq, ListSpacesResp, ListTagsReq, ListTagsResp, PartItem, Schema, SpaceDesc,
+        SpaceItem, TagItem, ID,
+    },
+};
+use nebula_fbthrift_storage::v3::dependencies::common::types as storage;
+
+use super::{
+    cluster::{MockCluster, SchemaItem, Space},
+    dispatch, protocol_id, Handler, MockReplyState, MockRequestContext, MockServer, MockTransport,
+};
+
+/// Answers `listSpaces`, `getSpace`, `listTags`, `listEdges`, `getPartsAlloc` and `listParts`; other calls fail.
+#[derive(Clone)]
+pub struct MockMeta {
+    cluster: MockCluster,
+}
+
+impl MockMeta {
+    pub(super) fn new(cluster: MockCluster) -> Self {
+        Self { cluster }
+    }
+
+    pub fn transport(&self) -> MockTransport {
+        MockTransport::new(self.handler())
+    }
+
+    /// Serves the mock on a local port, for code that connects by address.
+    pub async fn serve(&self) -> Result<MockServer, IoError> {
+        MockServer::bind(self.handler()).await
+    }
+
+    fn handler(&self) -> Handler {
+        let this = self.clone();
+        Arc::new(move |req| this.clone().handle(req).boxed())
+    }
+
+    async fn handle(self, req: Bytes) -> anyhow::Result<Bytes> {
+        let service = make_MetaService_server::<Bytes, _, MockRequestContext, MockReplyState>(
+            protocol_id(&req),
+            self,
+        )?;
+        dispatch(service.as_ref(), req).await
+    }
+
+    fn with_space<T>(&self, space_id: i32, f: impl FnOnce(&Space) -> T) -> Result<T, ErrorCode> {
+        let state = self.cluster.state();
+        state
+            .space(space_id)
+            .map(f)
+            .ok_or(ErrorCode::E_SPACE_NOT_FOUND)
+    }
+}
+
+fn schema(item: &SchemaItem) -> Schema {
+    Schema {
+        columns: item
+            .props
+            .iter()
+            .map(|(name, r#type)| ColumnDef {
+                name: name.as_bytes().to_vec(),
+                r#type: column_type(property_type(*r#type)),
+                nullable: Some(true),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+// nebula-fbthrift-meta is built against another copy of `common` than storage.
+fn property_type(r#type: storage::PropertyType) -> PropertyType {
+    PropertyType(r#type.0)
+}
+
+fn host_addr(addr: storage::HostAddr) -> HostAddr {
+    HostAddr {
+        host: addr.host,
+        port: addr.port,
+        ..Default::default()
+    }
+}
+
+fn column_type(r#type: PropertyType) -> ColumnTypeDef {
+    ColumnTypeDef {
+        r#type,
+        type_length: (r#type == PropertyType::FIXED_STRING).then_some(32),
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl MetaService for MockMeta {
+    async fn listSpaces(&self, _req: ListSpacesReq) -> Result<ListSpacesResp, ListSpacesExn> {
+        let state = self.cluster.state();
+
+        Ok(ListSpacesResp {
+            code: ErrorCode::SUCCEEDED,
+            spaces: state
+                .spaces
+                .iter()
+                .map(|space| IdName {
+                    id: ID::space_id(space.id),
+                    name: space.name.as_bytes().to_vec(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    async fn getSpace(&self, req: GetSpaceReq) -> Result<GetSpaceResp, GetSpaceExn> {
+        let state = self.cluster.state();
+        let space = match state
+            .spaces
+            .iter()
+            .find(|x| x.name.as_bytes() == req.space_name)
+        {
+            Some(space) => space,
+            None => {
+                return Ok(GetSpaceResp {
+                    code: ErrorCode::E_SPACE_NOT_FOUND,
+                    ..Default::default()
+                })
+            }
+        };
+
+        Ok(GetSpaceResp {
+            code: ErrorCode::SUCCEEDED,
+            item: SpaceItem {
+                space_id: space.id,
+                properties: SpaceDesc {
+                    space_name: space.name.as_bytes().to_vec(),
+                    partition_num: space.partitions,
+                    replica_factor: 1,
+                    charset_name: b"utf8".to_vec(),
+                    collate_name: b"utf8_bin".to_vec(),
+                    vid_type: column_type(property_type(space.vid_type)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    async fn listTags(&self, req: ListTagsReq) -> Result<ListTagsResp, ListTagsExn> {
+        let tags = self.with_space(req.space_id, |space| {
+            space
+                .tags
+                .iter()
+                .map(|tag| TagItem {
+                    tag_id: tag.id,
+                    tag_name: tag.name.as_bytes().to_vec(),
+                    schema: schema(tag),
+                    ..Default::default()
+                })
+                .collect()
+        });
+
+        Ok(match tags {
+            Ok(tags) => ListTagsResp {
+                code: ErrorCode::SUCCEEDED,
+                tags,
+                ..Default::default()
+            },
+            Err(code) => ListTagsResp {
+                code,
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn listEdges(&self, req: ListEdgesReq) -> Result<ListEdgesResp, ListEdgesExn> {
+        let edges = self.with_space(req.space_id, |space| {
+            space
+                .edge_types
+                .iter()
+                .map(|edge| EdgeItem {
+                    edge_type: edge.id,
+                    edge_name: edge.name.as_bytes().to_vec(),
+                    schema: schema(edge),
+                    ..Default::default()
+                })
+                .collect()
+        });
+
+        Ok(match edges {
+            Ok(edges) => ListEdgesResp {
+                code: ErrorCode::SUCCEEDED,
+                edges,
+                ..Default::default()
+            },
+            Err(code) => ListEdgesResp {
+                code,
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn getPartsAlloc(
+        &self,
+        req: GetPartsAllocReq,
+    ) -> Result<GetPartsAllocResp, GetPartsAllocExn> {
+        let state = self.cluster.state();
+        let space = match state.space(req.space_id) {
+            Some(space) => space,
+            None => {
+                return Ok(GetPartsAllocResp {
+                    code: ErrorCode::E_SPACE_NOT_FOUND,
+                    ..Default::default()
+                })
+            }
+        };
+
+        Ok(GetPartsAllocResp {
+            code: ErrorCode::SUCCEEDED,
+            parts: (1..=space.partitions)
+                .map(|part_id| {
+                    (
+                        part_id,
+                        state
+                            .leader(space, part_id)
+                            .map(host_addr)
+                            .into_iter()
+                            .collect(),
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    /// All partitions when `part_ids` is empty.
+    async fn listParts(&self, req: ListPartsReq) -> Result<ListPartsResp, ListPartsExn> {
+        let state = self.cluster.state();
+        let space = match state.space(req.space_id) {
+            Some(space) => space,
+            None => {
+                return Ok(ListPartsResp {
+                    code: ErrorCode::E_SPACE_NOT_FOUND,
+                    ..Default::default()
+                })
+            }
+        };
+
+        let part_ids = match req.part_ids.is_empty() {
+            true => (1..=space.partitions).collect(),
+            false => req.part_ids,
+        };
+
+        Ok(ListPartsResp {
+            code: ErrorCode::SUCCEEDED,
+            parts: part_ids
+                .into_iter()
+                .filter(|x| (1..=space.partitions).contains(x))
+                .map(|part_id| {
+                    let leader = state.leader(space, part_id).map(host_addr);
+                    PartItem {
+                        part_id,
+                        peers: leader.iter().cloned().collect(),
+                        leader,
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
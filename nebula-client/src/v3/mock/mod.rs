@@ -0,0 +1,248 @@
+//! In-process stand-ins for Nebula services, to test application code without a cluster.
+//!
+//! Enabled by the `test-support` feature. Each mock implements the generated
+//! `server` trait of its service, and can be reached two ways:
+//!
+//! * through a [`MockTransport`], an fbthrift `Transport` that hands requests
+//!   straight to the mock, e.g. `GraphClient::new(mock.transport())`;
+//! * through a [`MockServer`] listening on `127.0.0.1`, for code that opens
+//!   its own TCP connections (bb8 pools, `with_tokio_tcp_connect`).
+//!
+//! [`Recorder`] and [`Replay`] capture the calls of real transports and serve
+//! them back, for tests written against a cluster that should also run offline.
+
+use std::{
+    ffi::CStr,
+    io::{Cursor, Error as IoError},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use bytes::{Bytes, BytesMut};
+use fbthrift::{
+    binary_protocol::BinaryProtocolDeserializer, compact_protocol::CompactProtocolDeserializer,
+    DummyRequestContext, Framing, FramingDecoded, FramingEncodedFinal, ProtocolID, ProtocolReader,
+    ReplyState, SerializedStreamElement, TType, ThriftService, Transport,
+};
+use futures::{
+    future::{BoxFuture, FutureExt as _},
+    stream::BoxStream,
+};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::v3::protocol::frame_len;
+
+pub mod graph;
+pub use graph::{Matcher, MockGraph, Received, Reply};
+
+pub mod cluster;
+pub use cluster::{MockCluster, Scanned};
+
+pub mod meta;
+pub use meta::MockMeta;
+
+pub mod storage;
+pub use storage::MockStorage;
+
+pub mod recording;
+pub use recording::{Frame, Recorder, RecordingTransport, Replay, ReplayTransport};
+
+//
+//
+//
+type Handler = Arc<dyn Fn(Bytes) -> BoxFuture<'static, anyhow::Result<Bytes>> + Send + Sync>;
+
+/// An fbthrift `Transport` without a socket, see [`MockGraph::transport`] and [`MockCluster`].
+#[derive(Clone)]
+pub struct MockTransport {
+    handler: Handler,
+}
+
+impl MockTransport {
+    pub(crate) fn new(handler: Handler) -> Self {
+        Self { handler }
+    }
+}
+
+impl Framing for MockTransport {
+    type EncBuf = BytesMut;
+    type DecBuf = Cursor<Bytes>;
+
+    fn enc_with_capacity(cap: usize) -> Self::EncBuf {
+        BytesMut::with_capacity(cap)
+    }
+}
+
+impl Transport for MockTransport {
+    type RpcOptions = ();
+
+    fn call(
+        &self,
+        _service_name: &'static CStr,
+        _fn_name: &'static CStr,
+        req: FramingEncodedFinal<Self>,
+        _rpc_options: Self::RpcOptions,
+    ) -> BoxFuture<'static, anyhow::Result<FramingDecoded<Self>>> {
+        let handler = self.handler.clone();
+        async move { handler(req).await.map(Cursor::new) }.boxed()
+    }
+}
+
+//
+//
+//
+/// A mock listening on `127.0.0.1`. The listener stops when this is dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    accept: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub(crate) async fn bind(handler: Handler) -> Result<Self, IoError> {
+        Self::bind_with(|_| handler).await
+    }
+
+    /// For mocks that need to know their own address.
+    pub(crate) async fn bind_with(
+        handler: impl FnOnce(SocketAddr) -> Handler,
+    ) -> Result<Self, IoError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let handler = handler(addr);
+
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(connection(stream, handler.clone()));
+            }
+        });
+
+        Ok(Self { addr, accept })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// Requests are not framed, so a request is complete once it decodes.
+async fn connection(mut stream: TcpStream, handler: Handler) {
+    const MAX_REQUEST_LEN: usize = 64 * 1024 * 1024;
+
+    let mut buf = BytesMut::with_capacity(4096);
+    loop {
+        match stream.read_buf(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        // A oneway signout can arrive in the same read as the next request.
+        while let Some(len) = request_len(&buf) {
+            let req = buf.split_to(len).freeze();
+            let reply = match handler(req.clone()).await {
+                Ok(reply) => reply,
+                Err(_) => return,
+            };
+            // oneway, the client does not read a reply.
+            if method_name(&req).as_deref() == Some("signout") {
+                continue;
+            }
+            if stream.write_all(&reply).await.is_err() {
+                return;
+            }
+        }
+        if buf.len() >= MAX_REQUEST_LEN {
+            return;
+        }
+    }
+}
+
+/// The length of the first request in `buf`, once it is complete.
+fn request_len(buf: &[u8]) -> Option<usize> {
+    frame_len(buf, skip_message, skip_message)
+}
+
+fn skip_message<P: ProtocolReader>(des: &mut P) -> Option<()> {
+    des.read_message_begin(|_| ()).ok()?;
+    des.skip(TType::Struct).ok()?;
+    des.read_message_end().ok()
+}
+
+//
+//
+//
+pub(crate) type MockRequestContext = DummyRequestContext<CStr, Cursor<Bytes>>;
+
+#[derive(Default)]
+pub(crate) struct MockReplyState(Option<Bytes>);
+
+impl ReplyState<Bytes> for MockReplyState {
+    type RequestContext = MockRequestContext;
+
+    fn send_reply(&mut self, reply: Bytes) {
+        self.0 = Some(reply);
+    }
+
+    fn send_stream_reply(
+        &mut self,
+        _response: Bytes,
+        _stream: Option<BoxStream<'static, SerializedStreamElement<Bytes>>>,
+        _protocol_id: ProtocolID,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("streams are not supported by mocks")
+    }
+}
+
+/// Runs one request through a generated processor.
+pub(crate) async fn dispatch<S>(service: &S, req: Bytes) -> anyhow::Result<Bytes>
+where
+    S: ThriftService<Bytes, RequestContext = MockRequestContext, ReplyState = MockReplyState>
+        + ?Sized,
+{
+    let reply_state = Arc::new(Mutex::new(MockReplyState::default()));
+    service
+        .call(
+            Cursor::new(req),
+            &MockRequestContext::new(),
+            reply_state.clone(),
+        )
+        .await?;
+
+    let reply = reply_state.lock().expect("not poisoned").0.take();
+    reply.ok_or_else(|| anyhow::anyhow!("no reply"))
+}
+
+/// Compact messages start with `0x82`, strict binary ones with `0x80`.
+pub(crate) fn protocol_id(req: &[u8]) -> ProtocolID {
+    match req.first() {
+        Some(0x82) => ProtocolID::CompactProtocol,
+        _ => ProtocolID::BinaryProtocol,
+    }
+}
+
+fn method_name(req: &Bytes) -> Option<String> {
+    let name = match protocol_id(req) {
+        ProtocolID::CompactProtocol => {
+            CompactProtocolDeserializer::new(Cursor::new(req.clone()))
+                .read_message_begin(|x| x.to_vec())
+                .ok()?
+                .0
+        }
+        _ => {
+            BinaryProtocolDeserializer::new(Cursor::new(req.clone()))
+                .read_message_begin(|x| x.to_vec())
+                .ok()?
+                .0
+        }
+    };
+    String::from_utf8(name).ok()
+}
@@ -0,0 +1,425 @@
+//! Record the calls of real transports to a file, and replay them without a cluster.
+//!
+//! ```ignore
+//! use nebula_client::v3::mock::{Recorder, Replay};
+//!
+//! // Once, against a cluster.
+//! let recorder = Recorder::create("tests/recordings/players.bin")?;
+//! let client = GraphClient::new(recorder.wrap(graph_transport));
+//! let mclient = MetaClient::new(recorder.wrap(meta_transport));
+//!
+//! // Then offline.
+//! let replay = Replay::open("tests/recordings/players.bin")?;
+//! let client = GraphClient::new(replay.transport());
+//! let mclient = MetaClient::new(replay.transport());
+//! // ...
+//! assert!(replay.unplayed().is_empty());
+//! ```
+//!
+//! A call is answered with the first recorded call of the same method that
+//! was not replayed yet, so calls of one method are served in recording
+//! order. With [`Replay::match_request`] the request bytes must be identical
+//! too, which holds when the test sends the same statements in the same
+//! sessions.
+//!
+//! The file starts with a header line, then each call as a status byte and
+//! four length-prefixed (u32, big endian) fields: service, method, request,
+//! and the response or the error message.
+
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::{BufReader, BufWriter, Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use bytes::{Buf as _, Bytes, BytesMut};
+use fbthrift::{Framing, FramingDecoded, FramingEncodedFinal, Transport};
+use futures::future::{BoxFuture, FutureExt as _};
+
+const HEADER: &[u8] = b"nebula-rs recording v1\n";
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERR: u8 = 1;
+
+/// One recorded call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// e.g. `GraphService`.
+    pub service: String,
+    /// e.g. `GraphService.execute`.
+    pub method: String,
+    pub request: Bytes,
+    /// The error message when the call failed.
+    pub response: Result<Bytes, String>,
+}
+
+impl Frame {
+    fn write<W: Write>(&self, w: &mut W) -> Result<(), IoError> {
+        let (status, response) = match &self.response {
+            Ok(response) => (STATUS_OK, &response[..]),
+            Err(err) => (STATUS_ERR, err.as_bytes()),
+        };
+
+        w.write_all(&[status])?;
+        for field in [
+            self.service.as_bytes(),
+            self.method.as_bytes(),
+            &self.request[..],
+            response,
+        ] {
+            let len = u32::try_from(field.len()).map_err(IoError::other)?;
+            w.write_all(&len.to_be_bytes())?;
+            w.write_all(field)?;
+        }
+        Ok(())
+    }
+
+    fn read(buf: &mut Bytes) -> Result<Self, IoError> {
+        let invalid = |msg: &str| IoError::new(IoErrorKind::InvalidData, msg.to_owned());
+
+        if !buf.has_remaining() {
+            return Err(invalid("missing status"));
+        }
+        let status = buf.get_u8();
+
+        let mut fields = Vec::with_capacity(4);
+        for _ in 0..4 {
+            if buf.remaining() < 4 {
+                return Err(invalid("truncated length"));
+            }
+            let len = buf.get_u32() as usize;
+            if buf.remaining() < len {
+                return Err(invalid("truncated field"));
+            }
+            fields.push(buf.split_to(len));
+        }
+        let text = |bytes: Bytes| {
+            String::from_utf8(bytes.to_vec()).map_err(|_| invalid("field is not UTF-8"))
+        };
+
+        let response = fields.pop().expect("4 fields");
+        let request = fields.pop().expect("4 fields");
+        let method = text(fields.pop().expect("4 fields"))?;
+        let service = text(fields.pop().expect("4 fields"))?;
+
+        Ok(Self {
+            service,
+            method,
+            request,
+            response: match status {
+                STATUS_OK => Ok(response),
+                STATUS_ERR => Err(text(response)?),
+                _ => return Err(invalid("unknown status")),
+            },
+        })
+    }
+}
+
+fn name(x: &CStr) -> String {
+    x.to_string_lossy().into_owned()
+}
+
+//
+//
+//
+/// Writes the calls of the transports it wraps to one file. Clones share the file.
+#[derive(Clone)]
+pub struct Recorder {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Recorder {
+    /// Creates or truncates `path`.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn new(writer: impl Write + Send + 'static) -> Result<Self, IoError> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writer.write_all(HEADER)?;
+        writer.flush()?;
+        Ok(Self {
+            writer: Arc::new(Mutex::new(writer)),
+        })
+    }
+
+    pub fn wrap<T>(&self, transport: T) -> RecordingTransport<T> {
+        RecordingTransport {
+            inner: transport,
+            recorder: self.clone(),
+        }
+    }
+
+    /// Flushes every frame, so the file is usable even if the test panics later.
+    fn record(&self, frame: &Frame) -> Result<(), IoError> {
+        let mut writer = self.writer.lock().expect("not poisoned");
+        frame.write(&mut *writer)?;
+        writer.flush()
+    }
+}
+
+/// A transport that records each call to its [`Recorder`].
+#[derive(Clone)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    recorder: Recorder,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Framing for RecordingTransport<T>
+where
+    T: Framing<EncBuf = BytesMut, DecBuf = Cursor<Bytes>>,
+{
+    type EncBuf = BytesMut;
+    type DecBuf = Cursor<Bytes>;
+
+    fn enc_with_capacity(cap: usize) -> Self::EncBuf {
+        BytesMut::with_capacity(cap)
+    }
+}
+
+impl<T> Transport for RecordingTransport<T>
+where
+    T: Transport + Framing<EncBuf = BytesMut, DecBuf = Cursor<Bytes>>,
+{
+    type RpcOptions = T::RpcOptions;
+
+    fn call(
+        &self,
+        service_name: &'static CStr,
+        fn_name: &'static CStr,
+        req: FramingEncodedFinal<Self>,
+        rpc_options: Self::RpcOptions,
+    ) -> BoxFuture<'static, anyhow::Result<FramingDecoded<Self>>> {
+        let recorder = self.recorder.clone();
+        let request = req.clone();
+        let call = self.inner.call(service_name, fn_name, req, rpc_options);
+
+        async move {
+            let res = call.await.map(|cursor| {
+                let position = cursor.position() as usize;
+                cursor.into_inner().slice(position..)
+            });
+
+            recorder.record(&Frame {
+                service: name(service_name),
+                method: name(fn_name),
+                request,
+                response: res
+                    .as_ref()
+                    .map(Bytes::clone)
+                    .map_err(|err| err.to_string()),
+            })?;
+
+            res.map(Cursor::new)
+        }
+        .boxed()
+    }
+}
+
+//
+//
+//
+struct ReplayState {
+    frames: Vec<Frame>,
+    played: Vec<bool>,
+    match_request: bool,
+}
+
+/// Serves recorded calls back. Clones share the same frames.
+#[derive(Clone)]
+pub struct Replay {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self, IoError> {
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf)?;
+
+        if !buf.starts_with(HEADER) {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "not a nebula-rs recording",
+            ));
+        }
+        let mut buf = Bytes::from(buf).slice(HEADER.len()..);
+
+        let mut frames = vec![];
+        while buf.has_remaining() {
+            frames.push(Frame::read(&mut buf)?);
+        }
+
+        Ok(Self::new(frames))
+    }
+
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ReplayState {
+                played: vec![false; frames.len()],
+                frames,
+                match_request: false,
+            })),
+        }
+    }
+
+    /// Also require identical request bytes. Off by default.
+    pub fn match_request(self, yes: bool) -> Self {
+        self.state().match_request = yes;
+        self
+    }
+
+    pub fn transport(&self) -> ReplayTransport {
+        ReplayTransport {
+            replay: self.clone(),
+        }
+    }
+
+    /// Recorded calls that were not replayed yet.
+    pub fn unplayed(&self) -> Vec<Frame> {
+        let state = self.state();
+        state
+            .frames
+            .iter()
+            .zip(state.played.iter())
+            .filter(|(_, played)| !**played)
+            .map(|(frame, _)| frame.clone())
+            .collect()
+    }
+
+    fn play(&self, method: &str, request: &[u8]) -> anyhow::Result<Bytes> {
+        let mut state = self.state();
+        let state = &mut *state;
+
+        let index = state
+            .frames
+            .iter()
+            .zip(state.played.iter())
+            .position(|(frame, played)| {
+                !played
+                    && frame.method == method
+                    && (!state.match_request || frame.request == request)
+            })
+            .ok_or_else(|| anyhow::anyhow!("no recorded call left for {method}"))?;
+        state.played[index] = true;
+
+        match &state.frames[index].response {
+            Ok(response) => Ok(response.clone()),
+            Err(err) => Err(anyhow::anyhow!("{err}")),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().expect("not poisoned")
+    }
+}
+
+/// A transport answered by a [`Replay`], usable for graph, meta and storage clients.
+#[derive(Clone)]
+pub struct ReplayTransport {
+    replay: Replay,
+}
+
+impl Framing for ReplayTransport {
+    type EncBuf = BytesMut;
+    type DecBuf = Cursor<Bytes>;
+
+    fn enc_with_capacity(cap: usize) -> Self::EncBuf {
+        BytesMut::with_capacity(cap)
+    }
+}
+
+impl Transport for ReplayTransport {
+    type RpcOptions = ();
+
+    fn call(
+        &self,
+        _service_name: &'static CStr,
+        fn_name: &'static CStr,
+        req: FramingEncodedFinal<Self>,
+        _rpc_options: Self::RpcOptions,
+    ) -> BoxFuture<'static, anyhow::Result<FramingDecoded<Self>>> {
+        let res = self.replay.play(&name(fn_name), &req).map(Cursor::new);
+        async move { res }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, process};
+
+    use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+    use nebula_fbthrift_graph::v3::dependencies::common::types::{PropertyType, Value};
+
+    use crate::v3::{
+        mock::{MockCluster, MockGraph, Reply},
+        GraphClient, GraphTransportResponseHandler, MetaClient,
+    };
+
+    #[tokio::test]
+    async fn with_record_and_replay() -> Result<(), Box<dyn std::error::Error>> {
+        let path = env::temp_dir().join(format!("nebula-rs-recording-{}.bin", process::id()));
+
+        let graph = MockGraph::new();
+        graph.on(
+            "SHOW SPACES",
+            Reply::rows(&["Name"], vec![vec![Value::sVal(b"nba".to_vec())]]),
+        );
+        let cluster = MockCluster::new();
+        cluster.space("nba", 3, PropertyType::FIXED_STRING);
+
+        let server = graph.serve().await?;
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            server.addr(),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        )
+        .await?;
+
+        let recorder = Recorder::create(&path)?;
+        let mclient = MetaClient::new(recorder.wrap(cluster.meta().transport()));
+        let recorded_space = mclient.get_space(b"nba".to_vec()).await?;
+        let mut session = GraphClient::new(recorder.wrap(transport))
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let recorded = session.execute(&b"SHOW SPACES".to_vec()).await?;
+        assert!(session.execute(&b"SHOW HOSTS".to_vec()).await.is_ok());
+        drop(recorder);
+
+        let replay = Replay::open(&path)?.match_request(true);
+        fs::remove_file(&path)?;
+        assert_eq!(replay.unplayed().len(), 4);
+
+        let mut session = GraphClient::new(replay.transport())
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        assert_eq!(session.execute(&b"SHOW SPACES".to_vec()).await?, recorded);
+        let mclient = MetaClient::new(replay.transport());
+        assert_eq!(mclient.get_space(b"nba".to_vec()).await?, recorded_space);
+
+        assert!(session.execute(&b"SHOW ZONES".to_vec()).await.is_err());
+        assert_eq!(replay.unplayed().len(), 1);
+        assert_eq!(replay.unplayed()[0].method, "GraphService.execute");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(Replay::read(&b"foo"[..]).is_err());
+        assert!(Replay::read(&[HEADER, &[0, 0, 0]].concat()[..]).is_err());
+        assert!(Replay::read(HEADER).is_ok());
+    }
+}
@@ -0,0 +1,397 @@
+//! `scanVertex` and `scanEdge` of one storage host, answered from a [`MockCluster`].
+
+use std::{collections::BTreeMap, iter, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::FutureExt as _;
+use nebula_fbthrift_storage::v3::{
+    dependencies::common::types::{DataSet, ErrorCode, HostAddr, NullType, Row, Value},
+    server::{make_GraphStorageService_server, GraphStorageService},
+    services::graph_storage_service::{ScanEdgeExn, ScanVertexExn},
+    types::{
+        EdgeProp, PartitionResult, ScanCursor, ScanEdgeRequest, ScanResponse, ScanVertexRequest,
+        VertexProp,
+    },
+};
+
+use super::{
+    cluster::{MockCluster, Scanned, SchemaItem, Space},
+    dispatch, protocol_id, Handler, MockReplyState, MockRequestContext, MockTransport,
+};
+
+type Rows = BTreeMap<i32, Vec<Row>>;
+
+/// One storage host. Serve hosts with [`MockCluster::serve_storage`], or get
+/// one with [`MockCluster::storage`] to call it through [`transport`](Self::transport).
+#[derive(Clone)]
+pub struct MockStorage {
+    cluster: MockCluster,
+    host: HostAddr,
+}
+
+impl MockStorage {
+    pub(super) fn new(cluster: MockCluster, host: HostAddr) -> Self {
+        Self { cluster, host }
+    }
+
+    pub fn transport(&self) -> MockTransport {
+        MockTransport::new(self.handler())
+    }
+
+    pub(super) fn handler(&self) -> Handler {
+        let this = self.clone();
+        Arc::new(move |req| this.clone().handle(req).boxed())
+    }
+
+    async fn handle(self, req: Bytes) -> anyhow::Result<Bytes> {
+        let service =
+            make_GraphStorageService_server::<Bytes, _, MockRequestContext, MockReplyState>(
+                protocol_id(&req),
+                self,
+            )?;
+        dispatch(service.as_ref(), req).await
+    }
+
+    fn scan(
+        &self,
+        space_id: i32,
+        parts: &BTreeMap<i32, ScanCursor>,
+        limit: i64,
+        edge: bool,
+        rows: impl FnOnce(&Space) -> Result<(Vec<Vec<u8>>, Rows), ErrorCode>,
+    ) -> ScanResponse {
+        let mut state = self.cluster.state();
+        for (part_id, cursor) in parts.iter() {
+            state.scanned.push(Scanned {
+                host: format!("{}:{}", self.host.host, self.host.port),
+                space_id,
+                part_id: *part_id,
+                cursor: cursor.next_cursor.clone(),
+                edge,
+            });
+        }
+
+        let mut res = ScanResponse::default();
+        let space = state.space(space_id);
+        let (column_names, mut rows) =
+            match space.ok_or(ErrorCode::E_SPACE_NOT_FOUND).and_then(rows) {
+                Ok(x) => x,
+                Err(code) => {
+                    res.result.failed_parts = parts
+                        .keys()
+                        .map(|part_id| failed(*part_id, code, None))
+                        .collect();
+                    return res;
+                }
+            };
+        let space = space.expect("found");
+
+        let mut page = state.page_size.unwrap_or(usize::MAX);
+        if limit > 0 {
+            page = page.min(limit as usize);
+        }
+
+        let mut data = vec![];
+        for (part_id, cursor) in parts.iter() {
+            if !(1..=space.partitions).contains(part_id) {
+                res.result
+                    .failed_parts
+                    .push(failed(*part_id, ErrorCode::E_PART_NOT_FOUND, None));
+                continue;
+            }
+            let leader = state.leader(space, *part_id);
+            if leader.as_ref() != Some(&self.host) {
+                res.result
+                    .failed_parts
+                    .push(failed(*part_id, ErrorCode::E_LEADER_CHANGED, leader));
+                continue;
+            }
+
+            let offset = match cursor.next_cursor.as_deref().map(parse_cursor) {
+                None => 0,
+                Some(Some(offset)) => offset,
+                Some(None) => {
+                    res.result
+                        .failed_parts
+                        .push(failed(*part_id, ErrorCode::E_INVALID_PARM, None));
+                    continue;
+                }
+            };
+
+            let part_rows = rows.remove(part_id).unwrap_or_default();
+            let end = offset.saturating_add(page).min(part_rows.len());
+            let next_cursor = (end < part_rows.len()).then(|| end.to_string().into_bytes());
+            data.extend(part_rows.into_iter().take(end).skip(offset));
+            res.cursors.insert(
+                *part_id,
+                ScanCursor {
+                    next_cursor,
+                    ..Default::default()
+                },
+            );
+        }
+
+        res.props = Some(DataSet {
+            column_names,
+            rows: data,
+            ..Default::default()
+        });
+        res
+    }
+}
+
+fn failed(part_id: i32, code: ErrorCode, leader: Option<HostAddr>) -> PartitionResult {
+    PartitionResult {
+        code,
+        part_id,
+        leader,
+        ..Default::default()
+    }
+}
+
+fn parse_cursor(cursor: &[u8]) -> Option<usize> {
+    std::str::from_utf8(cursor).ok()?.parse().ok()
+}
+
+const NULL: Value = Value::nVal(NullType::__NULL__);
+
+//
+enum Field {
+    /// Also `_vid` of vertices.
+    Src,
+    Type,
+    Rank,
+    Dst,
+    Prop(usize),
+}
+
+/// Columns are `_vid`, then `tag.prop` for each requested property, like storaged.
+fn vertex_rows(space: &Space, columns: &[VertexProp]) -> Result<(Vec<Vec<u8>>, Rows), ErrorCode> {
+    let mut column_names = vec![b"_vid".to_vec()];
+    let mut fields = vec![];
+    for column in columns {
+        let tag = schema(&space.tags, column.tag).ok_or(ErrorCode::E_TAG_NOT_FOUND)?;
+        for prop in column.props.iter() {
+            let field = match &prop[..] {
+                b"_vid" => Field::Src,
+                name => Field::Prop(position(tag, name).ok_or(ErrorCode::E_TAG_PROP_NOT_FOUND)?),
+            };
+            column_names.push(column_name(tag, prop));
+            fields.push((tag.id, field));
+        }
+    }
+
+    let rows = space
+        .vertices
+        .iter()
+        .map(|(part_id, vertices)| {
+            let rows = vertices
+                .iter()
+                .filter(|x| columns.iter().any(|c| x.tags.contains_key(&c.tag)))
+                .map(|vertex| {
+                    let values = fields.iter().map(|(tag_id, field)| {
+                        match (vertex.tags.get(tag_id), field) {
+                            (None, _) => NULL,
+                            (Some(props), Field::Prop(i)) => props[*i].clone(),
+                            (Some(_), _) => vertex.vid.clone(),
+                        }
+                    });
+                    row(iter::once(vertex.vid.clone()).chain(values).collect())
+                })
+                .collect();
+            (*part_id, rows)
+        })
+        .collect();
+
+    Ok((column_names, rows))
+}
+
+/// Columns are `edge.prop` for each requested property, like storaged.
+fn edge_rows(space: &Space, columns: &[EdgeProp]) -> Result<(Vec<Vec<u8>>, Rows), ErrorCode> {
+    let mut column_names = vec![];
+    let mut fields = vec![];
+    for column in columns {
+        let edge_type =
+            schema(&space.edge_types, column.r#type).ok_or(ErrorCode::E_EDGE_NOT_FOUND)?;
+        for prop in column.props.iter() {
+            let field = match &prop[..] {
+                b"_src" => Field::Src,
+                b"_type" => Field::Type,
+                b"_rank" => Field::Rank,
+                b"_dst" => Field::Dst,
+                name => {
+                    Field::Prop(position(edge_type, name).ok_or(ErrorCode::E_EDGE_PROP_NOT_FOUND)?)
+                }
+            };
+            column_names.push(column_name(edge_type, prop));
+            fields.push((edge_type.id, field));
+        }
+    }
+
+    let rows = space
+        .edges
+        .iter()
+        .map(|(part_id, edges)| {
+            let rows = edges
+                .iter()
+                .filter(|x| columns.iter().any(|c| c.r#type == x.edge_type))
+                .map(|edge| {
+                    let values = fields.iter().map(|(edge_type, field)| match field {
+                        _ if *edge_type != edge.edge_type => NULL,
+                        Field::Src => edge.src.clone(),
+                        Field::Type => Value::iVal(edge.edge_type as i64),
+                        Field::Rank => Value::iVal(edge.rank),
+                        Field::Dst => edge.dst.clone(),
+                        Field::Prop(i) => edge.props[*i].clone(),
+                    });
+                    row(values.collect())
+                })
+                .collect();
+            (*part_id, rows)
+        })
+        .collect();
+
+    Ok((column_names, rows))
+}
+
+fn schema(items: &[SchemaItem], id: i32) -> Option<&SchemaItem> {
+    items.iter().find(|x| x.id == id)
+}
+
+fn position(item: &SchemaItem, name: &[u8]) -> Option<usize> {
+    item.props.iter().position(|(x, _)| x.as_bytes() == name)
+}
+
+fn column_name(item: &SchemaItem, prop: &[u8]) -> Vec<u8> {
+    [item.name.as_bytes(), b".", prop].concat()
+}
+
+fn row(values: Vec<Value>) -> Row {
+    Row {
+        values,
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl GraphStorageService for MockStorage {
+    async fn scanVertex(&self, req: ScanVertexRequest) -> Result<ScanResponse, ScanVertexExn> {
+        Ok(
+            self.scan(req.space_id, &req.parts, req.limit, false, |space| {
+                vertex_rows(space, &req.return_columns)
+            }),
+        )
+    }
+
+    async fn scanEdge(&self, req: ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeExn> {
+        Ok(
+            self.scan(req.space_id, &req.parts, req.limit, true, |space| {
+                edge_rows(space, &req.return_columns)
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_storage::v3::dependencies::common::types::PropertyType;
+
+    use crate::v3::StorageClient;
+
+    fn request(parts: Vec<(i32, Option<&str>)>, props: &[&str]) -> ScanVertexRequest {
+        ScanVertexRequest {
+            space_id: 1,
+            parts: parts
+                .into_iter()
+                .map(|(part_id, cursor)| {
+                    let cursor = ScanCursor {
+                        next_cursor: cursor.map(|x| x.as_bytes().to_vec()),
+                        ..Default::default()
+                    };
+                    (part_id, cursor)
+                })
+                .collect(),
+            return_columns: vec![VertexProp {
+                tag: 1,
+                props: props.iter().map(|x| x.as_bytes().to_vec()).collect(),
+                ..Default::default()
+            }],
+            limit: 100,
+            ..Default::default()
+        }
+    }
+
+    fn vids(res: &ScanResponse) -> Vec<Value> {
+        res.props
+            .iter()
+            .flat_map(|x| x.rows.iter())
+            .map(|x| x.values[0].clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn with_cursors_and_leaders() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster
+            .space("nba", 2, PropertyType::INT64)
+            .tag("nba", "player", &[("name", PropertyType::STRING)])
+            .leader("nba", 2, "10.0.0.2:9779")
+            .page_size(2);
+        for vid in 0..6 {
+            cluster.insert_vertex("nba", "player", Value::iVal(vid), vec![NULL]);
+        }
+        let client = StorageClient::new(cluster.storage("10.0.0.1:9779").transport());
+
+        let res = client
+            .scan_vertex(&request(vec![(1, None), (2, None)], &["_vid", "name"]))
+            .await?;
+        assert_eq!(
+            res.props.as_ref().map(|x| x.column_names.clone()),
+            Some(vec![
+                b"_vid".to_vec(),
+                b"player._vid".to_vec(),
+                b"player.name".to_vec()
+            ])
+        );
+        assert_eq!(vids(&res), vec![Value::iVal(0), Value::iVal(2)]);
+        assert_eq!(res.cursors[&1].next_cursor, Some(b"2".to_vec()));
+        assert_eq!(res.result.failed_parts.len(), 1);
+        assert_eq!(res.result.failed_parts[0].part_id, 2);
+        assert_eq!(res.result.failed_parts[0].code, ErrorCode::E_LEADER_CHANGED);
+        assert_eq!(
+            res.result.failed_parts[0]
+                .leader
+                .as_ref()
+                .map(|x| x.host.as_str()),
+            Some("10.0.0.2")
+        );
+
+        let res = client
+            .scan_vertex(&request(vec![(1, Some("2"))], &["name"]))
+            .await?;
+        assert_eq!(vids(&res), vec![Value::iVal(4)]);
+        assert_eq!(res.cursors[&1].next_cursor, None);
+
+        let res = client
+            .scan_vertex(&request(vec![(1, None)], &["age"]))
+            .await?;
+        assert_eq!(
+            res.result.failed_parts[0].code,
+            ErrorCode::E_TAG_PROP_NOT_FOUND
+        );
+
+        assert_eq!(
+            cluster
+                .scanned()
+                .iter()
+                .map(|x| (x.part_id, x.cursor.clone()))
+                .collect::<Vec<_>>(),
+            vec![(1, None), (2, None), (1, Some(b"2".to_vec())), (1, None)]
+        );
+
+        Ok(())
+    }
+}
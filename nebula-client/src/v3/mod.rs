@@ -1,14 +1,56 @@
+#[cfg(all(
+    feature = "metrics",
+    any(feature = "graph", feature = "meta", feature = "storage")
+))]
+pub mod metrics;
+pub mod trace;
+pub use trace::TraceConfig;
+
+pub mod connector;
+pub use connector::Connector;
+
+pub mod protocol;
+pub use protocol::ThriftProtocol;
+
+#[cfg(feature = "tls")]
+pub mod tls;
+
 #[cfg(feature = "graph")]
 pub mod graph;
 #[cfg(feature = "graph")]
-pub use graph::{GraphClient, GraphQuery, GraphSession, GraphTransportResponseHandler};
+pub use graph::{
+    GraphClient, GraphHandle, GraphQuery, GraphSession, GraphTransportResponseHandler,
+};
 
 #[cfg(feature = "meta")]
 pub mod meta;
 #[cfg(feature = "meta")]
-pub use self::meta::{MetaClient, MetaTransportResponseHandler};
+pub use self::meta::{MetaClient, MetaHandle, MetaTransportResponseHandler};
 
 #[cfg(feature = "storage")]
 pub mod storage;
+#[cfg(all(
+    feature = "storage",
+    any(feature = "impl_tokio", feature = "impl_async_io")
+))]
+pub use storage::{scan_edge, scan_vertex};
 #[cfg(feature = "storage")]
-pub use storage::{StorageClient, StorageTransportResponseHandler,scan_vertex,scan_edge};
+pub use storage::{
+    scan_edge_in, scan_edge_with, scan_vertex_in, scan_vertex_with, ScanClients, StorageClient,
+    StorageHandle, StorageTransportResponseHandler,
+};
+
+#[cfg(feature = "storage")]
+pub mod record;
+
+#[cfg(feature = "migrate")]
+pub mod migrate;
+
+#[cfg(all(
+    feature = "blocking",
+    any(feature = "graph", feature = "meta", feature = "storage")
+))]
+pub mod blocking;
+
+#[cfg(feature = "test-support")]
+pub mod mock;
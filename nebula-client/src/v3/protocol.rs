@@ -0,0 +1,79 @@
+//! The thrift protocols the clients can speak. Servers reply in the protocol of the request.
+
+#[cfg(any(feature = "graph", feature = "meta", feature = "storage"))]
+use std::io::Cursor;
+
+#[cfg(any(feature = "graph", feature = "meta", feature = "storage"))]
+use fbthrift::{
+    binary_protocol::BinaryProtocolDeserializer, compact_protocol::CompactProtocolDeserializer,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ThriftProtocol {
+    #[default]
+    Binary,
+    /// Smaller payloads, mostly for large results and scans.
+    Compact,
+}
+
+impl ThriftProtocol {
+    /// From the first bytes of a message, `None` when too short or neither protocol.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x80, 0x01, ..] => Some(Self::Binary),
+            [0x82, x, ..] if x & 0x1f == 0x02 => Some(Self::Compact),
+            _ => None,
+        }
+    }
+}
+
+/// The length of the message `bytes` start with, once `binary` or `compact` reads it whole.
+#[cfg(any(feature = "graph", feature = "meta", feature = "storage"))]
+pub(crate) fn frame_len<'a>(
+    bytes: &'a [u8],
+    binary: impl FnOnce(&mut BinaryProtocolDeserializer<Cursor<&'a [u8]>>) -> Option<()>,
+    compact: impl FnOnce(&mut CompactProtocolDeserializer<Cursor<&'a [u8]>>) -> Option<()>,
+) -> Option<usize> {
+    match ThriftProtocol::detect(bytes)? {
+        ThriftProtocol::Binary => {
+            let mut des = BinaryProtocolDeserializer::new(Cursor::new(bytes));
+            binary(&mut des)?;
+            Some(des.into_inner().position() as usize)
+        }
+        ThriftProtocol::Compact => {
+            let mut des = CompactProtocolDeserializer::new(Cursor::new(bytes));
+            compact(&mut des)?;
+            Some(des.into_inner().position() as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbthrift::{BinaryProtocol, CompactProtocol, MessageType, ProtocolWriter as _};
+
+    #[test]
+    fn test_detect() {
+        let binary = ::fbthrift::serialize!(BinaryProtocol, |p| {
+            p.write_message_begin("execute", MessageType::Reply, 1);
+            p.write_message_end();
+        });
+        let compact = ::fbthrift::serialize!(CompactProtocol, |p| {
+            p.write_message_begin("execute", MessageType::Reply, 1);
+            p.write_message_end();
+        });
+
+        assert_eq!(
+            ThriftProtocol::detect(&binary),
+            Some(ThriftProtocol::Binary)
+        );
+        assert_eq!(
+            ThriftProtocol::detect(&compact),
+            Some(ThriftProtocol::Compact)
+        );
+        assert_eq!(ThriftProtocol::detect(&compact[..1]), None);
+        assert_eq!(ThriftProtocol::detect(b"{}"), None);
+    }
+}
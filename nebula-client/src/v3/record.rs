@@ -0,0 +1,612 @@
+//! Typed tag and edge records.
+//!
+//! With the `derive` feature, `#[derive(NebulaTag)]` and `#[derive(NebulaEdge)]`
+//! implement the traits below from the struct definition:
+//!
+//! ```ignore
+//! #[derive(Deserialize, NebulaTag)]
+//! #[nebula(tag = "player")]
+//! struct Player {
+//!     #[nebula(vid)]
+//!     id: String,
+//!     name: String,
+//!     age: i64,
+//! }
+//!
+//! #[derive(Deserialize, NebulaEdge)]
+//! #[nebula(edge = "serve")]
+//! struct Serve {
+//!     #[nebula(src)]
+//!     player: String,
+//!     #[nebula(dst)]
+//!     team: String,
+//!     #[nebula(rank)]
+//!     rank: i64,
+//!     #[nebula(rename = "start_year")]
+//!     start: i64,
+//! }
+//! ```
+//!
+//! The tag / edge name defaults to the snake_case type name. Fields are
+//! properties unless marked `vid`, `src`, `dst`, `rank` or `skip`; `rename`
+//! sets the property name. Rows are matched to fields by the Rust field name,
+//! so do not combine this with `#[serde(rename)]`. Names are quoted with
+//! backticks in statements, so they cannot contain one.
+
+use std::collections::BTreeMap;
+
+use deserialize_nebula_fbthrift::v3::{
+    de::{data::DataDeserializeError, deserialize_scan_struct_response_with_columns},
+    literal::Literal,
+};
+use nebula_fbthrift_storage::v3::{
+    dependencies::common::types::Value,
+    types::{EdgeProp, ScanResponse, VertexProp},
+};
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "derive")]
+pub use nebula_derive::{NebulaEdge, NebulaTag};
+
+/// Quotes a tag, edge, property or alias name: `` `name` ``.
+pub fn quote_ident(name: &str) -> String {
+    format!("`{name}`")
+}
+
+/// Writes a Rust value as an nGQL literal.
+pub trait ToNgql {
+    fn to_ngql(&self) -> String;
+}
+
+impl ToNgql for str {
+    fn to_ngql(&self) -> String {
+        let mut s = String::with_capacity(self.len() + 2);
+        s.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => s.push_str("\\\""),
+                '\\' => s.push_str("\\\\"),
+                '\n' => s.push_str("\\n"),
+                '\r' => s.push_str("\\r"),
+                '\t' => s.push_str("\\t"),
+                c => s.push(c),
+            }
+        }
+        s.push('"');
+        s
+    }
+}
+
+impl ToNgql for String {
+    fn to_ngql(&self) -> String {
+        self.as_str().to_ngql()
+    }
+}
+
+macro_rules! impl_to_ngql_display {
+    ($($t:ty),*) => {
+        $(
+            impl ToNgql for $t {
+                fn to_ngql(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_to_ngql_display!(bool, i8, i16, i32, i64, u8, u16, u32);
+
+impl ToNgql for f32 {
+    fn to_ngql(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl ToNgql for f64 {
+    fn to_ngql(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl ToNgql for Value {
+    fn to_ngql(&self) -> String {
+        Literal(self).to_string()
+    }
+}
+
+impl<T: ToNgql + ?Sized> ToNgql for &T {
+    fn to_ngql(&self) -> String {
+        (**self).to_ngql()
+    }
+}
+
+impl<T: ToNgql> ToNgql for Option<T> {
+    fn to_ngql(&self) -> String {
+        match self {
+            Some(v) => v.to_ngql(),
+            None => "NULL".to_owned(),
+        }
+    }
+}
+
+impl<T: ToNgql> ToNgql for Vec<T> {
+    fn to_ngql(&self) -> String {
+        let items: Vec<String> = self.iter().map(ToNgql::to_ngql).collect();
+        format!("[{}]", items.join(", "))
+    }
+}
+
+impl<V: ToNgql> ToNgql for BTreeMap<String, V> {
+    fn to_ngql(&self) -> String {
+        let items: Vec<String> = self
+            .iter()
+            .map(|(k, v)| format!("{k}: {}", v.to_ngql()))
+            .collect();
+        format!("{{{}}}", items.join(", "))
+    }
+}
+
+//
+//
+//
+pub trait NebulaTag {
+    const TAG: &'static str;
+    /// Field holding the vertex id.
+    const VID_FIELD: &'static str;
+    /// Property names, in declaration order.
+    const PROPS: &'static [&'static str];
+    /// Field names matching [`PROPS`](Self::PROPS).
+    const FIELDS: &'static [&'static str];
+
+    /// The vertex id as an nGQL literal.
+    fn vid(&self) -> String;
+    /// Property values as nGQL literals, matching [`PROPS`](Self::PROPS).
+    fn prop_values(&self) -> Vec<String>;
+
+    /// ``INSERT VERTEX `player`(`name`, `age`) VALUES "p1":("Tim", 42)``
+    fn insert_statement(&self) -> String {
+        Self::insert_many_statement(core::iter::once(self))
+    }
+
+    fn insert_many_statement<'a>(items: impl IntoIterator<Item = &'a Self>) -> String
+    where
+        Self: 'a,
+    {
+        let values: Vec<String> = items
+            .into_iter()
+            .map(|x| format!("{}:({})", x.vid(), x.prop_values().join(", ")))
+            .collect();
+
+        format!(
+            "INSERT VERTEX {}({}) VALUES {}",
+            quote_ident(Self::TAG),
+            idents(Self::PROPS),
+            values.join(", ")
+        )
+    }
+
+    /// ``UPSERT VERTEX ON `player` "p1" SET `name` = "Tim", `age` = 42``
+    ///
+    /// Without properties there is nothing to set, this is `INSERT VERTEX IF NOT EXISTS` instead.
+    fn upsert_statement(&self) -> String {
+        if Self::PROPS.is_empty() {
+            return format!(
+                "INSERT VERTEX IF NOT EXISTS {}() VALUES {}:()",
+                quote_ident(Self::TAG),
+                self.vid()
+            );
+        }
+
+        format!(
+            "UPSERT VERTEX ON {} {} SET {}",
+            quote_ident(Self::TAG),
+            self.vid(),
+            assignments(Self::PROPS, self.prop_values())
+        )
+    }
+
+    /// ``FETCH PROP ON `player` "p1" YIELD id(vertex) AS `id`, properties(vertex).`name` AS `name`, ...``
+    ///
+    /// Columns are aliased to the field names, so the output deserializes with `query_as`.
+    fn fetch_statement(vid: &(impl ToNgql + ?Sized)) -> String {
+        let mut columns = vec![format!("id(vertex) AS {}", quote_ident(Self::VID_FIELD))];
+        columns.extend(Self::PROPS.iter().zip(Self::FIELDS).map(|(prop, field)| {
+            format!(
+                "properties(vertex).{} AS {}",
+                quote_ident(prop),
+                quote_ident(field)
+            )
+        }));
+
+        format!(
+            "FETCH PROP ON {} {} YIELD {}",
+            quote_ident(Self::TAG),
+            vid.to_ngql(),
+            columns.join(", ")
+        )
+    }
+
+    /// Return columns for a `ScanVertexRequest`, `tag_id` comes from `MetaClient::list_tags`.
+    fn vertex_prop(tag_id: i32) -> VertexProp {
+        let mut props = vec![b"_vid".to_vec()];
+        props.extend(Self::PROPS.iter().map(|x| x.as_bytes().to_vec()));
+
+        VertexProp {
+            tag: tag_id,
+            props,
+            ..Default::default()
+        }
+    }
+
+    /// `(column name, field name)` pairs of a scan response, e.g. `("player.name", "name")`.
+    fn scan_columns() -> Vec<(String, &'static str)> {
+        let mut columns = vec![(format!("{}._vid", Self::TAG), Self::VID_FIELD)];
+        columns.extend(
+            Self::PROPS
+                .iter()
+                .zip(Self::FIELDS)
+                .map(|(prop, field)| (format!("{}.{prop}", Self::TAG), *field)),
+        );
+        columns
+    }
+
+    fn from_scan_response(res: &ScanResponse) -> Result<Vec<Self>, DataDeserializeError>
+    where
+        Self: DeserializeOwned + Sized,
+    {
+        deserialize_scan_struct_response_with_columns(res, &Self::scan_columns())
+    }
+}
+
+//
+//
+//
+pub trait NebulaEdge {
+    const EDGE: &'static str;
+    const SRC_FIELD: &'static str;
+    const DST_FIELD: &'static str;
+    /// Without a rank field, the rank is always 0.
+    const RANK_FIELD: Option<&'static str>;
+    /// Property names, in declaration order.
+    const PROPS: &'static [&'static str];
+    /// Field names matching [`PROPS`](Self::PROPS).
+    const FIELDS: &'static [&'static str];
+
+    /// The source vertex id as an nGQL literal.
+    fn src(&self) -> String;
+    /// The destination vertex id as an nGQL literal.
+    fn dst(&self) -> String;
+    fn rank(&self) -> i64;
+    /// Property values as nGQL literals, matching [`PROPS`](Self::PROPS).
+    fn prop_values(&self) -> Vec<String>;
+
+    /// `"p1"->"t1"@0`
+    fn edge_key(&self) -> String {
+        format!("{}->{}@{}", self.src(), self.dst(), self.rank())
+    }
+
+    /// ``INSERT EDGE `serve`(`start_year`) VALUES "p1"->"t1"@0:(1997)``
+    fn insert_statement(&self) -> String {
+        Self::insert_many_statement(core::iter::once(self))
+    }
+
+    fn insert_many_statement<'a>(items: impl IntoIterator<Item = &'a Self>) -> String
+    where
+        Self: 'a,
+    {
+        let values: Vec<String> = items
+            .into_iter()
+            .map(|x| format!("{}:({})", x.edge_key(), x.prop_values().join(", ")))
+            .collect();
+
+        format!(
+            "INSERT EDGE {}({}) VALUES {}",
+            quote_ident(Self::EDGE),
+            idents(Self::PROPS),
+            values.join(", ")
+        )
+    }
+
+    /// ``UPSERT EDGE ON `serve` "p1"->"t1"@0 SET `start_year` = 1997``
+    ///
+    /// Without properties there is nothing to set, this is `INSERT EDGE IF NOT EXISTS` instead.
+    fn upsert_statement(&self) -> String {
+        if Self::PROPS.is_empty() {
+            return format!(
+                "INSERT EDGE IF NOT EXISTS {}() VALUES {}:()",
+                quote_ident(Self::EDGE),
+                self.edge_key()
+            );
+        }
+
+        format!(
+            "UPSERT EDGE ON {} {} SET {}",
+            quote_ident(Self::EDGE),
+            self.edge_key(),
+            assignments(Self::PROPS, self.prop_values())
+        )
+    }
+
+    /// ``FETCH PROP ON `serve` "p1"->"t1"@0 YIELD src(edge) AS `player`, ...``
+    ///
+    /// Columns are aliased to the field names, so the output deserializes with `query_as`.
+    fn fetch_statement(
+        src: &(impl ToNgql + ?Sized),
+        dst: &(impl ToNgql + ?Sized),
+        rank: i64,
+    ) -> String {
+        let mut columns = vec![
+            format!("src(edge) AS {}", quote_ident(Self::SRC_FIELD)),
+            format!("dst(edge) AS {}", quote_ident(Self::DST_FIELD)),
+        ];
+        if let Some(rank_field) = Self::RANK_FIELD {
+            columns.push(format!("rank(edge) AS {}", quote_ident(rank_field)));
+        }
+        columns.extend(Self::PROPS.iter().zip(Self::FIELDS).map(|(prop, field)| {
+            format!(
+                "properties(edge).{} AS {}",
+                quote_ident(prop),
+                quote_ident(field)
+            )
+        }));
+
+        format!(
+            "FETCH PROP ON {} {}->{}@{} YIELD {}",
+            quote_ident(Self::EDGE),
+            src.to_ngql(),
+            dst.to_ngql(),
+            rank,
+            columns.join(", ")
+        )
+    }
+
+    /// Return columns for a `ScanEdgeRequest`, `edge_type` comes from `MetaClient::list_edges`.
+    fn edge_prop(edge_type: i32) -> EdgeProp {
+        let mut props = vec![
+            b"_src".to_vec(),
+            b"_type".to_vec(),
+            b"_rank".to_vec(),
+            b"_dst".to_vec(),
+        ];
+        props.extend(Self::PROPS.iter().map(|x| x.as_bytes().to_vec()));
+
+        EdgeProp {
+            r#type: edge_type,
+            props,
+            ..Default::default()
+        }
+    }
+
+    /// `(column name, field name)` pairs of a scan response, e.g. `("serve._src", "player")`.
+    fn scan_columns() -> Vec<(String, &'static str)> {
+        let mut columns = vec![
+            (format!("{}._src", Self::EDGE), Self::SRC_FIELD),
+            (format!("{}._dst", Self::EDGE), Self::DST_FIELD),
+        ];
+        if let Some(rank_field) = Self::RANK_FIELD {
+            columns.push((format!("{}._rank", Self::EDGE), rank_field));
+        }
+        columns.extend(
+            Self::PROPS
+                .iter()
+                .zip(Self::FIELDS)
+                .map(|(prop, field)| (format!("{}.{prop}", Self::EDGE), *field)),
+        );
+        columns
+    }
+
+    fn from_scan_response(res: &ScanResponse) -> Result<Vec<Self>, DataDeserializeError>
+    where
+        Self: DeserializeOwned + Sized,
+    {
+        deserialize_scan_struct_response_with_columns(res, &Self::scan_columns())
+    }
+}
+
+fn idents(names: &[&str]) -> String {
+    let items: Vec<String> = names.iter().map(|x| quote_ident(x)).collect();
+    items.join(", ")
+}
+
+fn assignments(props: &[&str], values: Vec<String>) -> String {
+    let items: Vec<String> = props
+        .iter()
+        .zip(values)
+        .map(|(prop, value)| format!("{} = {value}", quote_ident(prop)))
+        .collect();
+    items.join(", ")
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_storage::v3::dependencies::common::types::{DataSet, Row};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, NebulaTag, Debug, PartialEq)]
+    #[nebula(tag = "player")]
+    struct Player {
+        #[nebula(vid)]
+        id: String,
+        name: String,
+        age: i64,
+        #[serde(default)]
+        #[nebula(skip)]
+        note: Option<String>,
+    }
+
+    #[derive(Deserialize, NebulaEdge, Debug, PartialEq)]
+    struct Serve {
+        #[nebula(src)]
+        player: String,
+        #[nebula(dst)]
+        team: String,
+        #[nebula(rank)]
+        rank: i64,
+        #[nebula(rename = "start_year")]
+        start: i64,
+    }
+
+    #[derive(NebulaTag)]
+    struct Person {
+        #[nebula(vid)]
+        id: String,
+    }
+
+    #[derive(NebulaEdge)]
+    struct Like {
+        #[nebula(src)]
+        from: String,
+        #[nebula(dst)]
+        to: String,
+    }
+
+    #[test]
+    fn with_tag() -> Result<(), Box<dyn std::error::Error>> {
+        let player = Player {
+            id: "p1".to_owned(),
+            name: "Tim \"TD\"".to_owned(),
+            age: 42,
+            note: None,
+        };
+
+        assert_eq!(
+            player.insert_statement(),
+            r#"INSERT VERTEX `player`(`name`, `age`) VALUES "p1":("Tim \"TD\"", 42)"#
+        );
+        assert_eq!(
+            player.upsert_statement(),
+            r#"UPSERT VERTEX ON `player` "p1" SET `name` = "Tim \"TD\"", `age` = 42"#
+        );
+        assert_eq!(
+            Player::fetch_statement("p1"),
+            r#"FETCH PROP ON `player` "p1" YIELD id(vertex) AS `id`, properties(vertex).`name` AS `name`, properties(vertex).`age` AS `age`"#
+        );
+        assert_eq!(
+            Player::vertex_prop(2).props,
+            vec![b"_vid".to_vec(), b"name".to_vec(), b"age".to_vec()]
+        );
+
+        let res = ScanResponse {
+            props: Some(DataSet {
+                column_names: vec![
+                    b"_vid".to_vec(),
+                    b"player._vid".to_vec(),
+                    b"player.name".to_vec(),
+                    b"player.age".to_vec(),
+                ],
+                rows: vec![Row {
+                    values: vec![
+                        Value::sVal(b"p1".to_vec()),
+                        Value::sVal(b"p1".to_vec()),
+                        Value::sVal(b"Tim".to_vec()),
+                        Value::iVal(42),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Player::from_scan_response(&res)?,
+            vec![Player {
+                id: "p1".to_owned(),
+                name: "Tim".to_owned(),
+                age: 42,
+                note: None,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn with_edge() -> Result<(), Box<dyn std::error::Error>> {
+        let serve = Serve {
+            player: "p1".to_owned(),
+            team: "t1".to_owned(),
+            rank: 0,
+            start: 1997,
+        };
+
+        assert_eq!(
+            serve.insert_statement(),
+            r#"INSERT EDGE `serve`(`start_year`) VALUES "p1"->"t1"@0:(1997)"#
+        );
+        assert_eq!(
+            serve.upsert_statement(),
+            r#"UPSERT EDGE ON `serve` "p1"->"t1"@0 SET `start_year` = 1997"#
+        );
+        assert_eq!(
+            Serve::fetch_statement("p1", "t1", 0),
+            r#"FETCH PROP ON `serve` "p1"->"t1"@0 YIELD src(edge) AS `player`, dst(edge) AS `team`, rank(edge) AS `rank`, properties(edge).`start_year` AS `start`"#
+        );
+        assert_eq!(
+            Serve::scan_columns(),
+            vec![
+                ("serve._src".to_owned(), "player"),
+                ("serve._dst".to_owned(), "team"),
+                ("serve._rank".to_owned(), "rank"),
+                ("serve.start_year".to_owned(), "start"),
+            ]
+        );
+
+        let res = ScanResponse {
+            props: Some(DataSet {
+                column_names: vec![
+                    b"serve._src".to_vec(),
+                    b"serve._type".to_vec(),
+                    b"serve._rank".to_vec(),
+                    b"serve._dst".to_vec(),
+                    b"serve.start_year".to_vec(),
+                ],
+                rows: vec![Row {
+                    values: vec![
+                        Value::sVal(b"p1".to_vec()),
+                        Value::iVal(3),
+                        Value::iVal(1),
+                        Value::sVal(b"t1".to_vec()),
+                        Value::iVal(1997),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Serve::from_scan_response(&res)?,
+            vec![Serve {
+                player: "p1".to_owned(),
+                team: "t1".to_owned(),
+                rank: 1,
+                start: 1997,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn without_props() {
+        let person = Person {
+            id: "p1".to_owned(),
+        };
+        let like = Like {
+            from: "p1".to_owned(),
+            to: "p2".to_owned(),
+        };
+
+        assert_eq!(
+            person.upsert_statement(),
+            r#"INSERT VERTEX IF NOT EXISTS `person`() VALUES "p1":()"#
+        );
+        assert_eq!(
+            like.upsert_statement(),
+            r#"INSERT EDGE IF NOT EXISTS `like`() VALUES "p1"->"p2"@0:()"#
+        );
+    }
+}
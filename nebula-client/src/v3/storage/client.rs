@@ -1,13 +1,26 @@
+use super::scan_struct::{StorageQuery, StorageQueryError, StorageQueryOutput};
+use super::StorageTransportResponseHandler;
+#[cfg(feature = "metrics")]
+use crate::v3::metrics::{self, TRANSPORT_ERROR};
+use crate::v3::{Connector, ThriftProtocol};
+use async_sleep::Sleepble;
 use async_trait::async_trait;
 use bytes::Bytes;
-use fbthrift::{BinaryProtocol, Transport};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use fbthrift::{BinaryProtocol, CompactProtocol, NonthrowingFunctionError, Transport};
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use futures_io::{AsyncRead, AsyncWrite};
 use nebula_fbthrift_storage::v3::{
     client::{GraphStorageService, GraphStorageServiceImpl},
     errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
     types::{ScanEdgeRequest, ScanResponse, ScanVertexRequest},
 };
 use serde::de::DeserializeOwned;
-use super::scan_struct::{StorageQuery, StorageQueryError, StorageQueryOutput};
+use std::io::Error as IoError;
+use tracing::{field::Empty, Instrument as _, Span};
 //
 //
 //
@@ -18,7 +31,10 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    service: GraphStorageServiceImpl<BinaryProtocol, T>,
+    service: Box<dyn GraphStorageService + Sync>,
+    addr: Option<String>,
+    close_required: AtomicBool,
+    _transport: PhantomData<T>,
 }
 
 impl<T> StorageConnection<T>
@@ -28,9 +44,20 @@ where
     ::fbthrift::ProtocolEncoded<BinaryProtocol>:
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
-    fn new(transport: T) -> Self {
+    fn new(transport: T, protocol: ThriftProtocol) -> Self {
+        let service: Box<dyn GraphStorageService + Sync> = match protocol {
+            ThriftProtocol::Binary => {
+                Box::new(GraphStorageServiceImpl::<BinaryProtocol, _>::new(transport))
+            }
+            ThriftProtocol::Compact => Box::new(
+                GraphStorageServiceImpl::<CompactProtocol, _>::new(transport),
+            ),
+        };
         Self {
-            service: GraphStorageServiceImpl::<BinaryProtocol, _>::new(transport),
+            service,
+            addr: None,
+            close_required: AtomicBool::new(false),
+            _transport: PhantomData,
         }
     }
 }
@@ -48,6 +75,27 @@ where
     connection: StorageConnection<T>,
 }
 
+impl<S, SLEEP> StorageClient<AsyncTransport<S, SLEEP, StorageTransportResponseHandler>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    SLEEP: Sleepble + Send + Sync + 'static,
+{
+    /// Connects to storaged at `addr` (`host:port`) through `connector`, such as a TLS one.
+    pub async fn connect<C>(connector: &C, addr: &str) -> Result<Self, IoError>
+    where
+        C: Connector<Stream = S, Sleep = SLEEP>,
+    {
+        let transport = connector
+            .transport(
+                addr,
+                AsyncTransportConfiguration::new(StorageTransportResponseHandler),
+            )
+            .await?;
+
+        Ok(Self::new(transport).with_addr(addr))
+    }
+}
+
 impl<T> StorageClient<T>
 where
     T: Transport,
@@ -56,8 +104,60 @@ where
         ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
 {
     pub fn new(transport: T) -> Self {
+        Self::new_with_protocol(transport, ThriftProtocol::Binary)
+    }
+
+    /// Compact shrinks large responses; servers reply in whichever protocol they are called with.
+    pub fn new_with_protocol(transport: T, protocol: ThriftProtocol) -> Self {
         Self {
-            connection: StorageConnection::new(transport),
+            connection: StorageConnection::new(transport, protocol),
+        }
+    }
+
+    /// Records the storaged address on spans, the transport itself does not know it.
+    pub fn with_addr(mut self, addr: impl Into<String>) -> Self {
+        self.connection.addr = Some(addr.into());
+        self
+    }
+
+    /// Set once a scan failed in the transport; the connection should be dropped.
+    pub fn is_close_required(&self) -> bool {
+        self.connection.close_required.load(Ordering::Relaxed)
+    }
+
+    fn span(&self, method: &'static str, space_id: i32, parts: Vec<i32>) -> Span {
+        tracing::info_span!(
+            "nebula.storage",
+            method,
+            host = self.connection.addr.as_deref().unwrap_or_default(),
+            space_id,
+            parts = ?parts,
+            latency_us = Empty,
+            failed_parts = Empty,
+        )
+    }
+
+    #[cfg(feature = "metrics")]
+    fn finish(request: metrics::Request, res: &ScanResponse) {
+        request.finish(
+            Some(res.result.latency_in_us),
+            res.result
+                .failed_parts
+                .first()
+                .map(|part| part.code.to_string()),
+        );
+    }
+
+    fn record(span: &Span, res: &ScanResponse) {
+        span.record("latency_us", res.result.latency_in_us);
+        span.record("failed_parts", res.result.failed_parts.len());
+        for part in res.result.failed_parts.iter() {
+            tracing::debug!(
+                parent: span,
+                part_id = part.part_id,
+                error_code = %part.code,
+                "scan failed on part"
+            );
         }
     }
 
@@ -66,16 +166,66 @@ where
         &self,
         req: &ScanVertexRequest,
     ) -> Result<ScanResponse, ScanVertexError> {
-        let res = self.connection.service.scanVertex(req).await?;
+        let span = self.span(
+            "scan_vertex",
+            req.space_id,
+            req.parts.keys().copied().collect(),
+        );
+        #[cfg(feature = "metrics")]
+        let request = metrics::Request::new("storage", "scan_vertex");
+
+        let res = self
+            .connection
+            .service
+            .scanVertex(req)
+            .instrument(span.clone())
+            .await
+            .inspect_err(|err| {
+                tracing::warn!(parent: &span, error = %err, "scan_vertex failed");
+                if let NonthrowingFunctionError::ThriftError(_) = err {
+                    self.connection
+                        .close_required
+                        .store(true, Ordering::Relaxed);
+                }
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
+            })?;
+
+        #[cfg(feature = "metrics")]
+        Self::finish(request, &res);
+        Self::record(&span, &res);
         Ok(res)
     }
 
-    pub async fn scan_edge(
-        &self,
-        req: &ScanEdgeRequest,
-    ) -> Result<ScanResponse, ScanEdgeError> {
-        let res = self.connection.service.scanEdge(req).await?;
+    pub async fn scan_edge(&self, req: &ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeError> {
+        let span = self.span(
+            "scan_edge",
+            req.space_id,
+            req.parts.keys().copied().collect(),
+        );
+        #[cfg(feature = "metrics")]
+        let request = metrics::Request::new("storage", "scan_edge");
+
+        let res = self
+            .connection
+            .service
+            .scanEdge(req)
+            .instrument(span.clone())
+            .await
+            .inspect_err(|err| {
+                tracing::warn!(parent: &span, error = %err, "scan_edge failed");
+                if let NonthrowingFunctionError::ThriftError(_) = err {
+                    self.connection
+                        .close_required
+                        .store(true, Ordering::Relaxed);
+                }
+                #[cfg(feature = "metrics")]
+                request.finish(None, Some(TRANSPORT_ERROR.to_owned()));
+            })?;
 
+        #[cfg(feature = "metrics")]
+        Self::finish(request, &res);
+        Self::record(&span, &res);
         Ok(res)
     }
 }
@@ -97,7 +247,6 @@ where
             .await
             .map_err(StorageQueryError::ScanVertexError)?;
 
-
         StorageQueryOutput::new(res)
     }
 
@@ -110,7 +259,6 @@ where
             .await
             .map_err(StorageQueryError::ScanEdgeError)?;
 
-
         StorageQueryOutput::new(res)
     }
 }
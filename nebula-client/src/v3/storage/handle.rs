@@ -0,0 +1,152 @@
+//! Storage clients without transport generics, to keep in application state or replace with a fake.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use fbthrift::{BinaryProtocol, Transport};
+use nebula_fbthrift_storage::v3::{
+    errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
+    types::{ScanEdgeRequest, ScanResponse, ScanVertexRequest},
+};
+use serde::de::DeserializeOwned;
+
+use super::{StorageClient, StorageQuery, StorageQueryError, StorageQueryOutput};
+
+/// The scans of [`StorageClient`], object-safe; [`StorageQuery`] is implemented for `dyn StorageHandle`.
+#[async_trait]
+pub trait StorageHandle: Send + Sync {
+    async fn scan_vertex(&self, req: &ScanVertexRequest) -> Result<ScanResponse, ScanVertexError>;
+
+    async fn scan_edge(&self, req: &ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeError>;
+
+    fn is_close_required(&self) -> bool;
+}
+
+pub type BoxStorageClient = Box<dyn StorageHandle>;
+
+#[async_trait]
+impl<T> StorageHandle for StorageClient<T>
+where
+    T: Transport + Send + Sync,
+    Bytes: ::fbthrift::Framing<DecBuf = ::fbthrift::FramingDecoded<T>>,
+    ::fbthrift::ProtocolEncoded<BinaryProtocol>:
+        ::fbthrift::BufMutExt<Final = ::fbthrift::FramingEncodedFinal<T>>,
+{
+    async fn scan_vertex(&self, req: &ScanVertexRequest) -> Result<ScanResponse, ScanVertexError> {
+        StorageClient::scan_vertex(self, req).await
+    }
+
+    async fn scan_edge(&self, req: &ScanEdgeRequest) -> Result<ScanResponse, ScanEdgeError> {
+        StorageClient::scan_edge(self, req).await
+    }
+
+    fn is_close_required(&self) -> bool {
+        StorageClient::is_close_required(self)
+    }
+}
+
+#[async_trait]
+impl<'a> StorageQuery for dyn StorageHandle + 'a {
+    async fn query_vertex<D: DeserializeOwned>(
+        &mut self,
+        req: &ScanVertexRequest,
+    ) -> Result<StorageQueryOutput<D>, StorageQueryError> {
+        let res = self
+            .scan_vertex(req)
+            .await
+            .map_err(StorageQueryError::ScanVertexError)?;
+
+        StorageQueryOutput::new(res)
+    }
+
+    async fn query_edge<D: DeserializeOwned>(
+        &mut self,
+        req: &ScanEdgeRequest,
+    ) -> Result<StorageQueryOutput<D>, StorageQueryError> {
+        let res = self
+            .scan_edge(req)
+            .await
+            .map_err(StorageQueryError::ScanEdgeError)?;
+
+        StorageQueryOutput::new(res)
+    }
+}
+
+//
+#[cfg(feature = "impl_tokio")]
+pub use self::impl_tokio::{TokioStorageClient, TokioStorageTransport};
+
+#[cfg(feature = "impl_tokio")]
+mod impl_tokio {
+    use fbthrift_transport::{
+        impl_tokio::{TokioSleep, TokioTcpStream},
+        AsyncTransport,
+    };
+
+    use super::super::{StorageClient, StorageTransportResponseHandler};
+
+    pub type TokioStorageTransport =
+        AsyncTransport<TokioTcpStream, TokioSleep, StorageTransportResponseHandler>;
+    pub type TokioStorageClient = StorageClient<TokioStorageTransport>;
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use nebula_fbthrift_storage::v3::{
+        dependencies::common::types::{PropertyType, Value},
+        types::VertexProp,
+    };
+
+    use crate::v3::{
+        meta::{MetaClient, MetaHandle},
+        mock::MockCluster,
+    };
+
+    #[tokio::test]
+    async fn with_mock() -> Result<(), Box<dyn std::error::Error>> {
+        let cluster = MockCluster::new();
+        cluster
+            .space("nba", 1, PropertyType::FIXED_STRING)
+            .tag(
+                "nba",
+                "player",
+                &[("name", PropertyType::STRING), ("age", PropertyType::INT64)],
+            )
+            .insert_vertex(
+                "nba",
+                "player",
+                Value::sVal(b"p1".to_vec()),
+                vec![Value::sVal(b"Tim".to_vec()), Value::iVal(42)],
+            );
+
+        let meta: Arc<dyn MetaHandle> = Arc::new(MetaClient::new(cluster.meta().transport()));
+        let space_id = meta.get_space(b"nba".to_vec()).await?.item.space_id;
+        assert_eq!(meta.list_tags(space_id).await?.tags.len(), 1);
+
+        let mut storage: BoxStorageClient = Box::new(StorageClient::new(
+            cluster.storage("127.0.0.1:9779").transport(),
+        ));
+        let req = ScanVertexRequest {
+            space_id,
+            parts: [(1, Default::default())].into(),
+            return_columns: vec![VertexProp {
+                tag: 1,
+                props: vec![b"_vid".to_vec(), b"name".to_vec(), b"age".to_vec()],
+                ..Default::default()
+            }],
+            limit: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            storage.scan_vertex(&req).await?.result.failed_parts.len(),
+            0
+        );
+        let output = storage.show_vertexs(&req).await?;
+        assert_eq!(output.data_set[0].name, "Tim");
+
+        Ok(())
+    }
+}
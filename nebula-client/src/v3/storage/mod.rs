@@ -1,11 +1,18 @@
 pub mod client;
 pub use client::StorageClient;
 
+pub mod handle;
+pub use handle::{BoxStorageClient, StorageHandle};
+#[cfg(feature = "impl_tokio")]
+pub use handle::{TokioStorageClient, TokioStorageTransport};
+
 pub mod transport_response_handler;
 pub use transport_response_handler::StorageTransportResponseHandler;
 
 pub mod scan;
-pub use scan::{scan_vertex,scan_edge};
+#[cfg(any(feature = "impl_tokio", feature = "impl_async_io"))]
+pub use scan::{scan_edge, scan_vertex};
+pub use scan::{scan_edge_in, scan_edge_with, scan_vertex_in, scan_vertex_with, ScanClients};
 
 pub mod scan_struct;
-pub use scan_struct::{StorageQuery, StorageQueryError, StorageQueryOutput};
\ No newline at end of file
+pub use scan_struct::{StorageQuery, StorageQueryError, StorageQueryOutput};
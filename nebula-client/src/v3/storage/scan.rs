@@ -1,250 +1,322 @@
-// cargo run -p nebula-client --bin nebula_demo 192.168.10.21 9559 9779     
+// cargo run -p nebula-client --bin nebula_demo 192.168.10.21 9559 9779
 use std::collections::BTreeMap;
 
-use crate::v3::meta::{MetaClient, MetaTransportResponseHandler};
-use super::{StorageClient,StorageTransportResponseHandler};
-use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
-use nebula_fbthrift_storage::v3::{
-    errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
-    types::{ScanCursor,ScanEdgeRequest,ScanVertexRequest,VertexProp,EdgeProp},
+#[cfg(feature = "show_struct_result")]
+use super::scan_struct::{Edge, StorageQueryOutput, Vertex};
+use super::{StorageClient, StorageHandle, StorageTransportResponseHandler};
+#[cfg(any(feature = "impl_tokio", feature = "impl_async_io"))]
+use crate::v3::connector::DefaultConnector;
+use crate::v3::{
+    meta::{MetaClient, MetaHandle, MetaTransportResponseHandler},
+    Connector,
 };
-use deserialize_nebula_fbthrift::v3::de::{deserialize_scan_response,datadeal::ProcessError};
+use async_trait::async_trait;
+use deserialize_nebula_fbthrift::v3::de::{datadeal::ProcessError, deserialize_scan_response};
+use fbthrift_transport::AsyncTransportConfiguration;
 use nebula_fbthrift_meta::v3::{
-    errors::meta_service::{GetSpaceError, ListPartsError, ListTagsError,ListEdgesError,GetPartsAllocError},
-    types::{TagItem,EdgeItem,ListPartsResp},
+    errors::meta_service::{
+        GetPartsAllocError, GetSpaceError, ListEdgesError, ListPartsError, ListTagsError,
+    },
+    types::{EdgeItem, ListPartsResp, TagItem},
+};
+use nebula_fbthrift_storage::v3::{
+    errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
+    types::{EdgeProp, ScanCursor, ScanEdgeRequest, ScanVertexRequest, VertexProp},
 };
 use std::error::Error;
 use std::fmt;
+use std::io::Error as IoError;
 
 const DEFAULT_START_TIME: i64 = 0;
 const DEFAULT_END_TIME: i64 = i64::MAX;
 const DEFAULT_LIMIT: i64 = 1000;
 
+/// Scans every part of `tag_name`, connecting with the [`DefaultConnector`].
+#[cfg(any(feature = "impl_tokio", feature = "impl_async_io"))]
+pub async fn scan_vertex(
+    maddr: String,
+    vspace_name: String,
+    tag_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    scan_vertex_with(&DefaultConnector::default(), maddr, vspace_name, tag_name).await
+}
 
-pub async fn scan_vertex(maddr: String,vspace_name: String,tag_name: String) -> Result<Vec<Vec<String>>, ScanError> {
-
-
-    let meta_address: Vec<&str> = maddr.split(',').collect();
-
-    let maddr = meta_address[0].to_string();
-
-    let mtransport = AsyncTransport::with_tokio_tcp_connect(
-        maddr,
-        AsyncTransportConfiguration::new(MetaTransportResponseHandler),
-    )
-    .await.map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
+pub async fn scan_vertex_with<C: Connector>(
+    connector: &C,
+    maddr: String,
+    vspace_name: String,
+    tag_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    let clients = Connect::new(connector, &maddr);
+    scan_vertex_in(&clients, vspace_name, tag_name).await
+}
 
-    // 创建meta_client
-    let mclient = MetaClient::new(mtransport);
+/// Like [`scan_vertex_with`], taking the clients from `clients`, e.g. connection pools.
+pub async fn scan_vertex_in<S: ScanClients + ?Sized>(
+    clients: &S,
+    vspace_name: String,
+    tag_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    let mclient = clients
+        .meta()
+        .await
+        .map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
 
     // 获取所需信息
     let space_name: Vec<u8> = vspace_name.into_bytes();
-    let space_id_res = mclient.get_space(space_name).await.map_err( ScanError::GetSpaceError)?;
+    let space_id_res = mclient
+        .get_space(space_name)
+        .await
+        .map_err(ScanError::GetSpaceError)?;
     let space_id = space_id_res.item.space_id;
 
-    let tag_item_res = mclient.list_tags(space_id).await.map_err( ScanError::ListTagsError)?;
+    let tag_item_res = mclient
+        .list_tags(space_id)
+        .await
+        .map_err(ScanError::ListTagsError)?;
     let ress = create_column(&tag_item_res.tags, tag_name).await?;
 
-    let part_res = mclient.get_parts(space_id).await.map_err( ScanError::GetPartsAllocError)?;
-    let mut parts_id:Vec<i32> = Vec::new();
+    let part_res = mclient
+        .get_parts(space_id)
+        .await
+        .map_err(ScanError::GetPartsAllocError)?;
+    let mut parts_id: Vec<i32> = Vec::new();
     for part_id in part_res.parts.keys() {
         parts_id.push(*part_id);
     }
-    let part_id_res = mclient.list_parts(space_id,parts_id.clone()).await.map_err( ScanError::ListPartsError)?;
-    
+    let part_id_res = mclient
+        .list_parts(space_id, parts_id.clone())
+        .await
+        .map_err(ScanError::ListPartsError)?;
+
     let result_map = get_leader_map(&part_id_res, &parts_id).await;
-    
 
-    
     let mut new_column: Vec<VertexProp> = Vec::new();
 
-    match ress {
-        Some(prop) => {
-            if let Prop::VertexProp(vertex_prop) = prop {
-                new_column.push(vertex_prop.clone()); // 或者使用引用，取决于你的需求
-            } else {
-                eprintln!("Error: VertexProp is None");
-            }
-        }
-        None => {
-            eprintln!("Error: VertexProp is None");
-        }
+    if let Some(Prop::VertexProp(vertex_prop)) = ress {
+        new_column.push(vertex_prop);
     }
 
-    let mut data_set:Vec<Vec<String>> = Vec::new();
-    
+    let mut data_set: Vec<Vec<String>> = Vec::new();
+
     for (part_id, leader) in result_map {
-        println!("Part ID: {}, Leader: {}", part_id, leader);
+        tracing::debug!(part_id, leader = %leader, "scanning part");
         let saddr = leader;
-    
-        let stransport = AsyncTransport::with_tokio_tcp_connect(
-            saddr,
-            AsyncTransportConfiguration::new(StorageTransportResponseHandler),
-        )
-        .await.map_err(|e| ScanError::Custom(format!("Storage Transport Error: {}", e)))?;
-    
-        // 创建storage_client
+
         #[allow(unused_mut)]
-        let mut sclient = StorageClient::new(stransport);
-        
+        let mut sclient = clients
+            .storage(&saddr)
+            .await
+            .map_err(|e| ScanError::Custom(format!("Storage Transport Error: {}", e)))?;
+
         //创建scan_vertex_request
         let cursor = ScanCursor {
-            next_cursor:  None, // Option为空
+            next_cursor: None, // Option为空
             ..Default::default()
-          
         };
 
         let mut part: BTreeMap<i32, ScanCursor> = BTreeMap::new();
         part.insert(part_id, cursor);
 
-        
         let scan_vertex_request = ScanVertexRequest {
-            space_id: space_id, 
-            parts: part, 
-            return_columns: new_column.clone(), 
-            limit: DEFAULT_LIMIT, 
-            start_time: Some(DEFAULT_START_TIME), 
-            end_time: Some(DEFAULT_END_TIME),  
-            filter: None, 
-            only_latest_version: false, 
-            enable_read_from_follower: true, 
-            common: None, 
+            space_id,
+            parts: part,
+            return_columns: new_column.clone(),
+            limit: DEFAULT_LIMIT,
+            start_time: Some(DEFAULT_START_TIME),
+            end_time: Some(DEFAULT_END_TIME),
+            filter: None,
+            only_latest_version: false,
+            enable_read_from_follower: true,
+            common: None,
             ..Default::default()
-            
-        }; 
-    
-    
-        let res1 = sclient.scan_vertex(&scan_vertex_request).await.map_err( ScanError::ScanVertexError)?;
+        };
+
+        let res1 = sclient
+            .scan_vertex(&scan_vertex_request)
+            .await
+            .map_err(ScanError::ScanVertexError)?;
 
         #[cfg(feature = "show_struct_result")]
-        {
-            let res2 = sclient.show_vertexs(&scan_vertex_request).await;
-            println!("{res2:?}");
-        }
-        
-     
-        let part_vertex_result : Vec<String> = deserialize_scan_response(&res1,true).map_err( ScanError::DeserializeVertexError)?;
-    
+        println!("{:?}", StorageQueryOutput::<Vertex>::new(res1.clone()));
+
+        let part_vertex_result: Vec<String> =
+            deserialize_scan_response(&res1, true).map_err(ScanError::DeserializeVertexError)?;
+
         data_set.push(part_vertex_result);
     }
 
-
     Ok(data_set)
 }
 
+/// Scans every part of `edge_name`, connecting with the [`DefaultConnector`].
+#[cfg(any(feature = "impl_tokio", feature = "impl_async_io"))]
+pub async fn scan_edge(
+    maddr: String,
+    espace_name: String,
+    edge_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    scan_edge_with(&DefaultConnector::default(), maddr, espace_name, edge_name).await
+}
 
+pub async fn scan_edge_with<C: Connector>(
+    connector: &C,
+    maddr: String,
+    espace_name: String,
+    edge_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    let clients = Connect::new(connector, &maddr);
+    scan_edge_in(&clients, espace_name, edge_name).await
+}
 
-
-
-pub async fn scan_edge(maddr:String,espace_name:String,edge_name:String) -> Result<Vec<Vec<String>>, ScanError> {
-
-    let meta_address: Vec<&str> = maddr.split(',').collect();
-
-    let maddr = meta_address[0].to_string();
-    
-    let mtransport = AsyncTransport::with_tokio_tcp_connect(
-        maddr,
-        AsyncTransportConfiguration::new(MetaTransportResponseHandler),
-    )
-    .await.map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
-
-
-    // 创建meta_client
-    let mclient = MetaClient::new(mtransport);
+/// Like [`scan_edge_with`], taking the clients from `clients`, e.g. connection pools.
+pub async fn scan_edge_in<S: ScanClients + ?Sized>(
+    clients: &S,
+    espace_name: String,
+    edge_name: String,
+) -> Result<Vec<Vec<String>>, ScanError> {
+    let mclient = clients
+        .meta()
+        .await
+        .map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
 
     // 获取所需信息
     let space_name: Vec<u8> = espace_name.into_bytes();
-    let space_id_res = mclient.get_space(space_name).await.map_err( ScanError::GetSpaceError)?;
+    let space_id_res = mclient
+        .get_space(space_name)
+        .await
+        .map_err(ScanError::GetSpaceError)?;
     let space_id = space_id_res.item.space_id;
 
-    let edge_item_res = mclient.list_edges(space_id).await.map_err( ScanError::ListEdgesError)?;
+    let edge_item_res = mclient
+        .list_edges(space_id)
+        .await
+        .map_err(ScanError::ListEdgesError)?;
     let ress = create_column(&edge_item_res.edges, edge_name).await?;
 
-    let part_res = mclient.get_parts(space_id).await.map_err( ScanError::GetPartsAllocError)?;
-    let mut parts_id:Vec<i32> = Vec::new();
+    let part_res = mclient
+        .get_parts(space_id)
+        .await
+        .map_err(ScanError::GetPartsAllocError)?;
+    let mut parts_id: Vec<i32> = Vec::new();
     for part_id in part_res.parts.keys() {
         parts_id.push(*part_id);
     }
-    let part_id_res = mclient.list_parts(space_id,parts_id.clone()).await.map_err( ScanError::ListPartsError)?;
+    let part_id_res = mclient
+        .list_parts(space_id, parts_id.clone())
+        .await
+        .map_err(ScanError::ListPartsError)?;
     let result_map = get_leader_map(&part_id_res, &parts_id).await;
-    
+
     let mut new_column: Vec<EdgeProp> = Vec::new();
 
-    match ress {
-        Some(prop) => {
-            if let Prop::EdgeProp(edge_prop) = prop {
-                new_column.push(edge_prop.clone()); 
-            } else {
-                eprintln!("Error: VertexProp is None");
-            }
-        }
-        None => {
-            eprintln!("Error: VertexProp is None");
-        }
+    if let Some(Prop::EdgeProp(edge_prop)) = ress {
+        new_column.push(edge_prop);
     }
 
-    let mut data_set:Vec<Vec<String>> = Vec::new();
+    let mut data_set: Vec<Vec<String>> = Vec::new();
 
     for (part_id, leader) in result_map {
-        println!("Part ID: {}, Leader: {}", part_id, leader);
+        tracing::debug!(part_id, leader = %leader, "scanning part");
         let saddr = leader;
-    
-        let stransport = AsyncTransport::with_tokio_tcp_connect(
-            saddr,
-            AsyncTransportConfiguration::new(StorageTransportResponseHandler),
-        )
-        .await.map_err(|e| ScanError::Custom(format!("Storage Transport Error: {}", e)))?;
-    
-        // 创建storage_client
+
         #[allow(unused_mut)]
-        let mut sclient = StorageClient::new(stransport);
-        
+        let mut sclient = clients
+            .storage(&saddr)
+            .await
+            .map_err(|e| ScanError::Custom(format!("Storage Transport Error: {}", e)))?;
+
         //创建scan_vertex_request
         let cursor = ScanCursor {
-            next_cursor:  None, // Option为空
+            next_cursor: None, // Option为空
             ..Default::default()
-          
         };
 
         let mut part: BTreeMap<i32, ScanCursor> = BTreeMap::new();
         part.insert(part_id, cursor);
 
-
         let scan_edge_request = ScanEdgeRequest {
-            space_id: space_id,  
-            parts: part,  
-            return_columns: new_column.clone(),  
-            limit: DEFAULT_LIMIT,  
-            start_time: Some(DEFAULT_START_TIME),  
-            end_time: Some(DEFAULT_END_TIME),    
-            filter: None,     
-            only_latest_version: false,  
-            enable_read_from_follower: true,  
-            common: None, 
+            space_id,
+            parts: part,
+            return_columns: new_column.clone(),
+            limit: DEFAULT_LIMIT,
+            start_time: Some(DEFAULT_START_TIME),
+            end_time: Some(DEFAULT_END_TIME),
+            filter: None,
+            only_latest_version: false,
+            enable_read_from_follower: true,
+            common: None,
             ..Default::default()
         };
-    
-        
-    
-        let res = sclient.scan_edge(&scan_edge_request).await.map_err( ScanError::ScanEdgeError)?;
+
+        let res = sclient
+            .scan_edge(&scan_edge_request)
+            .await
+            .map_err(ScanError::ScanEdgeError)?;
 
         #[cfg(feature = "show_struct_result")]
-        {
-            let res2 = sclient.show_edges(&scan_edge_request).await;
-            println!("{res2:?}");
-        }
+        println!("{:?}", StorageQueryOutput::<Edge>::new(res.clone()));
+
+        let part_edge_result: Vec<String> =
+            deserialize_scan_response(&res, false).map_err(ScanError::DeserializeEdgeError)?;
 
-        
-    
-        let part_edge_result : Vec<String> = deserialize_scan_response(&res,false).map_err( ScanError::DeserializeEdgeError)?;
-    
         data_set.push(part_edge_result);
     }
 
-
     Ok(data_set)
 }
 
+/// Where scans get their metad and storaged clients, see [`scan_vertex_in`].
+#[async_trait]
+pub trait ScanClients: Send + Sync {
+    async fn meta(&self) -> Result<Box<dyn MetaHandle + '_>, IoError>;
+
+    /// The storaged at `addr`, leading the part being scanned.
+    async fn storage(&self, addr: &str) -> Result<Box<dyn StorageHandle + '_>, IoError>;
+}
+
+/// Opens new connections for every scan, to the first of the comma separated metad addresses.
+struct Connect<'a, C> {
+    connector: &'a C,
+    maddr: String,
+}
+
+impl<'a, C> Connect<'a, C> {
+    fn new(connector: &'a C, maddr: &str) -> Self {
+        let maddr = maddr.split(',').next().unwrap_or_default().to_owned();
+        Self { connector, maddr }
+    }
+}
+
+#[async_trait]
+impl<C: Connector> ScanClients for Connect<'_, C> {
+    async fn meta(&self) -> Result<Box<dyn MetaHandle + '_>, IoError> {
+        let transport = self
+            .connector
+            .transport(
+                &self.maddr,
+                AsyncTransportConfiguration::new(MetaTransportResponseHandler),
+            )
+            .await?;
+
+        Ok(Box::new(
+            MetaClient::new(transport).with_addr(self.maddr.clone()),
+        ))
+    }
+
+    async fn storage(&self, addr: &str) -> Result<Box<dyn StorageHandle + '_>, IoError> {
+        let transport = self
+            .connector
+            .transport(
+                addr,
+                AsyncTransportConfiguration::new(StorageTransportResponseHandler),
+            )
+            .await?;
+
+        Ok(Box::new(StorageClient::new(transport).with_addr(addr)))
+    }
+}
+
 pub trait CommonProp {
     fn get_props(&self) -> Vec<Vec<u8>>;
     fn get_name(&self) -> Vec<u8>;
@@ -282,7 +354,12 @@ impl CommonProp for TagItem {
 
 impl CommonProp for EdgeItem {
     fn get_props(&self) -> Vec<Vec<u8>> {
-        let mut props: Vec<Vec<u8>> = vec![b"_src".to_vec(), b"_type".to_vec(), b"_rank".to_vec(), b"_dst".to_vec()];
+        let mut props: Vec<Vec<u8>> = vec![
+            b"_src".to_vec(),
+            b"_type".to_vec(),
+            b"_rank".to_vec(),
+            b"_dst".to_vec(),
+        ];
 
         for column_def in &self.schema.columns {
             props.push(column_def.name.clone());
@@ -290,7 +367,7 @@ impl CommonProp for EdgeItem {
 
         props
     }
-    fn get_name(&self) -> Vec<u8>  {
+    fn get_name(&self) -> Vec<u8> {
         self.edge_name.clone()
     }
 
@@ -309,24 +386,19 @@ impl CommonProp for EdgeItem {
     }
 }
 
-
 #[derive(Debug)]
 pub enum Prop {
     VertexProp(VertexProp),
     EdgeProp(EdgeProp),
 }
 
-
-pub async fn create_column<T>(
-    items: &Vec<T>,
-    target_name: String,
-) -> Result<Option<Prop>, ScanError>
+pub async fn create_column<T>(items: &[T], target_name: String) -> Result<Option<Prop>, ScanError>
 where
     T: CommonProp + 'static,
 {
     for item in items.iter() {
         let name_str = String::from_utf8(item.get_name().clone())
-        .map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
+            .map_err(|e| ScanError::Custom(format!("Meta Transport Error: {}", e)))?;
         if name_str == target_name {
             let props = item.get_props();
 
@@ -353,10 +425,10 @@ where
     Err(ScanError::CreateColumnError)
 }
 
-
-
-
-pub async fn get_leader_map(part_id_res: &ListPartsResp, parts_id: &Vec<i32>) -> BTreeMap<i32, String> {
+pub async fn get_leader_map(
+    part_id_res: &ListPartsResp,
+    parts_id: &[i32],
+) -> BTreeMap<i32, String> {
     let mut leader_map = BTreeMap::new();
 
     for part_id in parts_id {
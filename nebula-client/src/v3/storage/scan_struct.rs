@@ -2,16 +2,15 @@ use core::time::Duration;
 
 use async_trait::async_trait;
 
-
 use nebula_fbthrift_storage::v3::{
     errors::graph_storage_service::{ScanEdgeError, ScanVertexError},
-    types::{ScanEdgeRequest,ScanVertexRequest,ScanResponse},
+    types::{ScanEdgeRequest, ScanResponse, ScanVertexRequest},
 };
 
-use serde::de::DeserializeOwned;
 use deserialize_nebula_fbthrift::v3::de::{
     data::DataDeserializeError, deserialize_scan_struct_response,
 };
+use serde::de::DeserializeOwned;
 
 #[async_trait]
 pub trait StorageQuery {
@@ -25,16 +24,20 @@ pub trait StorageQuery {
         &mut self,
         req: &ScanEdgeRequest,
     ) -> Result<StorageQueryOutput<D>, StorageQueryError>;
- 
-    async fn show_vertexs(&mut self,req: &ScanVertexRequest) -> Result<StorageQueryOutput<Vertex>, StorageQueryError> {
+
+    async fn show_vertexs(
+        &mut self,
+        req: &ScanVertexRequest,
+    ) -> Result<StorageQueryOutput<Vertex>, StorageQueryError> {
         self.query_vertex(req).await
     }
-    
-    async fn show_edges(&mut self,req: &ScanEdgeRequest) -> Result<StorageQueryOutput<Edge>, StorageQueryError> {
-        self.query_edge(req).await
-    }   
-
 
+    async fn show_edges(
+        &mut self,
+        req: &ScanEdgeRequest,
+    ) -> Result<StorageQueryOutput<Edge>, StorageQueryError> {
+        self.query_edge(req).await
+    }
 }
 
 #[derive(Debug)]
@@ -55,10 +58,7 @@ where
         let data_set = deserialize_scan_struct_response::<D>(&res)
             .map_err(StorageQueryError::DataDeserializeError)?;
 
-        Ok(Self {
-            latency,
-            data_set,
-        })
+        Ok(Self { latency, data_set })
     }
 }
 
@@ -109,10 +109,6 @@ pub struct Vertex {
     pub age: i64,
 }
 
-
-
-
-
 #[derive(Deserialize, Debug)]
 pub struct Edge {
     #[serde(rename(deserialize = "serve._src"))]
@@ -128,5 +124,3 @@ pub struct Edge {
     #[serde(rename(deserialize = "serve.end_year"))]
     pub end_year: i64,
 }
-
-
@@ -1,12 +1,11 @@
-use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind};
+use std::io::Error as IoError;
 
-use fbthrift::{
-    binary_protocol::BinaryProtocolDeserializer, ApplicationException, Deserialize, MessageType,
-    ProtocolReader,
-};
+use fbthrift::{ApplicationException, Deserialize, MessageType, ProtocolReader};
 use fbthrift_transport_response_handler::ResponseHandler;
 use nebula_fbthrift_storage::v3::services::graph_storage_service::{ScanEdgeExn, ScanVertexExn};
 
+use crate::v3::protocol::frame_len;
+
 #[derive(Clone)]
 pub struct StorageTransportResponseHandler;
 
@@ -19,95 +18,100 @@ impl ResponseHandler for StorageTransportResponseHandler {
     ) -> Result<Option<Vec<u8>>, IoError> {
         match fn_name {
             b"GraphStorageService.scanVertex" | b"GraphStorageService.scanEdge" => Ok(None),
-            _ => Err(IoError::new(
-                IoErrorKind::Other,
-                format!("Unknown method {}", String::from_utf8_lossy(fn_name)),
-            )),
+            _ => Err(IoError::other(format!(
+                "Unknown method {}",
+                String::from_utf8_lossy(fn_name)
+            ))),
         }
     }
 
     fn parse_response_bytes(&mut self, response_bytes: &[u8]) -> Result<Option<usize>, IoError> {
-        let mut des = BinaryProtocolDeserializer::new(Cursor::new(response_bytes));
-        let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
-
-        match &name[..] {
-            b"scanVertex" | b"scanEdge" => {}
-            _ => return Ok(None),
-        };
-        
-        match message_type {
-            MessageType::Reply => {
-                match &name[..] {
-                    b"scanVertex" => {
-                        let _: ScanVertexExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    b"scanEdge" => {
-                        let _: ScanEdgeExn = match Deserialize::read(&mut des) {
-                            Ok(v) => v,
-                            Err(_) => return Ok(None),
-                        };
-                    }
-                    _ => unreachable!(),
-                };
-            }
-            MessageType::Exception => {
-                let _: ApplicationException = match Deserialize::read(&mut des) {
-                    Ok(v) => v,
-                    Err(_) => return Ok(None),
-                };
-            }
-            MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
-        }
+        Ok(frame_len(response_bytes, parse_response, parse_response))
+    }
+}
 
-        match des.read_message_end() {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
+fn parse_response<D: ProtocolReader>(des: &mut D) -> Option<()> {
+    let (name, message_type, _) = match des.read_message_begin(|v| v.to_vec()) {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
 
-        Ok(Some(des.into_inner().position() as usize))
+    match &name[..] {
+        b"scanVertex" | b"scanEdge" => {}
+        _ => return None,
+    };
+
+    match message_type {
+        MessageType::Reply => {
+            match &name[..] {
+                b"scanVertex" => {
+                    let _: ScanVertexExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                b"scanEdge" => {
+                    let _: ScanEdgeExn = match Deserialize::read(des) {
+                        Ok(v) => v,
+                        Err(_) => return None,
+                    };
+                }
+                _ => unreachable!(),
+            };
+        }
+        MessageType::Exception => {
+            let _: ApplicationException = match Deserialize::read(des) {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+        }
+        MessageType::Call | MessageType::Oneway | MessageType::InvalidMessageType => {}
     }
+
+    match des.read_message_end() {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
+
+    Some(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::io::ErrorKind as IoErrorKind;
+
     #[test]
     fn test_try_make_static_response_bytes() -> Result<(), Box<dyn std::error::Error>> {
         let mut handler = StorageTransportResponseHandler;
 
         assert_eq!(
             handler.try_make_static_response_bytes(
-                b"StorageService",
-                b"StorageService.scanVertex",
+                b"GraphStorageService",
+                b"GraphStorageService.scanVertex",
                 b"FOO"
             )?,
             None
         );
         assert_eq!(
             handler.try_make_static_response_bytes(
-                b"StorageService",
-                b"StorageService.scanEdge",
+                b"GraphStorageService",
+                b"GraphStorageService.scanEdge",
                 b"FOO"
             )?,
             None
         );
         match handler.try_make_static_response_bytes(
-            b"StorageService",
-            b"StorageService.foo",
+            b"GraphStorageService",
+            b"GraphStorageService.foo",
             b"FOO",
         ) {
             Ok(_) => panic!(),
             Err(err) => {
                 assert_eq!(err.kind(), IoErrorKind::Other);
 
-                assert_eq!(err.to_string(), "Unknown method StorageService.foo");
+                assert_eq!(err.to_string(), "Unknown method GraphStorageService.foo");
             }
         }
 
@@ -0,0 +1,443 @@
+//! TLS for graphd, metad and storaged connections, on top of any [`Connector`].
+//!
+//! ```ignore
+//! use nebula_client::v3::{connector::TokioConnector, tls::{TlsConfig, TlsConnector}, GraphClient};
+//!
+//! let tls = TlsConfig::default()
+//!     .with_ca_file("ca.pem")
+//!     .with_client_cert("client.pem", "client.key");
+//! let connector = TlsConnector::new(TokioConnector, &tls)?;
+//! let client = GraphClient::connect(&connector, "graphd:9669").await?;
+//! ```
+
+use core::fmt;
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use futures_rustls::{client::TlsStream, TlsConnector as RustlsConnector};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+
+use crate::v3::Connector;
+
+//
+//
+//
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// PEM CA certificates to trust. The Mozilla roots from `webpki-roots` when `None`.
+    pub ca_file: Option<PathBuf>,
+    /// PEM certificate chain presented for mutual TLS, with `client_key_file`.
+    pub client_cert_file: Option<PathBuf>,
+    pub client_key_file: Option<PathBuf>,
+    /// Verified against the server certificate instead of the host being dialed.
+    pub server_name: Option<String>,
+    /// Accepts any server certificate. Only for development.
+    pub insecure: bool,
+}
+
+impl TlsConfig {
+    pub fn with_ca_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_file = Some(path.into());
+        self
+    }
+
+    pub fn with_client_cert(
+        mut self,
+        cert_file: impl Into<PathBuf>,
+        key_file: impl Into<PathBuf>,
+    ) -> Self {
+        self.client_cert_file = Some(cert_file.into());
+        self.client_key_file = Some(key_file.into());
+        self
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn with_insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
+    /// Reads the certificate files.
+    pub fn client_config(&self) -> Result<ClientConfig, IoError> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(IoError::other)?;
+
+        let builder = if self.insecure {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerifier(provider)))
+        } else {
+            let mut roots = RootCertStore::empty();
+            match &self.ca_file {
+                Some(path) => {
+                    for cert in certs(path)? {
+                        roots.add(cert).map_err(|e| pem_error(path, e))?;
+                    }
+                }
+                None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+            }
+            builder.with_root_certificates(roots)
+        };
+
+        match (&self.client_cert_file, &self.client_key_file) {
+            (Some(cert_file), Some(key_file)) => {
+                let key =
+                    PrivateKeyDer::from_pem_file(key_file).map_err(|e| pem_error(key_file, e))?;
+                builder
+                    .with_client_auth_cert(certs(cert_file)?, key)
+                    .map_err(IoError::other)
+            }
+            (None, None) => Ok(builder.with_no_client_auth()),
+            _ => Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "client_cert_file and client_key_file must be set together",
+            )),
+        }
+    }
+}
+
+fn certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, IoError> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|x| x.collect())
+        .map_err(|e| pem_error(path, e))
+}
+
+fn pem_error(path: &Path, err: impl fmt::Display) -> IoError {
+    IoError::new(
+        IoErrorKind::InvalidData,
+        format!("{}: {}", path.display(), err),
+    )
+}
+
+//
+//
+//
+/// Wraps the streams of another connector in TLS.
+#[derive(Clone)]
+pub struct TlsConnector<C> {
+    inner: C,
+    connector: RustlsConnector,
+    server_name: Option<ServerName<'static>>,
+}
+
+impl<C> TlsConnector<C> {
+    pub fn new(inner: C, config: &TlsConfig) -> Result<Self, IoError> {
+        Self::with_client_config(inner, config.client_config()?)
+            .with_server_name(config.server_name.as_deref())
+    }
+
+    /// For settings [`TlsConfig`] does not cover.
+    pub fn with_client_config(inner: C, client_config: ClientConfig) -> Self {
+        Self {
+            inner,
+            connector: RustlsConnector::from(Arc::new(client_config)),
+            server_name: None,
+        }
+    }
+
+    fn with_server_name(mut self, server_name: Option<&str>) -> Result<Self, IoError> {
+        self.server_name = server_name.map(parse_server_name).transpose()?;
+        Ok(self)
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for TlsConnector<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("inner", &self.inner)
+            .field("server_name", &self.server_name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<C> Connector for TlsConnector<C>
+where
+    C: Connector,
+{
+    type Stream = TlsStream<C::Stream>;
+    type Sleep = C::Sleep;
+
+    async fn connect(&self, addr: &str) -> Result<Self::Stream, IoError> {
+        let server_name = match &self.server_name {
+            Some(server_name) => server_name.clone(),
+            None => parse_server_name(host(addr))?,
+        };
+        let stream = self.inner.connect(addr).await?;
+        self.connector.connect(server_name, stream).await
+    }
+
+    fn is_tls(&self) -> bool {
+        true
+    }
+}
+
+fn host(addr: &str) -> &str {
+    let host = match addr.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => host,
+        _ => addr,
+    };
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn parse_server_name(name: &str) -> Result<ServerName<'static>, IoError> {
+    ServerName::try_from(name.to_owned()).map_err(|e| IoError::new(IoErrorKind::InvalidInput, e))
+}
+
+//
+//
+//
+#[derive(Debug)]
+struct NoVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, net::SocketAddr, process};
+
+    use async_compat::Compat;
+    use fbthrift_transport::impl_tokio::tcp_connect;
+    use futures::{future::try_join, io::copy, AsyncReadExt as _};
+    use futures_rustls::TlsAcceptor;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::{server::WebPkiClientVerifier, ServerConfig};
+    use tokio::net::TcpListener;
+
+    use crate::v3::{
+        connector::TokioConnector,
+        mock::{MockGraph, Reply},
+        GraphClient, GraphQuery as _,
+    };
+
+    struct Pki {
+        dir: PathBuf,
+        ca: CertificateDer<'static>,
+        server: (CertificateDer<'static>, PrivateKeyDer<'static>),
+    }
+
+    impl Pki {
+        fn new(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+            let dir = env::temp_dir().join(format!("nebula-rs-tls-{}-{name}", process::id()));
+            fs::create_dir_all(&dir)?;
+
+            let ca_key = KeyPair::generate()?;
+            let mut ca_params = CertificateParams::new(vec![])?;
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = ca_params.self_signed(&ca_key)?;
+            fs::write(dir.join("ca.pem"), ca.pem())?;
+
+            let server_key = KeyPair::generate()?;
+            let server = CertificateParams::new(vec!["graphd.nebula.test".to_owned()])?.signed_by(
+                &server_key,
+                &ca,
+                &ca_key,
+            )?;
+
+            let client_key = KeyPair::generate()?;
+            let client = CertificateParams::new(vec!["client".to_owned()])?.signed_by(
+                &client_key,
+                &ca,
+                &ca_key,
+            )?;
+            fs::write(dir.join("client.pem"), client.pem())?;
+            fs::write(dir.join("client.key"), client_key.serialize_pem())?;
+
+            Ok(Self {
+                dir,
+                ca: ca.der().clone(),
+                server: (
+                    server.der().clone(),
+                    PrivateKeyDer::try_from(server_key.serialize_der())?,
+                ),
+            })
+        }
+
+        /// Terminates TLS, requiring a client certificate, and forwards to `backend`.
+        async fn proxy(
+            &self,
+            backend: SocketAddr,
+        ) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+            let provider = Arc::new(ring::default_provider());
+            let mut roots = RootCertStore::empty();
+            roots.add(self.ca.clone())?;
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .build()?;
+            let config = ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()?
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(vec![self.server.0.clone()], self.server.1.clone_key())?;
+            let acceptor = TlsAcceptor::from(Arc::new(config));
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let acceptor = acceptor.clone();
+                    tokio::spawn(async move {
+                        let tls = acceptor.accept(Compat::new(stream)).await?;
+                        let backend = tcp_connect(backend).await?;
+                        let (mut tls_r, mut tls_w) = tls.split();
+                        let (mut backend_r, mut backend_w) = backend.split();
+                        try_join(
+                            copy(&mut tls_r, &mut backend_w),
+                            copy(&mut backend_r, &mut tls_w),
+                        )
+                        .await
+                    });
+                }
+            });
+
+            Ok(addr)
+        }
+
+        fn config(&self) -> TlsConfig {
+            TlsConfig::default()
+                .with_ca_file(self.dir.join("ca.pem"))
+                .with_server_name("graphd.nebula.test")
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    async fn show_hosts(
+        connector: &impl Connector,
+        addr: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut session = GraphClient::connect(connector, addr)
+            .await?
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        session.show_hosts().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_mutual_tls() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on("SHOW HOSTS", Reply::Response(Default::default()));
+        let server = mock.serve().await?;
+        let pki = Pki::new("mutual")?;
+        let addr = pki.proxy(server.addr()).await?.to_string();
+
+        let config = pki
+            .config()
+            .with_client_cert(pki.dir.join("client.pem"), pki.dir.join("client.key"));
+        let connector = TlsConnector::new(TokioConnector, &config)?;
+        assert!(connector.is_tls());
+        show_hosts(&connector, &addr).await?;
+        mock.assert_received("SHOW HOSTS");
+
+        // Without a client certificate the server refuses the handshake.
+        let connector = TlsConnector::new(TokioConnector, &pki.config())?;
+        assert!(show_hosts(&connector, &addr).await.is_err());
+
+        // The test CA is not among the default roots.
+        let config = TlsConfig {
+            ca_file: None,
+            ..config
+        };
+        let connector = TlsConnector::new(TokioConnector, &config)?;
+        assert!(show_hosts(&connector, &addr).await.is_err());
+
+        // Insecure skips verifying the server, so the name and CA do not matter.
+        let config = config.with_server_name("localhost").with_insecure(true);
+        let connector = TlsConnector::new(TokioConnector, &config)?;
+        show_hosts(&connector, &addr).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config() {
+        let err = TlsConfig {
+            client_cert_file: Some("client.pem".into()),
+            ..Default::default()
+        }
+        .client_config()
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), IoErrorKind::InvalidInput);
+
+        let err = TlsConfig::default()
+            .with_ca_file("/nonexistent/ca.pem")
+            .client_config()
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("/nonexistent/ca.pem: "));
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("graphd:9669"), "graphd");
+        assert_eq!(host("127.0.0.1:9669"), "127.0.0.1");
+        assert_eq!(host("[::1]:9669"), "::1");
+        assert_eq!(host("[::1]"), "::1");
+        assert_eq!(host("graphd"), "graphd");
+    }
+}
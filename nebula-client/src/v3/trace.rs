@@ -0,0 +1,134 @@
+//! Settings for the `tracing` spans emitted by the clients.
+//!
+//! Every graph, meta and storage call runs inside a `nebula.graph`,
+//! `nebula.meta` or `nebula.storage` span whose `method` field names the RPC.
+//! Spans carry the peer address (see `with_addr` on the clients), the session
+//! id or space, the server-side latency and the returned error code. Statements are recorded through [`TraceConfig`] so
+//! that long or sensitive ones can be cut or masked.
+
+//
+#[derive(Debug, Clone)]
+pub struct TraceConfig {
+    /// Record the statement on graph spans at all.
+    pub record_statement: bool,
+    /// Statements longer than this many characters are cut and suffixed with `...`.
+    pub max_statement_len: Option<usize>,
+    /// Replace the content of every quoted string literal with `***`.
+    pub redact_literals: bool,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            record_statement: true,
+            max_statement_len: Some(1024),
+            redact_literals: false,
+        }
+    }
+}
+
+impl TraceConfig {
+    pub fn statement(&self, stmt: &[u8]) -> String {
+        if !self.record_statement {
+            return String::new();
+        }
+
+        let stmt = String::from_utf8_lossy(stmt);
+        let stmt = if self.redact_literals {
+            redact_literals(&stmt)
+        } else {
+            stmt.into_owned()
+        };
+
+        match self.max_statement_len {
+            Some(max) if stmt.chars().count() > max => {
+                let mut s: String = stmt.chars().take(max).collect();
+                s.push_str("...");
+                s
+            }
+            _ => stmt,
+        }
+    }
+}
+
+fn redact_literals(stmt: &str) -> String {
+    let mut out = String::with_capacity(stmt.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in stmt.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    out.push_str("***");
+                    out.push(c);
+                    quote = None;
+                }
+            }
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                out.push(c);
+            }
+        }
+    }
+
+    // Unterminated literal.
+    if quote.is_some() {
+        out.push_str("***");
+    }
+
+    out
+}
+
+#[cfg(feature = "graph")]
+pub(crate) fn lossy(bytes: &Option<Vec<u8>>) -> String {
+    bytes
+        .as_ref()
+        .map(|x| String::from_utf8_lossy(x).to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement() {
+        let stmt = br#"CREATE USER foo WITH PASSWORD "p\"w";"#;
+
+        assert_eq!(
+            TraceConfig::default().statement(stmt),
+            r#"CREATE USER foo WITH PASSWORD "p\"w";"#
+        );
+        assert_eq!(
+            TraceConfig {
+                redact_literals: true,
+                ..Default::default()
+            }
+            .statement(stmt),
+            r#"CREATE USER foo WITH PASSWORD "***";"#
+        );
+        assert_eq!(
+            TraceConfig {
+                max_statement_len: Some(6),
+                ..Default::default()
+            }
+            .statement(stmt),
+            "CREATE..."
+        );
+        assert_eq!(
+            TraceConfig {
+                record_statement: false,
+                ..Default::default()
+            }
+            .statement(stmt),
+            ""
+        );
+    }
+}
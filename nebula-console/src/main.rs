@@ -0,0 +1,446 @@
+/*
+cargo run -p nebula-console -- --addr 127.0.0.1:9669 --user root
+cargo run -p nebula-console -- --space basketballplayer -e 'MATCH (v:player) RETURN v LIMIT 3' --format csv
+cargo run -p nebula-console -- --space basketballplayer -f script.ngql
+*/
+
+use std::{
+    env, fs,
+    io::{self, Write as _},
+    process,
+    time::Instant,
+};
+
+use fbthrift_transport::{
+    impl_tokio::{TokioSleep, TokioTcpStream},
+    AsyncTransport, AsyncTransportConfiguration,
+};
+use nebula_client::v3::{
+    graph::script::{split_complete, split_statements},
+    GraphClient, GraphSession, GraphTransportResponseHandler,
+};
+use nebula_fbthrift_graph::v3::dependencies::common::types::ErrorCode;
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+mod output;
+use output::Format;
+
+type Session =
+    GraphSession<AsyncTransport<TokioTcpStream, TokioSleep, GraphTransportResponseHandler>>;
+
+const USAGE: &str = "\
+Usage: nebula-console [options]
+
+Options:
+    --addr <host:port>   graphd address [env NEBULA_ADDR, default 127.0.0.1:9669]
+    --user <user>        [env NEBULA_USER, default root]
+    --password <pass>    [env NEBULA_PASSWORD, prompted for when unset]
+    --space <space>      space to USE after connecting
+    --format <format>    table, csv or json [default table]
+    -e <statements>      run the statements and exit
+    -f <file>            run the script file and exit
+    -h, --help           print this help";
+
+const HELP: &str = "\
+Statements end with `;` and may span several lines.
+
+    :use <space>                  switch space
+    :format <table|csv|json>      output format
+    :timing <on|off>              print row counts and latency
+    :source <file>                run a script file
+    :help                         this help
+    :exit, :quit                  leave";
+
+#[derive(Debug)]
+struct Args {
+    addr: String,
+    user: String,
+    password: Option<String>,
+    space: Option<String>,
+    format: Format,
+    eval: Option<String>,
+    file: Option<String>,
+}
+
+struct Console {
+    session: Session,
+    user: String,
+    format: Format,
+    timing: bool,
+}
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Returns false when a statement of `-e` / `-f` failed.
+async fn run(args: Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let password = match args.password.clone() {
+        Some(password) => password,
+        None => rpassword::prompt_password("Password: ").map_err(|err| {
+            format!("no --password or NEBULA_PASSWORD, and no terminal to ask: {err}")
+        })?,
+    };
+
+    let transport = AsyncTransport::with_tokio_tcp_connect(
+        args.addr.clone(),
+        AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+    )
+    .await?;
+    let client = GraphClient::new(transport).with_addr(args.addr.clone());
+
+    let session = client
+        .authenticate(
+            &args.user.as_bytes().to_vec(),
+            &password.as_bytes().to_vec(),
+        )
+        .await?;
+
+    let mut console = Console {
+        session,
+        user: args.user.clone(),
+        format: args.format,
+        timing: args.eval.is_none() && args.file.is_none(),
+    };
+
+    if let Some(space) = args.space.as_ref() {
+        if !console.execute(&format!("USE `{space}`")).await {
+            return Ok(false);
+        }
+    }
+
+    let ok = if let Some(eval) = args.eval.as_ref() {
+        console.run_script(eval).await
+    } else if let Some(file) = args.file.as_ref() {
+        console.run_script(&fs::read_to_string(file)?).await
+    } else {
+        console.repl().await?;
+        true
+    };
+
+    console.session.signout().await?;
+
+    Ok(ok)
+}
+
+impl Console {
+    async fn repl(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut editor = DefaultEditor::new()?;
+        let history = env::var("HOME")
+            .map(|home| format!("{home}/.nebula_rs_history"))
+            .ok();
+        if let Some(history) = history.as_ref() {
+            let _ = editor.load_history(history);
+        }
+
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.trim().is_empty() {
+                format!("({}@nebula) [{}]> ", self.user, self.space())
+            } else {
+                "-> ".to_owned()
+            };
+
+            let line = match editor.readline(&prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            if buffer.trim().is_empty() && line.trim_start().starts_with(':') {
+                let _ = editor.add_history_entry(line.trim());
+                match self.meta_command(line.trim()).await {
+                    Flow::Continue => continue,
+                    Flow::Exit => break,
+                }
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+
+            let (statements, rest) = split_complete(&buffer);
+            if statements.is_empty() {
+                continue;
+            }
+            let _ = editor.add_history_entry(buffer[..buffer.len() - rest.len()].trim());
+
+            for statement in statements {
+                self.execute(&statement).await;
+            }
+            buffer = rest.to_owned();
+        }
+
+        if let Some(history) = history.as_ref() {
+            let _ = editor.save_history(history);
+        }
+
+        Ok(())
+    }
+
+    async fn meta_command(&mut self, line: &str) -> Flow {
+        let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = arg.trim().trim_end_matches(';').trim();
+
+        match (command, arg) {
+            (":exit" | ":quit", _) => return Flow::Exit,
+            (":help", _) => println!("{HELP}"),
+            (":use", space) if !space.is_empty() => {
+                self.execute(&format!("USE `{}`", space.trim_matches('`')))
+                    .await;
+            }
+            (":format", format) => match format.parse() {
+                Ok(format) => self.format = format,
+                Err(err) => eprintln!("{err}"),
+            },
+            (":timing", "on") => self.timing = true,
+            (":timing", "off") => self.timing = false,
+            (":source", file) if !file.is_empty() => match fs::read_to_string(file) {
+                Ok(script) => {
+                    self.run_script(&script).await;
+                }
+                Err(err) => eprintln!("{file}: {err}"),
+            },
+            _ => eprintln!("unknown command {line}, try :help"),
+        }
+
+        Flow::Continue
+    }
+
+    /// Stops at the first failed statement.
+    async fn run_script(&mut self, script: &str) -> bool {
+        for statement in split_statements(script) {
+            if !self.execute(&statement).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Prints the result or the error, returns whether the statement succeeded.
+    async fn execute(&mut self, statement: &str) -> bool {
+        let now = Instant::now();
+        let res = match self.session.execute(&statement.as_bytes().to_vec()).await {
+            Ok(res) => res,
+            Err(err) => {
+                eprintln!("[ERROR]: {err}");
+                return false;
+            }
+        };
+        let elapsed = now.elapsed();
+
+        if res.error_code != ErrorCode::SUCCEEDED {
+            eprintln!(
+                "[ERROR ({})]: {}",
+                res.error_code,
+                res.error_msg
+                    .as_ref()
+                    .map(|x| String::from_utf8_lossy(x).into_owned())
+                    .unwrap_or_default()
+            );
+            return false;
+        }
+
+        let mut stdout = io::stdout().lock();
+        let mut rows = None;
+        let written = match res.data.as_ref() {
+            Some(data_set) => {
+                rows = Some(data_set.rows.len());
+                output::write_data_set(&mut stdout, data_set, self.format)
+            }
+            None => Ok(()),
+        }
+        .and_then(|_| match res.plan_desc.as_ref() {
+            Some(plan) => output::write_plan(&mut stdout, plan),
+            None => Ok(()),
+        });
+        if let Err(err) = written {
+            eprintln!("[ERROR]: {err}");
+            return false;
+        }
+
+        if self.timing {
+            let spent = format!(
+                "time spent {}us/{}us",
+                res.latency_in_us,
+                elapsed.as_micros()
+            );
+            let _ = match rows {
+                Some(rows) => writeln!(stdout, "Got {rows} rows ({spent})\n"),
+                None => writeln!(stdout, "Execution succeeded ({spent})\n"),
+            };
+        }
+        let _ = stdout.flush();
+
+        true
+    }
+
+    fn space(&self) -> String {
+        self.session
+            .space_name()
+            .map(|x| String::from_utf8_lossy(x).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// `None` for `-h` / `--help`.
+fn parse_args(mut iter: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = Args {
+        addr: env::var("NEBULA_ADDR").unwrap_or_else(|_| "127.0.0.1:9669".to_owned()),
+        user: env::var("NEBULA_USER").unwrap_or_else(|_| "root".to_owned()),
+        password: env::var("NEBULA_PASSWORD").ok(),
+        space: None,
+        format: Format::Table,
+        eval: None,
+        file: None,
+    };
+
+    while let Some(flag) = iter.next() {
+        if flag == "-h" || flag == "--help" {
+            return Ok(None);
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("missing value for {flag}"))?;
+        match flag.as_str() {
+            "--addr" => args.addr = value,
+            "--user" => args.user = value,
+            "--password" => args.password = Some(value),
+            "--space" => args.space = Some(value),
+            "--format" => args.format = value.parse()?,
+            "-e" => args.eval = Some(value),
+            "-f" => args.file = Some(value),
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    Ok(Some(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_client::v3::mock::{MockGraph, Reply};
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() -> Result<(), String> {
+        assert!(parse(&["-h"])?.is_none());
+        assert!(parse(&["--space", "nba", "--help"])?.is_none());
+
+        let args = parse(&[
+            "--addr",
+            "graphd:9669",
+            "--user",
+            "tim",
+            "--password",
+            "secret",
+            "--space",
+            "nba",
+            "--format",
+            "csv",
+            "-e",
+            "SHOW HOSTS",
+        ])?
+        .expect("not help");
+        assert_eq!(args.addr, "graphd:9669");
+        assert_eq!(args.user, "tim");
+        assert_eq!(args.password.as_deref(), Some("secret"));
+        assert_eq!(args.space.as_deref(), Some("nba"));
+        assert_eq!(args.format, Format::Csv);
+        assert_eq!(args.eval.as_deref(), Some("SHOW HOSTS"));
+        assert_eq!(args.file, None);
+
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--space"]).is_err());
+        assert!(parse(&["--port", "9669"]).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_meta_commands() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockGraph::new();
+        mock.on("SHOW HOSTS", Reply::rows(&["Host"], vec![]));
+        let server = mock.serve().await?;
+
+        let transport = AsyncTransport::with_tokio_tcp_connect(
+            server.addr().to_string(),
+            AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+        )
+        .await?;
+        let session = GraphClient::new(transport)
+            .authenticate(&b"root".to_vec(), &b"nebula".to_vec())
+            .await?;
+        let mut console = Console {
+            session,
+            user: "root".to_owned(),
+            format: Format::Table,
+            timing: false,
+        };
+
+        assert!(matches!(
+            console.meta_command(":use `nba`;").await,
+            Flow::Continue
+        ));
+        mock.assert_received("USE `nba`");
+        assert_eq!(console.space(), "nba");
+
+        console.meta_command(":format csv").await;
+        assert_eq!(console.format, Format::Csv);
+        console.meta_command(":format xml").await;
+        assert_eq!(console.format, Format::Csv);
+
+        console.meta_command(":timing on").await;
+        assert!(console.timing);
+        console.meta_command(":timing off").await;
+        assert!(!console.timing);
+
+        let script = env::temp_dir().join(format!("nebula-console-{}.ngql", process::id()));
+        fs::write(&script, "SHOW HOSTS;\nSHOW SPACES;\nSHOW HOSTS;\n")?;
+        mock.clear_received();
+        console
+            .meta_command(&format!(":source {}", script.display()))
+            .await;
+        fs::remove_file(&script)?;
+        // Stops at SHOW SPACES, which the mock has no reply for.
+        assert_eq!(mock.statements(), vec!["SHOW HOSTS", "SHOW SPACES"]);
+
+        assert!(matches!(console.meta_command(":quit").await, Flow::Exit));
+
+        Ok(())
+    }
+}
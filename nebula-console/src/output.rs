@@ -0,0 +1,258 @@
+use std::{
+    collections::BTreeMap,
+    io::{Error as IoError, Write},
+    str::FromStr,
+};
+
+use deserialize_nebula_fbthrift::v3::{
+    export::{CsvWriter, JsonLinesWriter},
+    table::DisplayTable as _,
+};
+use nebula_fbthrift_graph::v3::{
+    dependencies::common::types::{DataSet, Row, Value},
+    types::{PlanDescription, PlanNodeDescription},
+};
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "json" | "jsonl" => Ok(Self::Json),
+            _ => Err(format!("unknown format {s}, expected table, csv or json")),
+        }
+    }
+}
+
+pub fn write_data_set<W: Write>(
+    w: &mut W,
+    data_set: &DataSet,
+    format: Format,
+) -> Result<(), IoError> {
+    match format {
+        Format::Table => write!(w, "{}", data_set.table()),
+        Format::Csv => {
+            let mut csv = CsvWriter::new(w);
+            csv.write_data_set(data_set)?;
+            csv.flush()
+        }
+        Format::Json => {
+            let mut jsonl = JsonLinesWriter::new(w);
+            jsonl.write_data_set(data_set)?;
+            jsonl.flush()
+        }
+    }
+}
+
+/// Renders `EXPLAIN` / `PROFILE` plans, as a table for `format="row"` and as Graphviz for `format="dot"`.
+pub fn write_plan<W: Write>(w: &mut W, plan: &PlanDescription) -> Result<(), IoError> {
+    writeln!(
+        w,
+        "Execution Plan (optimize time {} us)",
+        plan.optimize_time_in_us
+    )?;
+
+    if plan.format.starts_with(b"dot") {
+        return write_plan_dot(w, plan);
+    }
+
+    let column_names: Vec<Vec<u8>> = [
+        "id",
+        "name",
+        "dependencies",
+        "profiling data",
+        "operator info",
+    ]
+    .iter()
+    .map(|x| x.as_bytes().to_vec())
+    .collect();
+    let rows: Vec<Row> = plan
+        .plan_node_descs
+        .iter()
+        .map(|node| Row {
+            values: [
+                node.id.to_string(),
+                String::from_utf8_lossy(&node.name).into_owned(),
+                dependencies(node).join(","),
+                profiles(node),
+                description(node),
+            ]
+            .into_iter()
+            .map(|x| Value::sVal(x.into_bytes()))
+            .collect(),
+            ..Default::default()
+        })
+        .collect();
+    let data_set = DataSet {
+        column_names,
+        rows,
+        ..Default::default()
+    };
+
+    write!(w, "{}", data_set.table().max_column_width(None))
+}
+
+fn write_plan_dot<W: Write>(w: &mut W, plan: &PlanDescription) -> Result<(), IoError> {
+    let ids: BTreeMap<i64, String> = plan
+        .plan_node_descs
+        .iter()
+        .map(|node| {
+            (
+                node.id,
+                format!("{}_{}", String::from_utf8_lossy(&node.name), node.id),
+            )
+        })
+        .collect();
+
+    writeln!(w, "digraph exec_plan {{")?;
+    writeln!(w, "    rankdir=BT;")?;
+    for node in plan.plan_node_descs.iter() {
+        let label = format!("{}\n{}", ids[&node.id], description(node));
+        writeln!(
+            w,
+            "    \"{}\"[label=\"{}\"];",
+            ids[&node.id],
+            label
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\l")
+        )?;
+    }
+    for node in plan.plan_node_descs.iter() {
+        for dep in node.dependencies.iter().flatten() {
+            if let Some(dep) = ids.get(dep) {
+                writeln!(w, "    \"{dep}\"->\"{}\";", ids[&node.id])?;
+            }
+        }
+    }
+    writeln!(w, "}}")
+}
+
+fn dependencies(node: &PlanNodeDescription) -> Vec<String> {
+    node.dependencies
+        .iter()
+        .flatten()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn profiles(node: &PlanNodeDescription) -> String {
+    node.profiles
+        .iter()
+        .flatten()
+        .map(|x| {
+            format!(
+                "rows: {}, execTime: {}us, totalTime: {}us",
+                x.rows, x.exec_duration_in_us, x.total_duration_in_us
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn description(node: &PlanNodeDescription) -> String {
+    let mut lines = vec![format!(
+        "outputVar: {}",
+        String::from_utf8_lossy(&node.output_var)
+    )];
+    for pair in node.description.iter().flatten() {
+        lines.push(format!(
+            "{}: {}",
+            String::from_utf8_lossy(&pair.key),
+            String::from_utf8_lossy(&pair.value)
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nebula_fbthrift_graph::v3::types::{Pair, ProfilingStats};
+
+    fn plan(format: &str) -> PlanDescription {
+        PlanDescription {
+            plan_node_descs: vec![
+                PlanNodeDescription {
+                    name: b"Start".to_vec(),
+                    id: 0,
+                    output_var: b"__Start_0".to_vec(),
+                    ..Default::default()
+                },
+                PlanNodeDescription {
+                    name: b"Project".to_vec(),
+                    id: 1,
+                    output_var: b"__Project_1".to_vec(),
+                    description: Some(vec![Pair {
+                        key: b"columns".to_vec(),
+                        value: b"[\"1\"]".to_vec(),
+                        ..Default::default()
+                    }]),
+                    profiles: Some(vec![ProfilingStats {
+                        rows: 1,
+                        exec_duration_in_us: 2,
+                        total_duration_in_us: 3,
+                        ..Default::default()
+                    }]),
+                    dependencies: Some(vec![0]),
+                    ..Default::default()
+                },
+            ],
+            optimize_time_in_us: 7,
+            format: format.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_plan() -> Result<(), IoError> {
+        let mut buf = vec![];
+        write_plan(&mut buf, &plan("row"))?;
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            r#"Execution Plan (optimize time 7 us)
++----+---------+--------------+----------------------------------------+------------------------+
+| id | name    | dependencies | profiling data                         | operator info          |
++----+---------+--------------+----------------------------------------+------------------------+
+| 0  | Start   |              |                                        | outputVar: __Start_0   |
+| 1  | Project | 0            | rows: 1, execTime: 2us, totalTime: 3us | outputVar: __Project_1 |
+|    |         |              |                                        | columns: ["1"]         |
++----+---------+--------------+----------------------------------------+------------------------+
+"#
+        );
+
+        let mut buf = vec![];
+        write_plan(&mut buf, &plan("dot"))?;
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            r#"Execution Plan (optimize time 7 us)
+digraph exec_plan {
+    rankdir=BT;
+    "Start_0"[label="Start_0\loutputVar: __Start_0"];
+    "Project_1"[label="Project_1\loutputVar: __Project_1\lcolumns: [\"1\"]"];
+    "Start_0"->"Project_1";
+}
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("table".parse(), Ok(Format::Table));
+        assert_eq!("jsonl".parse(), Ok(Format::Json));
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! `#[derive(NebulaTag)]` and `#[derive(NebulaEdge)]`.
+//!
+//! Use them through `nebula_client::v3::record` (feature `derive`), which holds
+//! the traits and documents the attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, Data, DeriveInput, Error, Fields, Ident, LitStr,
+};
+
+#[proc_macro_derive(NebulaTag, attributes(nebula))]
+pub fn derive_nebula_tag(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, Kind::Tag)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(NebulaEdge, attributes(nebula))]
+pub fn derive_nebula_edge(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input, Kind::Edge)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+//
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Tag,
+    Edge,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Prop,
+    Vid,
+    Src,
+    Dst,
+    Rank,
+    Skip,
+}
+
+struct Field {
+    ident: Ident,
+    role: Role,
+    prop: String,
+}
+
+fn expand(input: DeriveInput, kind: Kind) -> Result<TokenStream2, Error> {
+    let name = parse_container(&input, kind)?;
+    let fields = parse_fields(&input, kind)?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let find = |role: Role| fields.iter().find(|x| x.role == role);
+    let missing = |attr: &str| {
+        Error::new(
+            Span::call_site(),
+            format!("one field must be marked with #[nebula({attr})]"),
+        )
+    };
+
+    let props: Vec<&Field> = fields.iter().filter(|x| x.role == Role::Prop).collect();
+    let prop_names: Vec<&str> = props.iter().map(|x| x.prop.as_str()).collect();
+    let field_names: Vec<String> = props.iter().map(|x| unraw(&x.ident)).collect();
+    let prop_idents: Vec<&Ident> = props.iter().map(|x| &x.ident).collect();
+
+    let body = match kind {
+        Kind::Tag => {
+            let vid = find(Role::Vid).ok_or_else(|| missing("vid"))?;
+            let vid_ident = &vid.ident;
+            let vid_field = unraw(vid_ident);
+
+            quote! {
+                impl #impl_generics ::nebula_client::v3::record::NebulaTag for #ident #ty_generics #where_clause {
+                    const TAG: &'static str = #name;
+                    const VID_FIELD: &'static str = #vid_field;
+                    const PROPS: &'static [&'static str] = &[#(#prop_names),*];
+                    const FIELDS: &'static [&'static str] = &[#(#field_names),*];
+
+                    fn vid(&self) -> ::std::string::String {
+                        ::nebula_client::v3::record::ToNgql::to_ngql(&self.#vid_ident)
+                    }
+
+                    fn prop_values(&self) -> ::std::vec::Vec<::std::string::String> {
+                        ::std::vec![#(::nebula_client::v3::record::ToNgql::to_ngql(&self.#prop_idents)),*]
+                    }
+                }
+            }
+        }
+        Kind::Edge => {
+            let src = find(Role::Src).ok_or_else(|| missing("src"))?;
+            let dst = find(Role::Dst).ok_or_else(|| missing("dst"))?;
+            let (src_ident, dst_ident) = (&src.ident, &dst.ident);
+            let (src_field, dst_field) = (unraw(src_ident), unraw(dst_ident));
+
+            let (rank_field, rank) = match find(Role::Rank) {
+                Some(rank) => {
+                    let rank_ident = &rank.ident;
+                    let rank_field = unraw(rank_ident);
+                    (
+                        quote!(::core::option::Option::Some(#rank_field)),
+                        quote!(::core::convert::Into::<i64>::into(self.#rank_ident)),
+                    )
+                }
+                None => (quote!(::core::option::Option::None), quote!(0)),
+            };
+
+            quote! {
+                impl #impl_generics ::nebula_client::v3::record::NebulaEdge for #ident #ty_generics #where_clause {
+                    const EDGE: &'static str = #name;
+                    const SRC_FIELD: &'static str = #src_field;
+                    const DST_FIELD: &'static str = #dst_field;
+                    const RANK_FIELD: ::core::option::Option<&'static str> = #rank_field;
+                    const PROPS: &'static [&'static str] = &[#(#prop_names),*];
+                    const FIELDS: &'static [&'static str] = &[#(#field_names),*];
+
+                    fn src(&self) -> ::std::string::String {
+                        ::nebula_client::v3::record::ToNgql::to_ngql(&self.#src_ident)
+                    }
+
+                    fn dst(&self) -> ::std::string::String {
+                        ::nebula_client::v3::record::ToNgql::to_ngql(&self.#dst_ident)
+                    }
+
+                    fn rank(&self) -> i64 {
+                        #rank
+                    }
+
+                    fn prop_values(&self) -> ::std::vec::Vec<::std::string::String> {
+                        ::std::vec![#(::nebula_client::v3::record::ToNgql::to_ngql(&self.#prop_idents)),*]
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(body)
+}
+
+/// `#[nebula(tag = "player")]` / `#[nebula(edge = "serve")]`, defaulting to the snake_case type name.
+fn parse_container(input: &DeriveInput, kind: Kind) -> Result<String, Error> {
+    let key = match kind {
+        Kind::Tag => "tag",
+        Kind::Edge => "edge",
+    };
+
+    let mut name = None;
+    for attr in input.attrs.iter().filter(|x| x.path().is_ident("nebula")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                name = Some(parse_name(&meta)?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("expected `{key} = \"...\"`")))
+            }
+        })?;
+    }
+
+    Ok(name.unwrap_or_else(|| snake_case(&input.ident.to_string())))
+}
+
+fn parse_fields(input: &DeriveInput, kind: Kind) -> Result<Vec<Field>, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "only structs with named fields are supported",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            ))
+        }
+    };
+
+    let mut out: Vec<Field> = Vec::with_capacity(fields.len());
+    for field in fields.iter() {
+        let ident = field.ident.clone().expect("named field");
+        let mut role = Role::Prop;
+        let mut prop = unraw(&ident);
+
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("nebula")) {
+            attr.parse_nested_meta(|meta| {
+                let (new_role, allowed) = if meta.path.is_ident("vid") {
+                    (Role::Vid, kind == Kind::Tag)
+                } else if meta.path.is_ident("src") {
+                    (Role::Src, kind == Kind::Edge)
+                } else if meta.path.is_ident("dst") {
+                    (Role::Dst, kind == Kind::Edge)
+                } else if meta.path.is_ident("rank") {
+                    (Role::Rank, kind == Kind::Edge)
+                } else if meta.path.is_ident("skip") {
+                    (Role::Skip, true)
+                } else if meta.path.is_ident("rename") {
+                    prop = parse_name(&meta)?;
+                    return Ok(());
+                } else {
+                    return Err(meta.error("unknown nebula attribute"));
+                };
+
+                if !allowed {
+                    return Err(meta.error("not allowed here"));
+                }
+                if out
+                    .iter()
+                    .any(|x| x.role == new_role && new_role != Role::Skip)
+                {
+                    return Err(meta.error("only one field can have this attribute"));
+                }
+                role = new_role;
+                Ok(())
+            })?;
+        }
+
+        out.push(Field { ident, role, prop });
+    }
+
+    Ok(out)
+}
+
+/// A tag, edge or property name; it is quoted with backticks in statements.
+fn parse_name(meta: &ParseNestedMeta) -> Result<String, Error> {
+    let lit = meta.value()?.parse::<LitStr>()?;
+    let name = lit.value();
+    if name.is_empty() || name.contains('`') {
+        return Err(Error::new_spanned(
+            lit,
+            "name must be non-empty and without '`'",
+        ));
+    }
+    Ok(name)
+}
+
+/// The field name as serde sees it, without the `r#` of raw identifiers.
+fn unraw(ident: &Ident) -> String {
+    ident.to_string().trim_start_matches("r#").to_owned()
+}
+
+fn snake_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -3,7 +3,7 @@
 
 #![recursion_limit = "100000000"]
 #![allow(bare_trait_objects)]
-#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, unused_crate_dependencies, clippy::all)]
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, unused_crate_dependencies, unused_imports, clippy::all)]
 
 pub use self::consts::*;
 pub use self::errors::*;
@@ -0,0 +1,177 @@
+/*
+cargo run -p nebula-migrate -- status --space basketballplayer --dir migrations
+cargo run -p nebula-migrate -- migrate --space basketballplayer --dir migrations --dry-run
+cargo run -p nebula-migrate -- migrate --addr 127.0.0.1:9669 --user root --password nebula --space basketballplayer --dir migrations --wait 20
+*/
+
+use std::{env, process, time::Duration};
+
+use fbthrift_transport::{AsyncTransport, AsyncTransportConfiguration};
+use nebula_client::v3::{
+    migrate::{load_dir, MigrationState, Migrator, MigratorConfig},
+    GraphClient, GraphTransportResponseHandler,
+};
+
+const USAGE: &str = "\
+Usage: nebula-migrate <status|migrate> --space <space> [options]
+
+Options:
+    --addr <host:port>   graphd address [env NEBULA_ADDR, default 127.0.0.1:9669]
+    --user <user>        [env NEBULA_USER, default root]
+    --password <pass>    [env NEBULA_PASSWORD, default nebula]
+    --space <space>      [env NEBULA_SPACE]
+    --dir <dir>          directory of V<version>__<name>.ngql files [default migrations]
+    --wait <secs>        wait after schema changes, at least the heartbeat interval
+                         [env NEBULA_MIGRATE_WAIT, default 20]
+    --dry-run            only print the statements migrate would run";
+
+#[derive(Debug)]
+struct Args {
+    command: String,
+    addr: String,
+    user: String,
+    password: String,
+    space: String,
+    dir: String,
+    wait: Duration,
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(args).await {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let migrations = load_dir(&args.dir)?;
+
+    //
+    let transport = AsyncTransport::with_tokio_tcp_connect(
+        args.addr.clone(),
+        AsyncTransportConfiguration::new(GraphTransportResponseHandler),
+    )
+    .await?;
+    let client = GraphClient::new(transport).with_addr(args.addr.clone());
+
+    let mut session = client
+        .authenticate(
+            &args.user.as_bytes().to_vec(),
+            &args.password.as_bytes().to_vec(),
+        )
+        .await?;
+
+    // Signed out on errors too, so the session does not linger on graphd.
+    let res: Result<(), Box<dyn std::error::Error>> = async {
+        let config = MigratorConfig::default()
+            .with_propagation_wait(args.wait)
+            .with_dry_run(args.dry_run);
+        let mut migrator = Migrator::with_config(&mut session, args.space.clone(), config);
+
+        match args.command.as_str() {
+            "status" => {
+                for status in migrator.status(&migrations).await? {
+                    let state = match status.state {
+                        MigrationState::Pending => "pending".to_owned(),
+                        MigrationState::Applied { applied_at } => {
+                            format!("applied at {applied_at}")
+                        }
+                        MigrationState::ChecksumMismatch { .. } => {
+                            "applied, but the file changed since".to_owned()
+                        }
+                    };
+                    println!("V{} {} {state}", status.version, status.name);
+                }
+            }
+            "migrate" => {
+                let applied = migrator.migrate(&migrations).await?;
+                if applied.is_empty() {
+                    println!("{} is up to date", args.space);
+                }
+                for migration in applied {
+                    if args.dry_run {
+                        println!("V{} {} would run:", migration.version, migration.name);
+                        for statement in migration.statements.iter() {
+                            println!("    {statement};");
+                        }
+                    } else {
+                        println!("V{} {} applied", migration.version, migration.name);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+    .await;
+    let signout = session.signout().await;
+
+    res?;
+    signout?;
+    Ok(())
+}
+
+fn parse_args(mut iter: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = iter.next().ok_or("missing command")?;
+    if command != "status" && command != "migrate" {
+        return Err(format!("unknown command {command}"));
+    }
+
+    let mut args = Args {
+        command,
+        addr: env::var("NEBULA_ADDR").unwrap_or_else(|_| "127.0.0.1:9669".to_owned()),
+        user: env::var("NEBULA_USER").unwrap_or_else(|_| "root".to_owned()),
+        password: env::var("NEBULA_PASSWORD").unwrap_or_else(|_| "nebula".to_owned()),
+        space: env::var("NEBULA_SPACE").unwrap_or_default(),
+        dir: "migrations".to_owned(),
+        wait: match env::var("NEBULA_MIGRATE_WAIT") {
+            Ok(value) => parse_wait(&value)?,
+            Err(_) => Duration::from_secs(20),
+        },
+        dry_run: false,
+    };
+
+    while let Some(flag) = iter.next() {
+        if flag == "--dry-run" {
+            args.dry_run = true;
+            continue;
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("missing value for {flag}"))?;
+        match flag.as_str() {
+            "--addr" => args.addr = value,
+            "--user" => args.user = value,
+            "--password" => args.password = value,
+            "--space" => args.space = value,
+            "--dir" => args.dir = value,
+            "--wait" => args.wait = parse_wait(&value)?,
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    if args.space.is_empty() {
+        return Err("missing --space".to_owned());
+    }
+
+    Ok(args)
+}
+
+fn parse_wait(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("invalid --wait {value}"))
+}